@@ -0,0 +1,129 @@
+use bevy::{
+    core::Name,
+    ecs::{entity::EntityMap, reflect::AppTypeRegistry, world::World},
+    reflect::Reflect,
+    render::view::{ComputedVisibility, Visibility},
+    transform::components::{GlobalTransform, Transform},
+};
+use bevy_nursery::prefab::{
+    clone_compact, write_to_world, write_to_world_with, Patch, Prefab, PrefabEntity, WriteOptions,
+};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+const ENTITIES: u32 = 10_000;
+
+fn registry() -> AppTypeRegistry {
+    let registry = AppTypeRegistry::default();
+    registry.write().register::<Transform>();
+    registry.write().register::<GlobalTransform>();
+    registry.write().register::<Visibility>();
+    registry.write().register::<ComputedVisibility>();
+    registry.write().register::<Name>();
+    registry
+}
+
+/// A prefab with dynamic values, as produced by deserialization.
+fn prefab() -> Prefab {
+    let entities = (0..ENTITIES).map(|entity| {
+        PrefabEntity::new(
+            entity,
+            vec![Transform::from_xyz(entity as f32, 0.0, 0.0).clone_value()],
+        )
+    });
+    Prefab {
+        entities: entities.collect(),
+    }
+}
+
+fn spawn(c: &mut Criterion) {
+    let registry = registry();
+    let dynamic = prefab();
+    let mut compact = prefab();
+    compact.compact(&registry.read());
+
+    let mut group = c.benchmark_group("write_to_world");
+    for (name, prefab) in [("dynamic", &dynamic), ("compact", &compact)] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut world = World::new();
+                    world.insert_resource(registry.clone());
+                    world
+                },
+                |mut world| {
+                    let mut entity_map = EntityMap::default();
+                    write_to_world(&Patch::default(), prefab, &mut world, &mut entity_map).unwrap();
+                    world
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// A compact prefab with several components per entity.
+fn bundles() -> Prefab {
+    let entities = (0..ENTITIES).map(|entity| {
+        PrefabEntity::new(
+            entity,
+            vec![
+                Box::new(Transform::from_xyz(entity as f32, 0.0, 0.0)),
+                Box::new(GlobalTransform::default()),
+                Box::new(Visibility::default()),
+                Box::new(ComputedVisibility::default()),
+                Box::new(Name::new(entity.to_string())),
+            ],
+        )
+    });
+    Prefab {
+        entities: entities.collect(),
+    }
+}
+
+fn batch(c: &mut Criterion) {
+    let registry = registry();
+    let prefab = bundles();
+
+    let mut group = c.benchmark_group("batch_inserts");
+    for (name, batch_inserts) in [("batched", true), ("one_by_one", false)] {
+        let options = WriteOptions {
+            batch_inserts,
+            ..Default::default()
+        };
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || {
+                    let mut world = World::new();
+                    world.insert_resource(registry.clone());
+                    world
+                },
+                |mut world| {
+                    let mut entity_map = EntityMap::default();
+                    let patch = Patch::default();
+                    write_to_world_with(&patch, &prefab, &mut world, &mut entity_map, &options)
+                        .unwrap();
+                    world
+                },
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn clone(c: &mut Criterion) {
+    let registry = registry();
+    let registry = registry.read();
+    let value = Transform::from_xyz(1.0, 2.0, 3.0).clone_value();
+
+    let mut group = c.benchmark_group("clone_component");
+    group.bench_function("clone_value", |b| b.iter(|| value.clone_value()));
+    group.bench_function("clone_compact", |b| {
+        b.iter(|| clone_compact(value.as_ref(), &registry))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, spawn, batch, clone);
+criterion_main!(benches);
@@ -0,0 +1,106 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, LitStr, PathArguments,
+    Type,
+};
+
+/// Derive `PrefabPatch` for a struct of `Option<T>` fields, see `bevy_nursery::prefab::PrefabPatch`.
+///
+/// Fields are components overriding the ones of the entity, or component fields
+/// with `#[prefab_patch(component = Health, path = "max")]`.
+#[proc_macro_derive(PrefabPatch, attributes(prefab_patch))]
+pub fn derive_prefab_patch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "`PrefabPatch` can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(
+            &data.fields,
+            "`PrefabPatch` needs named fields",
+        ));
+    };
+
+    let mut writes = Vec::new();
+    for field in &fields.named {
+        let ident = &field.ident;
+        if !is_option(&field.ty) {
+            return Err(Error::new_spanned(
+                &field.ty,
+                "fields of a `PrefabPatch` must be `Option<T>`",
+            ));
+        }
+
+        let mut component: Option<Type> = None;
+        let mut path: Option<LitStr> = None;
+        for attr in &field.attrs {
+            if !attr.path().is_ident("prefab_patch") {
+                continue;
+            }
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("component") {
+                    component = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("path") {
+                    path = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `component` or `path`"))
+                }
+            })?;
+        }
+
+        writes.push(match (component, path) {
+            (Some(component), Some(path)) => quote! {
+                if let Some(value) = &self.#ident {
+                    entity.set_field::<#component>(#path, value);
+                }
+            },
+            (None, None) => quote! {
+                if let Some(value) = &self.#ident {
+                    entity.append_component(value);
+                }
+            },
+            _ => {
+                return Err(Error::new_spanned(
+                    field,
+                    "`component` and `path` must be given together",
+                ))
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::bevy_nursery::prefab::PrefabPatch for #name #ty_generics #where_clause {
+            fn patch_entity(&self, entity: &mut ::bevy_nursery::prefab::PatchEntity) {
+                #(#writes)*
+            }
+        }
+    })
+}
+
+fn is_option(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    let Some(segment) = path.path.segments.last() else {
+        return false;
+    };
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return false;
+    };
+    segment.ident == "Option" && matches!(arguments.args.first(), Some(GenericArgument::Type(_)))
+}
@@ -1 +1,4 @@
+// Lets `#[derive(PrefabPatch)]` name this crate from its own tests.
+extern crate self as bevy_nursery;
+
 pub mod prefab;
@@ -1,27 +1,157 @@
 use super::{
     builder::PrefabBuilder,
-    serde::{PrefabDeserializer, PrefabSerializer},
+    diagnostics::{PrefabLoadLog, PrefabLoadRecord},
+    id::PrefabUuid,
+    lazy::split_entry,
+    migrate::PrefabMigrations,
+    serde::{LabeledPrefabsDeserializer, PrefabDeserializer, PrefabSerializer},
+    stream::split_map,
+    PrefabError, SerializedComponent,
 };
 use bevy::{
     asset::{AssetLoader, BoxedFuture, Error, LoadContext, LoadedAsset},
     ecs::reflect::AppTypeRegistry,
-    ecs::world::{EntityMut, FromWorld, World},
-    reflect::{FromType, Reflect, TypePath, TypeRegistryArc, TypeUuid},
-    utils::{HashMap, HashSet},
+    ecs::system::Resource,
+    ecs::world::{EntityMut, EntityRef, FromWorld, World},
+    log::info_span,
+    reflect::{FromType, Reflect, TypePath, TypeRegistryArc, TypeRegistryInternal, TypeUuid},
+    utils::{HashMap, HashSet, Instant},
 };
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
-#[derive(Default)]
+/// Changes applied on top of a prefab when spawning it.
+///
+/// Patches are also assets, an instance spawned with
+/// [`PrefabSpawner::spawn_with_patch_asset`](super::PrefabSpawner::spawn_with_patch_asset)
+/// is spawned again when its patch is modified.
+#[derive(Default, TypeUuid, TypePath)]
+#[uuid = "9e3b6a0f-2c41-4d8e-a7f5-61b0c2d94e17"]
 pub struct Patch {
     pub path: String,
     pub modify: Vec<PatchEntity>,
     pub ignore: HashSet<u32>,
 }
 
+impl Clone for Patch {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            modify: self.modify.clone(),
+            ignore: self.ignore.clone(),
+        }
+    }
+}
+
+impl Patch {
+    /// Merge `other` into this patch, its changes win over the ones already here.
+    pub fn merge(&mut self, other: &Patch) {
+        self.ignore.extend(other.ignore.iter().copied());
+        for patch in &other.modify {
+            let Some(entry) = self
+                .modify
+                .iter_mut()
+                .find(|e| e.entity == patch.entity && e.uuid == patch.uuid && e.name == patch.name)
+            else {
+                self.modify.push(patch.clone());
+                continue;
+            };
+
+            entry
+                .append
+                .extend(patch.append.iter().map(|c| c.clone_value()));
+            for (type_name, fields) in &patch.modify {
+                let entry = entry.modify.entry(type_name.clone()).or_default();
+                for (path, value) in fields {
+                    entry.insert(path.clone(), value.clone_value());
+                }
+            }
+            entry.remove.extend(patch.remove.iter().cloned());
+            if patch.parent.is_some() {
+                entry.parent = patch.parent;
+            }
+        }
+    }
+}
+
 pub struct PatchEntity {
     pub entity: u32,
+    /// Target the entity with this [`PrefabUuid`] instead of `entity`, when the prefab has it.
+    ///
+    /// Unlike ids, uuids survive extracting the world again, see [`PrefabUuid`].
+    pub uuid: Option<PrefabUuid>,
+    /// Target the first entity with this [`Name`](bevy::core::Name) instead of `entity`,
+    /// when the prefab has it and no entity has the `uuid`.
+    ///
+    /// Makes hand-written patches robust against prefabs exported again.
+    pub name: Option<String>,
     pub append: Vec<Box<dyn Reflect>>,
     pub modify: HashMap<String, HashMap<String, Box<dyn Reflect>>>,
     pub remove: HashSet<String>,
+    /// Move the entity under another parent, or make it a root of the instance.
+    pub parent: Option<PatchParent>,
+}
+
+/// Where [`PatchEntity::parent`] moves an entity in the hierarchy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatchParent {
+    /// Attach the entity to the prefab entity with this id.
+    Entity(u32),
+    /// Detach the entity from its parent in the prefab.
+    ///
+    /// Like other roots, it is attached to the parent of the instance when it has one.
+    Root,
+}
+
+impl PatchEntity {
+    /// An empty patch of the prefab entity with this id.
+    pub fn new(entity: u32) -> Self {
+        Self {
+            entity,
+            uuid: None,
+            name: None,
+            append: Vec::new(),
+            modify: HashMap::default(),
+            remove: HashSet::default(),
+            parent: None,
+        }
+    }
+
+    /// Set a field of the component of type `C`, the path isn't checked.
+    ///
+    /// See [`PatchBuilder`](super::PatchBuilder) to check it against the registry.
+    pub fn set_field<C: 'static>(&mut self, path: &str, value: &impl Reflect) {
+        let type_name = std::any::type_name::<C>().to_string();
+        let fields = self.modify.entry(type_name).or_default();
+        fields.insert(path.to_string(), value.clone_value());
+    }
+
+    /// Append a component, it replaces the one of the prefab entity if it has one.
+    pub fn append_component(&mut self, component: &impl Reflect) {
+        self.append.push(component.clone_value());
+    }
+}
+
+impl Clone for PatchEntity {
+    fn clone(&self) -> Self {
+        let modify = self.modify.iter().map(|(type_name, fields)| {
+            let fields = fields
+                .iter()
+                .map(|(path, value)| (path.clone(), value.clone_value()));
+            (type_name.clone(), fields.collect())
+        });
+
+        Self {
+            entity: self.entity,
+            uuid: self.uuid,
+            name: self.name.clone(),
+            append: self.append.iter().map(|c| c.clone_value()).collect(),
+            modify: modify.collect(),
+            remove: self.remove.clone(),
+            parent: self.parent,
+        }
+    }
 }
 
 #[derive(Default, TypeUuid, TypePath)]
@@ -58,26 +188,123 @@ impl Prefab {
             &mut ron::de::Deserializer::from_bytes(input)?,
         )
     }
+
+    /// Deserialize several prefabs from one rust object notation (ron) file, with their labels.
+    ///
+    /// The file maps labels to prefabs: `{ "Barrel": { 0: { ... } }, "Crate": { ... } }`.
+    /// The [`PrefabLoader`] loads them as labeled assets, like `props.prefab#Barrel`.
+    /// Versioned prefabs of such files are only migrated by the [`PrefabLoader`],
+    /// see [`PrefabMigrations`].
+    pub fn deserialize_ron_labeled(
+        input: &[u8],
+        registry: &TypeRegistryArc,
+    ) -> Result<Vec<(String, Self)>, ron::Error> {
+        let registry = &registry.read();
+        serde::de::DeserializeSeed::deserialize(
+            LabeledPrefabsDeserializer::new(registry),
+            &mut ron::de::Deserializer::from_bytes(input)?,
+        )
+    }
+
+    /// Serialize this prefab into json, for tools that can't write ron.
+    ///
+    /// Entity ids become strings, as json only has string keys.
+    pub fn serialize_json(&self, registry: &AppTypeRegistry) -> Result<String, serde_json::Error> {
+        let registry = &registry.read();
+        serde_json::to_string_pretty(&PrefabSerializer::new(self, registry))
+    }
+
+    /// Deserialize prefab from json.
+    pub fn deserialize_json(
+        input: &[u8],
+        registry: &TypeRegistryArc,
+    ) -> Result<Self, serde_json::Error> {
+        let registry = &registry.read();
+        serde::de::DeserializeSeed::deserialize(
+            PrefabDeserializer::new(registry),
+            &mut serde_json::Deserializer::from_slice(input),
+        )
+    }
+
+    /// Deserialize prefab from yaml.
+    pub fn deserialize_yaml(
+        input: &[u8],
+        registry: &TypeRegistryArc,
+    ) -> Result<Self, serde_yaml::Error> {
+        let registry = &registry.read();
+        serde::de::DeserializeSeed::deserialize(
+            PrefabDeserializer::new(registry),
+            serde_yaml::Deserializer::from_slice(input),
+        )
+    }
 }
 
 pub struct PrefabEntity {
     pub entity: u32,
     pub components: Vec<Box<dyn Reflect>>,
+    pub(crate) serialized: Vec<SerializedComponent>,
+}
+
+impl PrefabEntity {
+    pub fn new(entity: u32, components: Vec<Box<dyn Reflect>>) -> Self {
+        Self {
+            entity,
+            components,
+            serialized: Vec::new(),
+        }
+    }
+
+    /// Components deserialized on first spawn, see [`Prefab::deserialize_ron_lazy`].
+    pub fn serialized(&self) -> &[SerializedComponent] {
+        &self.serialized
+    }
 }
 
 pub trait PrefabComponent {
     fn insert(self, entity: &mut EntityMut);
+
+    /// Undo [`PrefabComponent::insert`], when a patch or an updated prefab removes the component.
+    ///
+    /// Does nothing by default.
+    fn remove(entity: &mut EntityMut)
+    where
+        Self: Sized,
+    {
+        let _ = entity;
+    }
+
+    /// Build the proxy back from the components [`PrefabComponent::insert`] inserted,
+    /// for the [`PrefabBuilder`](super::PrefabBuilder).
+    ///
+    /// Returns `None` by default, the proxy is then not extracted.
+    fn extract(entity: EntityRef) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let _ = entity;
+        None
+    }
 }
 
 #[derive(Clone)]
 pub struct ReflectPrefabComponent {
     apply_insert: fn(&mut EntityMut, &dyn Reflect),
+    apply_remove: fn(&mut EntityMut),
+    extract: fn(EntityRef) -> Option<Box<dyn Reflect>>,
 }
 
 impl ReflectPrefabComponent {
     pub fn apply_insert(&self, entity: &mut EntityMut, proxy: &dyn Reflect) {
         (self.apply_insert)(entity, proxy);
     }
+
+    pub fn apply_remove(&self, entity: &mut EntityMut) {
+        (self.apply_remove)(entity);
+    }
+
+    pub fn extract(&self, entity: EntityRef) -> Option<Box<dyn Reflect>> {
+        (self.extract)(entity)
+    }
 }
 
 impl<T: PrefabComponent + FromWorld + Reflect> FromType<T> for ReflectPrefabComponent {
@@ -88,22 +315,203 @@ impl<T: PrefabComponent + FromWorld + Reflect> FromType<T> for ReflectPrefabComp
                 proxy.apply(reflect);
                 proxy.insert(entity);
             },
+            apply_remove: |entity| T::remove(entity),
+            extract: |entity| Some(Box::new(T::extract(entity)?)),
         }
     }
 }
 
+/// A transformation applied to every prefab loaded by the [`PrefabLoader`].
+pub type PrefabHook = fn(&mut Prefab, &TypeRegistryInternal);
+
+/// Hooks run by the [`PrefabLoader`] on every loaded prefab, in registration order.
+///
+/// Use them for project-wide asset conventions like unit conversion or legacy field fixups.
+/// Hooks added after the loader was created still apply to prefabs loaded later.
+#[derive(Clone, Default, Resource)]
+pub struct PrefabLoaderHooks {
+    hooks: Arc<RwLock<Vec<PrefabHook>>>,
+}
+
+impl PrefabLoaderHooks {
+    pub fn add(&self, hook: PrefabHook) {
+        self.hooks.write().unwrap().push(hook);
+    }
+
+    /// Run all hooks on a prefab.
+    pub fn apply(&self, prefab: &mut Prefab, registry: &TypeRegistryInternal) {
+        for hook in self.hooks.read().unwrap().iter() {
+            hook(prefab, registry);
+        }
+    }
+}
+
+impl std::fmt::Debug for PrefabLoaderHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let len = self.hooks.read().map_or(0, |hooks| hooks.len());
+        f.debug_struct("PrefabLoaderHooks")
+            .field("hooks", &len)
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub struct PrefabLoader {
     registry: TypeRegistryArc,
+    hooks: PrefabLoaderHooks,
+    migrations: PrefabMigrations,
+    load_log: PrefabLoadLog,
+    lazy_components: bool,
+    strict: bool,
+}
+
+impl PrefabLoader {
+    /// Keep components of loaded prefabs serialized until they are spawned.
+    ///
+    /// Hooks only see the components that are already deserialized.
+    /// Only applies to ron prefabs, json and yaml prefabs are always deserialized when loaded.
+    pub fn with_lazy_components(mut self, lazy_components: bool) -> Self {
+        self.lazy_components = lazy_components;
+        self
+    }
+
+    /// Fail loading prefabs with components that aren't registered components.
+    ///
+    /// All of them are reported in one
+    /// [`PrefabError::UnregisteredComponents`](super::PrefabError::UnregisteredComponents) error,
+    /// instead of spawning failing on the first one, see [`Prefab::validate`].
+    /// Prefabs are checked once the hooks ran.
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Run the hooks on a loaded prefab, compact it and record its load.
+    ///
+    /// The prefab depends on the assets referenced by its components,
+    /// see [`Prefab::asset_paths`].
+    pub(super) fn finish(
+        &self,
+        mut prefab: Prefab,
+        load_context: &LoadContext,
+        label: Option<&str>,
+        start: Instant,
+        bytes: usize,
+    ) -> Result<LoadedAsset<Prefab>, Error> {
+        let registry = &self.registry.read();
+        let path = match label {
+            Some(label) => PathBuf::from(format!("{}#{}", load_context.path().display(), label)),
+            None => load_context.path().to_path_buf(),
+        };
+        self.prepare(&mut prefab, registry)
+            .map_err(|err| load_error(&path, err))?;
+        let paths = prefab.asset_paths(registry);
+        prefab.compact(registry);
+        if self.load_log.is_enabled() {
+            let record = PrefabLoadRecord::new(&path, start, &prefab, bytes);
+            self.load_log.record(record);
+        }
+        Ok(LoadedAsset::new(prefab).with_dependencies(paths))
+    }
+
+    /// Run the hooks on a read prefab, then check it if the loader is strict.
+    fn prepare(
+        &self,
+        prefab: &mut Prefab,
+        registry: &TypeRegistryInternal,
+    ) -> Result<(), PrefabError> {
+        self.hooks.apply(prefab, registry);
+        if self.strict {
+            prefab.validate(registry)?;
+        }
+        Ok(())
+    }
+
+    /// Read a ron prefab that isn't loaded from a file, migrated and checked like a loaded one,
+    /// see [`PrefabSpawner::spawn_from_ron`](super::PrefabSpawner::spawn_from_ron).
+    pub(super) fn read_unloaded(&self, bytes: &[u8]) -> Result<Prefab, Error> {
+        let mut prefab = self.read_ron(bytes)?;
+        let registry = &self.registry.read();
+        self.prepare(&mut prefab, registry)?;
+        prefab.compact(registry);
+        Ok(prefab)
+    }
+
+    /// Read a file holding a single prefab, in the format given by its extension.
+    pub(super) fn read(&self, bytes: &[u8], path: &Path) -> Result<Prefab, Error> {
+        let prefab = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Prefab::deserialize_json(bytes, &self.registry)?,
+            Some("yaml") => Prefab::deserialize_yaml(bytes, &self.registry)?,
+            _ => self.read_ron(bytes)?,
+        };
+        Ok(prefab)
+    }
+
+    /// Read a ron file of labeled prefabs, see [`Prefab::deserialize_ron_labeled`].
+    ///
+    /// Each prefab is migrated on its own, so they may have different versions:
+    /// `{ "Barrel": (version: 1, entities: { ... }), "Crate": { ... } }`.
+    pub(super) fn read_labeled(&self, bytes: &[u8]) -> Result<Vec<(String, Prefab)>, Error> {
+        let mut prefabs = Vec::new();
+        for entry in split_map(bytes)? {
+            let (label, prefab) = split_entry::<String>(std::str::from_utf8(&entry)?)?;
+            prefabs.push((label, self.read_ron(prefab.as_bytes())?));
+        }
+        Ok(prefabs)
+    }
+
+    fn read_ron(&self, bytes: &[u8]) -> Result<Prefab, ron::Error> {
+        let entities = self.migrations.migrate(bytes)?;
+        if self.lazy_components {
+            Prefab::deserialize_ron_lazy(&entities)
+        } else {
+            Prefab::deserialize_ron(&entities, &self.registry)
+        }
+    }
+
+    pub(super) fn registry(&self) -> &TypeRegistryArc {
+        &self.registry
+    }
 }
 
 impl FromWorld for PrefabLoader {
     fn from_world(world: &mut World) -> Self {
         let registry = world.resource::<AppTypeRegistry>().0.clone();
-        Self { registry }
+        let hooks = world.get_resource_or_insert_with(PrefabLoaderHooks::default);
+        let hooks = hooks.clone();
+        let migrations = world.get_resource_or_insert_with(PrefabMigrations::default);
+        let migrations = migrations.clone();
+        let load_log = world.get_resource_or_insert_with(PrefabLoadLog::default);
+        let load_log = load_log.clone();
+        Self {
+            registry,
+            hooks,
+            migrations,
+            load_log,
+            lazy_components: false,
+            strict: false,
+        }
     }
 }
 
+/// Name the file an error of a prefab loader comes from,
+/// as the asset server only logs the message of the error.
+pub(super) fn load_error(path: &Path, err: impl Into<Error>) -> Error {
+    Error::msg(format!("`{}`: {:#}", path.display(), err.into()))
+}
+
+/// Does this ron file hold labeled prefabs, see [`Prefab::deserialize_ron_labeled`]?
+///
+/// Labeled prefabs are told apart by their first key being a string.
+fn is_labeled(input: &[u8]) -> bool {
+    let mut bytes = input.iter().filter(|b| !b.is_ascii_whitespace());
+    bytes.next() == Some(&b'{') && bytes.next() == Some(&b'"')
+}
+
+/// File extensions loaded by the [`PrefabLoader`].
+pub(super) const PREFAB_EXTENSIONS: &[&str] =
+    &["prefab", "prefab.ron", "prefab.json", "prefab.yaml"];
+
 impl AssetLoader for PrefabLoader {
     fn load<'a>(
         &'a self,
@@ -111,13 +519,30 @@ impl AssetLoader for PrefabLoader {
         load_context: &'a mut LoadContext,
     ) -> BoxedFuture<'a, Result<(), Error>> {
         Box::pin(async move {
-            let prefab = Prefab::deserialize_ron(bytes, &self.registry)?;
-            load_context.set_default_asset(LoadedAsset::new(prefab));
+            let path = load_context.path().display().to_string();
+            let _span = info_span!("prefab_load", path).entered();
+            let start = Instant::now();
+            let extension = load_context.path().extension();
+            let is_ron = !matches!(extension.and_then(|e| e.to_str()), Some("json" | "yaml"));
+            if is_ron && is_labeled(bytes) {
+                let prefabs = self.read_labeled(bytes);
+                let prefabs = prefabs.map_err(|err| load_error(load_context.path(), err))?;
+                for (label, prefab) in prefabs {
+                    let asset =
+                        self.finish(prefab, load_context, Some(&label), start, bytes.len())?;
+                    load_context.set_labeled_asset(&label, asset);
+                }
+                return Ok(());
+            }
+            let prefab = self.read(bytes, load_context.path());
+            let prefab = prefab.map_err(|err| load_error(load_context.path(), err))?;
+            let asset = self.finish(prefab, load_context, None, start, bytes.len())?;
+            load_context.set_default_asset(asset);
             Ok(())
         })
     }
 
     fn extensions(&self) -> &[&str] {
-        &["prefab", "prefab.ron"]
+        PREFAB_EXTENSIONS
     }
 }
@@ -1,18 +1,61 @@
-use super::{Prefab, PrefabEntity};
+use super::{
+    write::is_hierarchy, Patch, Prefab, PrefabEntity, PrefabError, PrefabUuid,
+    ReflectPrefabComponent,
+};
 use bevy::ecs::{
-    entity::Entity,
-    reflect::{AppTypeRegistry, ReflectComponent},
-    world::World,
+    entity::{Entity, EntityMap},
+    reflect::{AppTypeRegistry, ReflectComponent, ReflectResource},
+    system::Resource,
+    world::{EntityRef, World},
 };
+use bevy::hierarchy::{Children, Parent};
+use bevy::reflect::{DynamicList, FromType, List, Reflect, ReflectMut, TypeRegistration};
 use bevy::utils::{default, HashMap};
 
+/// Type data of components that are never extracted by the [`PrefabBuilder`],
+/// like derived state or caches, added with `#[reflect(PrefabSkip)]`.
+///
+/// The [`PrefabPlugin`](struct@super::PrefabPlugin) adds it to
+/// [`GlobalTransform`](bevy::transform::components::GlobalTransform) and
+/// [`ComputedVisibility`](bevy::render::view::ComputedVisibility).
+#[derive(Clone)]
+pub struct ReflectPrefabSkip;
+
+impl<T> FromType<T> for ReflectPrefabSkip {
+    fn from_type() -> Self {
+        Self
+    }
+}
+
 /// A [`Prefab`] builder, used to build a scene from a [`World`] by extracting some entities.
+///
+/// The hierarchy between extracted entities is always kept, even if [`Parent`] and [`Children`]
+/// are not registered. References to entities that were not extracted are dropped from it.
+/// Serializing the prefab still needs them registered, like the `HierarchyPlugin` does.
+///
+/// Registered [`PrefabComponent`] proxies are extracted from the components they inserted,
+/// see [`PrefabComponent::extract`]. Those components are extracted as well
+/// if they are registered themselves.
+///
+/// [`PrefabComponent`]: super::PrefabComponent
+/// [`PrefabComponent::extract`]: super::PrefabComponent::extract
 pub struct PrefabBuilder<'w> {
     entities: HashMap<u32, PrefabEntity>,
+    /// Extracted resources, see [`PrefabBuilder::build_with_resources`].
+    resources: Vec<Box<dyn Reflect>>,
+    /// Extracted entities, mapped to themselves.
+    extracted: EntityMap,
     registry: AppTypeRegistry,
     world: &'w World,
+    uuids: bool,
+    /// See [`PrefabBuilder::with_dense_ids`].
+    dense_ids: bool,
+    /// See [`PrefabBuilder::with_component_filter`].
+    filter: Option<Box<ComponentFilter<'w>>>,
 }
 
+type ComponentFilter<'w> = dyn Fn(EntityRef, &TypeRegistration) -> bool + 'w;
+
 impl<'w> PrefabBuilder<'w> {
     /// Prepare a builder that will extract entities and their component from the given [`World`].
     /// All components registered in that world's [`AppTypeRegistry`] resource will be extracted.
@@ -26,15 +69,192 @@ impl<'w> PrefabBuilder<'w> {
     pub fn from_world_with_registry(world: &'w World, registry: AppTypeRegistry) -> Self {
         Self {
             entities: default(),
+            resources: Vec::new(),
+            extracted: default(),
             registry,
             world,
+            uuids: false,
+            dense_ids: false,
+            filter: None,
         }
     }
 
+    /// Give a new [`PrefabUuid`] to the extracted entities that don't have one yet.
+    ///
+    /// The entities of the world are left untouched, only the prefab gets the uuids.
+    pub fn with_uuids(mut self) -> Self {
+        self.uuids = true;
+        self
+    }
+
+    /// Renumber the extracted entities from 0, in the order of their ids in the world.
+    ///
+    /// References to entities inside of components are mapped like with
+    /// [`PrefabBuilder::build_mapped`], so exporting the same entities gives the same file.
+    pub fn with_dense_ids(mut self) -> Self {
+        self.dense_ids = true;
+        self
+    }
+
+    /// Only extract the components of an entity for which `filter` returns `true`.
+    ///
+    /// Unlike registering [`ReflectPrefabSkip`], the decision can depend on the entity,
+    /// like skipping `Transform` only on UI nodes. Proxies are filtered by their own registration.
+    /// The hierarchy is extracted in any case.
+    pub fn with_component_filter(
+        mut self,
+        filter: impl Fn(EntityRef, &TypeRegistration) -> bool + 'w,
+    ) -> Self {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
     /// Consume the builder, producing a [`Prefab`].
+    ///
+    /// Entities are sorted by id and their components by type name, so that exported files
+    /// diff well. Proxies reading components written before them should not rely on this order.
     pub fn build(self) -> Prefab {
-        Prefab {
-            entities: self.entities.into_values().collect(),
+        if self.dense_ids {
+            let mut extracted: Vec<Entity> = self.extracted.keys().collect();
+            extracted.sort_by_key(|entity| entity.index());
+            let mut map = EntityMap::default();
+            for (id, entity) in extracted.into_iter().enumerate() {
+                map.insert(entity, Entity::from_raw(id as u32));
+            }
+            return self.build_mapped(&map);
+        }
+
+        let mut entities: Vec<_> = self.entities.into_values().collect();
+        for entry in &mut entities {
+            entry.components.retain_mut(|component| {
+                !is_hierarchy(component.type_name())
+                    || map_value(component.as_mut(), &self.extracted)
+            });
+        }
+        sorted(entities)
+    }
+
+    /// Consume the builder, producing a [`Prefab`] and the extracted resources.
+    ///
+    /// Prefabs only hold entities, the resources are returned next to it
+    /// for the caller to save along with the prefab, like settings of a level.
+    pub fn build_with_resources(mut self) -> (Prefab, Vec<Box<dyn Reflect>>) {
+        let resources = std::mem::take(&mut self.resources);
+        (self.build(), resources)
+    }
+
+    /// Consume the builder, producing the [`Patch`] that turns `base` into the extracted entities.
+    ///
+    /// Only the components that differ from `base` are recorded, see [`Patch::diff`].
+    /// Entities are matched by their id in the built prefab, see [`PrefabBuilder::with_dense_ids`].
+    /// Changes of the hierarchy are recorded as [`PatchEntity::parent`](super::PatchEntity::parent)
+    /// overrides, like with [`PrefabSpawner::capture_overrides`](super::PrefabSpawner::capture_overrides).
+    pub fn extract_diff(self, base: &Prefab) -> Result<Patch, PrefabError> {
+        let registry = self.registry.clone();
+        let prefab = self.build();
+        let registry = registry.read();
+        Patch::diff_overrides(base, &prefab, &registry)
+    }
+
+    /// Consume the builder, producing a [`Prefab`] with entity ids taken from `map`.
+    ///
+    /// `map` goes from extracted entities to prefab ids, as [`Entity::from_raw`].
+    /// References to entities inside of components are mapped too.
+    /// References to entities missing from `map` are dropped from lists of entities,
+    /// any other component referencing such an entity is dropped entirely.
+    pub fn build_mapped(self, map: &EntityMap) -> Prefab {
+        let ids: HashMap<u32, u32> = map
+            .iter()
+            .map(|(entity, id)| (entity.index(), id.index()))
+            .collect();
+
+        let mut entities: Vec<_> = self.entities.into_values().collect();
+        entities.retain_mut(|entry| {
+            let Some(&id) = ids.get(&entry.entity) else {
+                return false;
+            };
+            entry.entity = id;
+            entry
+                .components
+                .retain_mut(|component| map_value(component.as_mut(), map));
+            true
+        });
+        sorted(entities)
+    }
+
+    /// Remove a component of type `T` from an extracted entity.
+    ///
+    /// The world is left untouched. Entities that were not extracted are ignored.
+    pub fn remove_component<T: Reflect>(&mut self, entity: Entity) -> &mut Self {
+        let type_name = std::any::type_name::<T>();
+        if let Some(entry) = self.entities.get_mut(&entity.index()) {
+            entry.components.retain(|c| c.type_name() != type_name);
+        }
+        self
+    }
+
+    /// Insert a component into an extracted entity, replacing the component of the same type.
+    ///
+    /// The world is left untouched. Entities that were not extracted are ignored.
+    pub fn insert_component(&mut self, entity: Entity, value: impl Reflect) -> &mut Self {
+        if let Some(entry) = self.entities.get_mut(&entity.index()) {
+            let type_name = value.type_name();
+            let component = entry
+                .components
+                .iter_mut()
+                .find(|c| c.type_name() == type_name);
+            match component {
+                Some(component) => *component = Box::new(value),
+                None => entry.components.push(Box::new(value)),
+            }
+        }
+        self
+    }
+
+    /// Remove an extracted entity from the prefab.
+    ///
+    /// References to it are dropped like references to entities that were not extracted.
+    pub fn remove_entity(&mut self, entity: Entity) -> &mut Self {
+        self.entities.remove(&entity.index());
+        self.extracted.remove(entity);
+        self
+    }
+
+    /// Extract a resource from the builder's [`World`], if it exists.
+    ///
+    /// Re-extracting a resource that was already extracted will have no effect.
+    pub fn extract_resource<T: Resource + Reflect>(&mut self) -> &mut Self {
+        if let Some(resource) = self.world.get_resource::<T>() {
+            self.push_resource(resource);
+        }
+        self
+    }
+
+    /// Extract all the resources of the builder's [`World`] registered with [`ReflectResource`],
+    /// but those with [`ReflectPrefabSkip`].
+    ///
+    /// Re-extracting a resource that was already extracted will have no effect.
+    pub fn extract_resources(&mut self) -> &mut Self {
+        let registry = self.registry.clone();
+        let registry = registry.read();
+        for registration in registry.iter() {
+            if registration.data::<ReflectPrefabSkip>().is_some() {
+                continue;
+            }
+            let Some(reflect) = registration.data::<ReflectResource>() else {
+                continue;
+            };
+            if let Some(resource) = reflect.reflect(self.world) {
+                self.push_resource(resource);
+            }
+        }
+        self
+    }
+
+    fn push_resource(&mut self, resource: &dyn Reflect) {
+        let type_name = resource.type_name();
+        if !self.resources.iter().any(|r| r.type_name() == type_name) {
+            self.resources.push(resource.clone_value());
         }
     }
 
@@ -45,6 +265,21 @@ impl<'w> PrefabBuilder<'w> {
         self.extract_entities(std::iter::once(entity))
     }
 
+    /// Extract an entity from the builder's [`World`] with all its descendants.
+    ///
+    /// Re-extracting an entity that was already extracted will have no effect.
+    pub fn extract_entity_recursive(&mut self, entity: Entity) -> &mut Self {
+        let mut entities = Vec::new();
+        let mut stack = vec![entity];
+        while let Some(entity) = stack.pop() {
+            entities.push(entity);
+            if let Some(children) = self.world.get::<Children>(entity) {
+                stack.extend(children.iter().rev());
+            }
+        }
+        self.extract_entities(entities.into_iter())
+    }
+
     /// Extract entities from the builder's [`World`].
     ///
     /// Re-extracting an entity that was already extracted will have no effect.
@@ -52,15 +287,24 @@ impl<'w> PrefabBuilder<'w> {
     /// Extracting entities can be used to extract entities from a query.
     pub fn extract_entities(&mut self, entities: impl Iterator<Item = Entity>) -> &mut Self {
         let registry = self.registry.read();
+        let proxies: Vec<_> = registry
+            .iter()
+            .filter_map(|registration| {
+                let proxy = registration.data::<ReflectPrefabComponent>()?;
+                Some((registration, proxy))
+            })
+            .collect();
 
         for entity in entities {
             if self.entities.contains_key(&entity.index()) {
                 continue;
             }
 
-            let mut entry = PrefabEntity {
-                entity: entity.index(),
-                components: Vec::new(),
+            let mut entry = PrefabEntity::new(entity.index(), Vec::new());
+
+            let filter = |registration: &TypeRegistration| {
+                let filter = self.filter.as_ref();
+                filter.is_none_or(|filter| filter(self.world.entity(entity), registration))
             };
 
             for component_id in self.world.entity(entity).archetype().components() {
@@ -69,6 +313,9 @@ impl<'w> PrefabBuilder<'w> {
                     .components()
                     .get_info(component_id)
                     .and_then(|info| registry.get(info.type_id().unwrap()))
+                    .filter(|registration| !is_hierarchy(registration.type_name()))
+                    .filter(|registration| registration.data::<ReflectPrefabSkip>().is_none())
+                    .filter(|registration| filter(registration))
                     .and_then(|registration| registration.data::<ReflectComponent>());
 
                 if let Some(reflect_component) = reflect_component {
@@ -79,7 +326,32 @@ impl<'w> PrefabBuilder<'w> {
                 }
             }
 
+            // Proxies that are components too may be in the world as they are.
+            for &(registration, proxy) in &proxies {
+                let type_name = registration.type_name();
+                let components = &entry.components;
+                if components.iter().any(|c| c.type_name() == type_name) || !filter(registration) {
+                    continue;
+                }
+                if let Some(component) = proxy.extract(self.world.entity(entity)) {
+                    entry.components.push(component);
+                }
+            }
+
+            // The hierarchy is extracted whether it is registered or not.
+            if let Some(parent) = self.world.get::<Parent>(entity) {
+                entry.components.push(parent.clone_value());
+            }
+            if let Some(children) = self.world.get::<Children>(entity) {
+                entry.components.push(children.clone_value());
+            }
+
+            if self.uuids && !self.world.entity(entity).contains::<PrefabUuid>() {
+                entry.components.push(Box::new(PrefabUuid::new()));
+            }
+
             self.entities.insert(entity.index(), entry);
+            self.extracted.insert(entity, entity);
         }
 
         drop(registry);
@@ -87,17 +359,84 @@ impl<'w> PrefabBuilder<'w> {
     }
 }
 
+/// A prefab with its entities sorted by id and their components by type name.
+fn sorted(mut entities: Vec<PrefabEntity>) -> Prefab {
+    entities.sort_by_key(|entry| entry.entity);
+    for entry in &mut entities {
+        entry
+            .components
+            .sort_by(|a, b| a.type_name().cmp(b.type_name()));
+    }
+    Prefab { entities }
+}
+
+/// Map entities referenced by a reflected value.
+///
+/// Returns `false` if the value references an entity missing from `map`
+/// that can't be dropped from a list.
+pub(super) fn map_value(value: &mut dyn Reflect, map: &EntityMap) -> bool {
+    match value.reflect_mut() {
+        ReflectMut::Struct(value) => {
+            (0..value.field_len()).all(|i| map_value(value.field_at_mut(i).unwrap(), map))
+        }
+        ReflectMut::TupleStruct(value) => {
+            (0..value.field_len()).all(|i| map_value(value.field_mut(i).unwrap(), map))
+        }
+        ReflectMut::Tuple(value) => {
+            (0..value.field_len()).all(|i| map_value(value.field_mut(i).unwrap(), map))
+        }
+        ReflectMut::Enum(value) => {
+            (0..value.field_len()).all(|i| map_value(value.field_at_mut(i).unwrap(), map))
+        }
+        ReflectMut::Array(value) => {
+            (0..value.len()).all(|i| map_value(value.get_mut(i).unwrap(), map))
+        }
+        ReflectMut::List(list) => {
+            let Some(list) = list.as_reflect_mut().downcast_mut::<DynamicList>() else {
+                return (0..list.len()).all(|i| map_value(list.get_mut(i).unwrap(), map));
+            };
+
+            let mut mapped = DynamicList::default();
+            mapped.set_represented_type(list.get_represented_type_info());
+            for item in list.iter() {
+                let mut item = item.clone_value();
+                if map_value(item.as_mut(), map) {
+                    mapped.push_box(item);
+                }
+            }
+            *list = mapped;
+            true
+        }
+        // Keys can't be changed in place, maps of entities are left as is.
+        ReflectMut::Map(_) => true,
+        ReflectMut::Value(value) => match value.downcast_mut::<Entity>() {
+            Some(entity) => match map.get(*entity) {
+                Some(mapped) => {
+                    *entity = mapped;
+                    true
+                }
+                None => false,
+            },
+            None => true,
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::PrefabBuilder;
+    use super::{PrefabBuilder, ReflectPrefabSkip};
+    use crate::prefab::{PatchParent, Prefab, PrefabComponent, ReflectPrefabComponent};
     use bevy::ecs::{
         component::Component,
         prelude::Entity,
         query::With,
-        reflect::{AppTypeRegistry, ReflectComponent},
-        world::World,
+        reflect::{AppTypeRegistry, ReflectComponent, ReflectResource},
+        system::Resource,
+        world::{EntityMut, EntityRef, World},
     };
-    use bevy::reflect::Reflect;
+    use bevy::hierarchy::{BuildWorldChildren, Parent};
+    use bevy::reflect::{Reflect, ReflectRef};
+    use bevy::utils::HashSet;
 
     #[derive(Component, Reflect, Default, Eq, PartialEq, Debug)]
     #[reflect(Component)]
@@ -107,6 +446,43 @@ mod tests {
     #[reflect(Component)]
     struct ComponentB;
 
+    #[derive(Component, Reflect, Default, Eq, PartialEq, Debug)]
+    #[reflect(Component, PrefabSkip)]
+    struct Cache;
+
+    #[derive(Component, Reflect, Default, Eq, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Counter(u32);
+
+    #[derive(Component, Reflect, Default, Eq, PartialEq, Debug)]
+    struct Health(u32);
+
+    #[derive(Resource, Reflect, Default, Eq, PartialEq, Debug)]
+    #[reflect(Resource)]
+    struct Gravity(u32);
+
+    #[derive(Resource, Reflect, Default, Eq, PartialEq, Debug)]
+    #[reflect(Resource, PrefabSkip)]
+    struct FrameCache(u32);
+
+    #[derive(Resource, Reflect, Default, Eq, PartialEq, Debug)]
+    struct Weather(u32);
+
+    /// Inserts [`Health`], which isn't registered.
+    #[derive(Reflect, Default, Eq, PartialEq, Debug)]
+    #[reflect(PrefabComponent)]
+    struct HealthProxy(u32);
+
+    impl PrefabComponent for HealthProxy {
+        fn insert(self, entity: &mut EntityMut) {
+            entity.insert(Health(self.0));
+        }
+
+        fn extract(entity: EntityRef) -> Option<Self> {
+            entity.get::<Health>().map(|health| Self(health.0))
+        }
+    }
+
     #[test]
     fn extract_one_entity() {
         let mut world = World::default();
@@ -173,6 +549,128 @@ mod tests {
         assert!(scene.entities[0].components[1].represents::<ComponentB>());
     }
 
+    #[test]
+    fn skip_components() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        {
+            let mut register = atr.write();
+            register.register::<ComponentA>();
+            register.register::<ComponentB>();
+            register.register::<Cache>();
+            register.register_type_data::<ComponentB, ReflectPrefabSkip>();
+        }
+        world.insert_resource(atr);
+
+        let entity = world.spawn((ComponentA, ComponentB, Cache)).id();
+
+        let mut builder = PrefabBuilder::from_world(&world);
+        builder.extract_entity(entity);
+        let scene = builder.build();
+
+        assert_eq!(scene.entities[0].components.len(), 1);
+        assert!(scene.entities[0].components[0].represents::<ComponentA>());
+    }
+
+    #[test]
+    fn extract_resources() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        {
+            let mut register = atr.write();
+            register.register::<Gravity>();
+            register.register::<FrameCache>();
+            register.register::<Weather>();
+        }
+        world.insert_resource(atr);
+        world.insert_resource(Gravity(10));
+        world.insert_resource(FrameCache(3));
+        world.insert_resource(Weather(2));
+
+        let mut builder = PrefabBuilder::from_world(&world);
+        builder.extract_resources();
+        builder.extract_resource::<Gravity>();
+        let (_, resources) = builder.build_with_resources();
+        assert_eq!(resources.len(), 1);
+        assert!(resources[0].reflect_partial_eq(&Gravity(10)).unwrap());
+
+        // Resources extracted by type need no `ReflectResource`.
+        let mut builder = PrefabBuilder::from_world(&world);
+        builder.extract_resource::<Weather>();
+        let (_, resources) = builder.build_with_resources();
+        assert!(resources[0].reflect_partial_eq(&Weather(2)).unwrap());
+    }
+
+    #[test]
+    fn edit_extracted() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        {
+            let mut register = atr.write();
+            register.register::<ComponentA>();
+            register.register::<ComponentB>();
+        }
+        world.insert_resource(atr);
+
+        let root = world.spawn((ComponentA, ComponentB)).id();
+        let child = world.spawn(ComponentA).id();
+        world.entity_mut(root).push_children(&[child]);
+
+        let mut builder = PrefabBuilder::from_world(&world);
+        builder.extract_entity_recursive(root);
+        builder
+            .remove_component::<ComponentA>(root)
+            .insert_component(root, Health(3))
+            .insert_component(root, Health(5))
+            .remove_entity(child);
+        let scene = builder.build();
+
+        assert_eq!(scene.entities.len(), 1);
+        let components = &scene.entities[0].components;
+        assert!(!components.iter().any(|c| c.represents::<ComponentA>()));
+        assert!(components.iter().any(|c| c.represents::<ComponentB>()));
+        let health: Vec<_> = components
+            .iter()
+            .filter(|c| c.represents::<Health>())
+            .collect();
+        assert_eq!(health.len(), 1);
+        assert!(health[0].reflect_partial_eq(&Health(5)).unwrap());
+        assert!(world.get::<ComponentA>(root).is_some());
+    }
+
+    #[test]
+    fn filter_components() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        {
+            let mut register = atr.write();
+            register.register::<ComponentA>();
+            register.register::<ComponentB>();
+        }
+        world.insert_resource(atr);
+
+        let node = world.spawn((ComponentA, ComponentB)).id();
+        let other = world.spawn((ComponentA, ComponentB)).id();
+
+        // `ComponentB` is only extracted from `node`.
+        let builder = PrefabBuilder::from_world(&world);
+        let mut builder = builder.with_component_filter(|entity, registration| {
+            let is_b = registration.type_id() == std::any::TypeId::of::<ComponentB>();
+            !is_b || entity.id() == node
+        });
+        builder.extract_entities([node, other].into_iter());
+        let mut scene = builder.build();
+        scene.entities.sort_by_key(|entry| entry.entity);
+
+        assert_eq!(scene.entities[0].components.len(), 2);
+        assert_eq!(scene.entities[1].components.len(), 1);
+        assert!(scene.entities[1].components[0].represents::<ComponentA>());
+    }
+
     #[test]
     fn extract_query() {
         let mut world = World::default();
@@ -199,4 +697,162 @@ mod tests {
         scene_entities.sort();
         assert_eq!(scene_entities, [entity_a_b.index(), entity_a.index()]);
     }
+
+    #[test]
+    fn extract_recursive() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        atr.write().register::<ComponentA>();
+        world.insert_resource(atr);
+
+        let root = world.spawn(ComponentA).id();
+        let child = world.spawn(ComponentA).id();
+        let grandchild = world.spawn(ComponentA).id();
+        let _other = world.spawn(ComponentA).id();
+        world.entity_mut(root).push_children(&[child]);
+        world.entity_mut(child).push_children(&[grandchild]);
+
+        let mut builder = PrefabBuilder::from_world(&world);
+        builder.extract_entity_recursive(root);
+        let scene = builder.build();
+
+        let mut scene_entities: Vec<u32> = scene.entities.iter().map(|e| e.entity).collect();
+        scene_entities.sort();
+        assert_eq!(
+            scene_entities,
+            [root.index(), child.index(), grandchild.index()]
+        );
+    }
+
+    #[test]
+    fn extract_hierarchy() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        atr.write().register::<ComponentA>();
+        world.insert_resource(atr);
+
+        let root = world.spawn(ComponentA).id();
+        let child = world.spawn(ComponentA).id();
+        let other = world.spawn(ComponentA).id();
+        world.entity_mut(root).push_children(&[child, other]);
+
+        let mut builder = PrefabBuilder::from_world(&world);
+        builder.extract_entities([root, child].into_iter());
+        let mut scene = builder.build();
+        scene.entities.sort_by_key(|entry| entry.entity);
+
+        let children = scene.entities[0].components[0].reflect_ref();
+        let ReflectRef::TupleStruct(children) = children else {
+            panic!("`Children` is a tuple struct");
+        };
+        let ReflectRef::List(children) = children.field(0).unwrap().reflect_ref() else {
+            panic!("`Children` holds a list");
+        };
+        assert_eq!(children.len(), 1);
+        assert_eq!(children.get(0).unwrap().downcast_ref(), Some(&child));
+        assert!(scene.entities[1].components[0].represents::<Parent>());
+    }
+
+    #[test]
+    fn dense_ids() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        atr.write().register::<ComponentA>();
+        world.insert_resource(atr);
+
+        world.spawn_empty();
+        let root = world.spawn(ComponentA).id();
+        world.spawn_empty();
+        let child = world.spawn((ComponentB, ComponentA)).id();
+        world.entity_mut(root).push_children(&[child]);
+
+        let mut builder = PrefabBuilder::from_world(&world).with_dense_ids();
+        builder.extract_entities([child, root].into_iter());
+        let scene = builder.build();
+
+        let ids: Vec<u32> = scene.entities.iter().map(|entry| entry.entity).collect();
+        assert_eq!(ids, [0, 1]);
+        let components = &scene.entities[1].components;
+        let type_names: Vec<&str> = components.iter().map(|c| c.type_name()).collect();
+        assert_eq!(
+            type_names,
+            [
+                std::any::type_name::<Parent>(),
+                std::any::type_name::<ComponentA>()
+            ]
+        );
+        let parent = components[0].reflect_ref();
+        let ReflectRef::TupleStruct(parent) = parent else {
+            panic!("`Parent` is a tuple struct");
+        };
+        let parent = parent.field(0).unwrap().downcast_ref::<Entity>();
+        assert_eq!(parent, Some(&Entity::from_raw(0)));
+    }
+
+    #[test]
+    fn extract_diff() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        {
+            let mut register = atr.write();
+            register.register::<ComponentA>();
+            register.register::<Counter>();
+        }
+        world.insert_resource(atr);
+
+        let root = world.spawn((ComponentA, Counter(1))).id();
+        let child = world.spawn(Counter(2)).id();
+        let other = world.spawn(Counter(3)).id();
+        world.entity_mut(root).push_children(&[child, other]);
+        let mut builder = PrefabBuilder::from_world(&world).with_dense_ids();
+        builder.extract_entity_recursive(root);
+        let base = builder.build();
+
+        world.entity_mut(root).remove::<ComponentA>();
+        world.get_mut::<Counter>(child).unwrap().0 = 5;
+        world.entity_mut(child).remove_parent();
+
+        let mut builder = PrefabBuilder::from_world(&world).with_dense_ids();
+        builder.extract_entities([root, child, other].into_iter());
+        let patch = builder.extract_diff(&base).unwrap();
+
+        assert_eq!(patch.modify.len(), 2);
+        let root = &patch.modify[0];
+        assert_eq!(root.entity, 0);
+        let removed = HashSet::from_iter([std::any::type_name::<ComponentA>().to_string()]);
+        assert_eq!(root.remove, removed);
+        assert!(root.modify.is_empty() && root.append.is_empty());
+        let child = &patch.modify[1];
+        assert_eq!(child.entity, 1);
+        assert_eq!(child.parent, Some(PatchParent::Root));
+        let counter = child.modify.get(std::any::type_name::<Counter>()).unwrap();
+        assert_eq!(counter.len(), 1);
+    }
+
+    #[test]
+    fn extract_proxies() {
+        let mut world = World::default();
+
+        let atr = AppTypeRegistry::default();
+        {
+            let mut register = atr.write();
+            register.register::<ComponentA>();
+            register.register::<HealthProxy>();
+        }
+        world.insert_resource(atr.clone());
+
+        world.spawn((ComponentA, Health(7)));
+        world.spawn(ComponentA);
+        let mut prefab = Prefab::from_world(&world, &atr);
+        prefab.entities.sort_by_key(|entry| entry.entity);
+
+        let components = &prefab.entities[0].components;
+        assert_eq!(components.len(), 2);
+        assert!(components[1].reflect_partial_eq(&HealthProxy(7)).unwrap());
+        assert_eq!(prefab.entities[1].components.len(), 1);
+    }
 }
@@ -0,0 +1,70 @@
+use super::Prefab;
+use bevy::reflect::{Reflect, ReflectFromReflect, TypeRegistryInternal};
+
+impl Prefab {
+    /// Replace dynamic component values with values of their concrete types.
+    ///
+    /// Deserialized components are trees of dynamic values with an allocation per field,
+    /// a concrete value takes a single allocation and is cheaper to apply to the world.
+    /// Values of types without [`ReflectFromReflect`] are kept as they are.
+    pub fn compact(&mut self, registry: &TypeRegistryInternal) {
+        let components = self.entities.iter_mut().flat_map(|e| &mut e.components);
+        for component in components {
+            if let Some(value) = to_concrete(component.as_ref(), registry) {
+                *component = value;
+            }
+        }
+    }
+}
+
+/// Clone a reflected value, as a concrete value when possible.
+///
+/// Unlike [`Reflect::clone_value`], which builds a dynamic value with an allocation per field,
+/// this allocates once for types registered with [`ReflectFromReflect`].
+pub fn clone_compact(value: &dyn Reflect, registry: &TypeRegistryInternal) -> Box<dyn Reflect> {
+    to_concrete(value, registry).unwrap_or_else(|| value.clone_value())
+}
+
+pub(super) fn to_concrete(
+    value: &dyn Reflect,
+    registry: &TypeRegistryInternal,
+) -> Option<Box<dyn Reflect>> {
+    let info = value.get_represented_type_info()?;
+    let from_reflect = registry.get_type_data::<ReflectFromReflect>(info.type_id())?;
+    from_reflect.from_reflect(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::clone_compact;
+    use crate::prefab::{Prefab, PrefabEntity};
+    use bevy::reflect::{Reflect, TypeRegistryInternal};
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Stats {
+        health: u32,
+        speed: f32,
+    }
+
+    #[test]
+    fn compact_dynamic_values() {
+        let mut registry = TypeRegistryInternal::default();
+        registry.register::<Stats>();
+
+        let value = Stats {
+            health: 3,
+            speed: 1.5,
+        };
+        let mut prefab = Prefab {
+            entities: vec![PrefabEntity::new(0, vec![value.clone_value()])],
+        };
+        assert!(prefab.entities[0].components[0].is_dynamic());
+
+        prefab.compact(&registry);
+        let component = &prefab.entities[0].components[0];
+        assert_eq!(component.downcast_ref::<Stats>(), Some(&value));
+
+        let clone = clone_compact(component.as_ref(), &registry);
+        assert_eq!(clone.downcast_ref::<Stats>(), Some(&value));
+    }
+}
@@ -0,0 +1,314 @@
+use super::{Prefab, PrefabSpawner};
+use bevy::{
+    app::{App, Plugin, PostUpdate},
+    asset::{AssetServer, Handle},
+    ecs::{
+        component::Component,
+        entity::{Entities, Entity},
+        query::With,
+        schedule::IntoSystemConfigs,
+        system::{Commands, Query, Res, ResMut, Resource},
+    },
+    gizmos::gizmos::Gizmos,
+    hierarchy::Parent,
+    math::{Quat, Vec3},
+    render::{color::Color, view::Visibility},
+    text::{Text, TextStyle},
+    transform::{components::GlobalTransform, TransformSystem},
+    ui::{node_bundles::TextBundle, PositionType, Style, Val},
+    utils::{HashMap, HashSet},
+};
+use std::hash::{Hash, Hasher};
+
+/// Draws debug gizmos for every live prefab instance.
+///
+/// Each instance gets a box around all of its entities and a marker on each root entity,
+/// colored per prefab. Instances that look duplicated are drawn in red.
+/// Per-prefab instance and entity counts, including leaked instances,
+/// are collected into [`PrefabDebugStats`] and shown in a text overlay.
+pub struct PrefabDebugPlugin;
+
+impl Plugin for PrefabDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PrefabDebugConfig>()
+            .init_resource::<PrefabDebugStats>()
+            .add_systems(
+                PostUpdate,
+                (
+                    (prefab_debug_stats_system, prefab_debug_draw_system)
+                        .chain()
+                        .after(TransformSystem::TransformPropagate)
+                        .run_if(|config: Res<PrefabDebugConfig>| config.enabled),
+                    prefab_debug_text_system.after(prefab_debug_stats_system),
+                ),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Resource)]
+pub struct PrefabDebugConfig {
+    pub enabled: bool,
+    /// Draw a box around all entities of an instance.
+    pub bounds: bool,
+    /// Draw a marker on the root entities of an instance.
+    pub roots: bool,
+    /// Show the instance and entity counts of each prefab in a text overlay.
+    pub counts: bool,
+    /// Radius of the root markers.
+    pub root_radius: f32,
+}
+
+impl Default for PrefabDebugConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            bounds: true,
+            roots: true,
+            counts: true,
+            root_radius: 0.25,
+        }
+    }
+}
+
+/// Per-prefab numbers collected by the [`PrefabDebugPlugin`] on the last frame.
+#[derive(Default, Resource)]
+pub struct PrefabDebugStats {
+    pub prefabs: HashMap<Handle<Prefab>, PrefabDebugEntry>,
+    /// Instances with a transform, drawn by the plugin.
+    shapes: Vec<InstanceShape>,
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct PrefabDebugEntry {
+    /// Number of live instances.
+    pub instances: usize,
+    /// Number of entities over all instances.
+    pub entities: usize,
+    /// Instances that have no entity left in the world.
+    pub leaked: usize,
+    /// Instances whose roots are at the same place as the ones of another instance of the prefab.
+    ///
+    /// Instances with all roots at the origin are not counted, they were likely not placed yet.
+    pub duplicated: usize,
+}
+
+struct InstanceShape {
+    color: Color,
+    min: Vec3,
+    max: Vec3,
+    roots: Vec<Vec3>,
+}
+
+/// Marks the text overlay of the [`PrefabDebugPlugin`].
+#[derive(Component)]
+struct PrefabDebugText;
+
+const WARNING: Color = Color::RED;
+
+fn prefab_debug_stats_system(
+    spawner: Res<PrefabSpawner>,
+    entities: &Entities,
+    transforms: Query<(&GlobalTransform, Option<&Parent>)>,
+    mut stats: ResMut<PrefabDebugStats>,
+) {
+    let stats = &mut *stats;
+    stats.prefabs.clear();
+    stats.shapes.clear();
+
+    // Root positions, rounded to the millimeter.
+    let mut seen: HashSet<(Handle<Prefab>, Vec<[i32; 3]>)> = HashSet::default();
+
+    for (handle, info) in spawner.instances() {
+        let entry = stats.prefabs.entry(handle.clone_weak()).or_default();
+        entry.instances += 1;
+
+        let instance: HashSet<Entity> = info.entities().collect();
+        entry.entities += instance.len();
+        if !instance.iter().any(|&entity| entities.contains(entity)) {
+            entry.leaked += 1;
+            continue;
+        }
+
+        let mut min = Vec3::splat(f32::INFINITY);
+        let mut max = Vec3::splat(f32::NEG_INFINITY);
+        let mut roots = Vec::new();
+        for &entity in &instance {
+            let Ok((transform, parent)) = transforms.get(entity) else {
+                continue;
+            };
+            let position = transform.translation();
+            min = min.min(position);
+            max = max.max(position);
+
+            if !parent.is_some_and(|parent| instance.contains(&parent.get())) {
+                roots.push(position);
+            }
+        }
+
+        // Nothing to draw without transforms.
+        if min.x > max.x {
+            continue;
+        }
+
+        let placed = roots.iter().any(|&root| root != Vec3::ZERO);
+        let mut key: Vec<[i32; 3]> = (roots.iter())
+            .map(|&root| (root * 1000.0).round().as_ivec3().to_array())
+            .collect();
+        key.sort_unstable();
+        let duplicated = placed && !seen.insert((handle.clone_weak(), key));
+        let color = if duplicated {
+            entry.duplicated += 1;
+            WARNING
+        } else {
+            prefab_color(handle)
+        };
+
+        stats.shapes.push(InstanceShape {
+            color,
+            min,
+            max,
+            roots,
+        });
+    }
+}
+
+fn prefab_debug_draw_system(
+    config: Res<PrefabDebugConfig>,
+    stats: Res<PrefabDebugStats>,
+    mut gizmos: Gizmos,
+) {
+    for shape in &stats.shapes {
+        if config.bounds {
+            let size = (shape.max - shape.min).max(Vec3::splat(config.root_radius * 2.0));
+            let transform = GlobalTransform::from_translation((shape.min + shape.max) * 0.5)
+                * GlobalTransform::from_scale(size);
+            gizmos.cuboid(transform, shape.color);
+        }
+
+        if config.roots {
+            for &position in &shape.roots {
+                gizmos.sphere(position, Quat::IDENTITY, config.root_radius, shape.color);
+            }
+        }
+    }
+}
+
+fn prefab_debug_text_system(
+    mut commands: Commands,
+    config: Res<PrefabDebugConfig>,
+    stats: Res<PrefabDebugStats>,
+    asset_server: Option<Res<AssetServer>>,
+    mut texts: Query<(&mut Text, &mut Visibility), With<PrefabDebugText>>,
+) {
+    let show = config.enabled && config.counts;
+    let Ok((mut text, mut visibility)) = texts.get_single_mut() else {
+        if show {
+            let style = Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(8.0),
+                left: Val::Px(8.0),
+                ..Default::default()
+            };
+            let text = TextBundle::from_section("", TextStyle::default()).with_style(style);
+            commands.spawn((text, PrefabDebugText));
+        }
+        return;
+    };
+
+    *visibility = if show {
+        Visibility::Inherited
+    } else {
+        Visibility::Hidden
+    };
+    if !show {
+        return;
+    }
+
+    let mut lines: Vec<String> = (stats.prefabs.iter())
+        .map(|(handle, entry)| {
+            let path = asset_server.as_ref();
+            let path = path.and_then(|server| server.get_handle_path(handle));
+            let name = match path {
+                Some(path) => path.path().display().to_string(),
+                None => format!("{:?}", handle.id()),
+            };
+            format!(
+                "{}: {} instances, {} entities, {} leaked, {} duplicated",
+                name, entry.instances, entry.entities, entry.leaked, entry.duplicated
+            )
+        })
+        .collect();
+    lines.sort();
+    text.sections[0].value = lines.join("\n");
+}
+
+/// A stable color for a prefab, derived from its handle.
+fn prefab_color(handle: &Handle<Prefab>) -> Color {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    handle.id().hash(&mut hasher);
+    let hue = (hasher.finish() % 360) as f32;
+    Color::hsl(hue, 0.8, 0.6)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prefab_debug_stats_system, PrefabDebugStats};
+    use crate::prefab::test_utils;
+    use bevy::app::PostUpdate;
+    use bevy::ecs::{component::Component, reflect::ReflectComponent, schedule::IntoSystemConfigs};
+    use bevy::reflect::Reflect;
+    use bevy::transform::{components::Transform, TransformBundle, TransformSystem};
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Part(u32);
+
+    #[test]
+    fn collect_stats() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        app.init_resource::<PrefabDebugStats>().add_systems(
+            PostUpdate,
+            prefab_debug_stats_system.after(TransformSystem::TransformPropagate),
+        );
+
+        let ron = format!(
+            "{{ 0: {{ \"{0}\": (0) }}, 1: {{ \"{0}\": (1) }} }}",
+            std::any::type_name::<Part>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let positions = [
+            Some(Transform::from_xyz(1.0, 0.0, 0.0)),
+            Some(Transform::from_xyz(1.0, 0.0, 0.0)),
+            Some(Transform::IDENTITY),
+            Some(Transform::IDENTITY),
+            None,
+            None,
+        ];
+        let mut instances = Vec::new();
+        for transform in positions {
+            let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+            if let Some(transform) = transform {
+                let entity = test_utils::instance_entity(&app, &instance, 0);
+                let bundle = TransformBundle::from_transform(transform);
+                app.world.entity_mut(entity).insert(bundle);
+            }
+            instances.push(instance);
+        }
+        // The last instance leaked, its entities were despawned without the spawner.
+        for prefab_entity in [0, 1] {
+            let entity = test_utils::instance_entity(&app, &instances[5], prefab_entity);
+            app.world.despawn(entity);
+        }
+        app.update();
+
+        let stats = app.world.resource::<PrefabDebugStats>();
+        let entry = &stats.prefabs[&handle];
+        assert_eq!(entry.instances, 6);
+        assert_eq!(entry.entities, 12);
+        assert_eq!(entry.leaked, 1);
+        // Instances at the origin were not placed, they are not duplicates.
+        assert_eq!(entry.duplicated, 1);
+        assert_eq!(stats.shapes.len(), 4);
+    }
+}
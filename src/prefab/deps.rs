@@ -0,0 +1,122 @@
+use super::{
+    handle::{handle_paths, has_handle_paths},
+    Prefab,
+};
+use bevy::asset::AssetPath;
+use bevy::reflect::{FromReflect, FromType, Reflect, TypeRegistryInternal};
+use bevy::utils::HashMap;
+
+/// A component referencing assets by their path.
+///
+/// The [`PrefabLoader`](super::PrefabLoader) loads the paths as dependencies of the prefabs
+/// holding the component, so they are loaded before the prefab is.
+/// Register it with `#[reflect(PrefabAssetPaths)]`.
+pub trait PrefabAssetPaths {
+    fn asset_paths(&self) -> Vec<AssetPath<'static>>;
+}
+
+#[derive(Clone)]
+pub struct ReflectPrefabAssetPaths {
+    asset_paths: fn(&dyn Reflect) -> Vec<AssetPath<'static>>,
+}
+
+impl ReflectPrefabAssetPaths {
+    pub fn asset_paths(&self, component: &dyn Reflect) -> Vec<AssetPath<'static>> {
+        (self.asset_paths)(component)
+    }
+}
+
+impl<T: PrefabAssetPaths + FromReflect> FromType<T> for ReflectPrefabAssetPaths {
+    fn from_type() -> Self {
+        Self {
+            asset_paths: |reflect| {
+                let component = T::from_reflect(reflect);
+                component.map_or_else(Vec::new, |component| component.asset_paths())
+            },
+        }
+    }
+}
+
+impl Prefab {
+    /// The assets referenced by the components of this prefab, without duplicates.
+    ///
+    /// These are the paths of [`PrefabAssetPaths`] components and of [`HandlePath`](super::HandlePath) values,
+    /// which are replaced by handles by [`Prefab::compact`].
+    /// Serialized components are only deserialized if they are [`PrefabAssetPaths`]
+    /// or can hold handles written as paths.
+    pub fn asset_paths(&self, registry: &TypeRegistryInternal) -> Vec<AssetPath<'static>> {
+        let reflect_of = |type_name: &str| {
+            let registration = registry.get_with_name(type_name)?;
+            registration.data::<ReflectPrefabAssetPaths>()
+        };
+        let mut has_paths: HashMap<&str, bool> = HashMap::default();
+
+        let mut paths = Vec::new();
+        for entity in &self.entities {
+            let serialized = entity.serialized.iter().filter(|component| {
+                let type_name = component.type_name();
+                *has_paths.entry(type_name).or_insert_with(|| {
+                    let Some(registration) = registry.get_with_name(type_name) else {
+                        return false;
+                    };
+                    registration.data::<ReflectPrefabAssetPaths>().is_some()
+                        || has_handle_paths(registration.type_id(), registry)
+                })
+            });
+            let serialized =
+                serialized.filter_map(|component| component.materialize(registry).ok());
+            let components = entity.components.iter().map(AsRef::as_ref);
+            for component in components.chain(serialized) {
+                let mut found = Vec::new();
+                handle_paths(component, &mut found);
+                if let Some(reflect) = reflect_of(component.type_name()) {
+                    found.extend(reflect.asset_paths(component));
+                }
+                for path in found {
+                    if !paths.contains(&path) {
+                        paths.push(path);
+                    }
+                }
+            }
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrefabAssetPaths, ReflectPrefabAssetPaths};
+    use crate::prefab::Prefab;
+    use bevy::asset::AssetPath;
+    use bevy::ecs::reflect::AppTypeRegistry;
+    use bevy::reflect::Reflect;
+
+    #[derive(Reflect, Default)]
+    #[reflect(PrefabAssetPaths)]
+    struct Model {
+        path: String,
+    }
+
+    impl PrefabAssetPaths for Model {
+        fn asset_paths(&self) -> Vec<AssetPath<'static>> {
+            vec![AssetPath::from(self.path.as_str()).to_owned()]
+        }
+    }
+
+    #[test]
+    fn asset_paths() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Model>();
+
+        let input = format!(
+            "{{ 0: {{ \"{0}\": (path: \"tree.glb\") }}, 1: {{ \"{0}\": (path: \"tree.glb\") }} }}",
+            std::any::type_name::<Model>()
+        );
+        let expected = vec![AssetPath::from("tree.glb")];
+        let prefab = Prefab::deserialize_ron(input.as_bytes(), &registry.0).unwrap();
+        assert_eq!(prefab.asset_paths(&registry.read()), expected);
+
+        let lazy = Prefab::deserialize_ron_lazy(input.as_bytes()).unwrap();
+        assert_eq!(lazy.asset_paths(&registry.read()), expected);
+    }
+}
@@ -0,0 +1,339 @@
+use super::{Prefab, PrefabSpawner, PrefabSpawnerStats};
+use bevy::{
+    app::{App, Plugin, Update},
+    diagnostic::{
+        Diagnostic, DiagnosticId, DiagnosticMeasurement, DiagnosticsStore, RegisterDiagnostic,
+    },
+    ecs::schedule::IntoSystemConfigs,
+    ecs::system::{Res, ResMut, Resource},
+    log::info,
+    utils::{Duration, Instant, Uuid},
+};
+use std::{
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Records load metrics of every prefab into the [`DiagnosticsStore`].
+///
+/// Each load adds a measurement to the totals ([`PrefabDiagnosticsPlugin::LOAD_TIME`] and friends)
+/// and to diagnostics of the loaded prefab, see [`PrefabDiagnosticsPlugin::prefab`].
+///
+/// The [`PrefabSpawnerStats`] of every maintenance are also recorded
+/// ([`PrefabDiagnosticsPlugin::INSTANCES`] and friends) and kept as a resource.
+/// [`PrefabDiagnosticsPlugin::SPAWN_ENTITIES`] is the average number of entities
+/// of the instances spawned by a maintenance, only measured when some are.
+#[derive(Default)]
+pub struct PrefabDiagnosticsPlugin {
+    /// Also log every load as a tracing event.
+    pub log: bool,
+}
+
+impl Plugin for PrefabDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.world
+            .get_resource_or_insert_with(PrefabLoadLog::default)
+            .enable();
+
+        let log = self.log;
+        app.register_diagnostic(
+            Diagnostic::new(Self::LOAD_TIME, "prefab_load_time", 20).with_suffix("ms"),
+        )
+        .register_diagnostic(Diagnostic::new(Self::ENTITIES, "prefab_entities", 20))
+        .register_diagnostic(Diagnostic::new(Self::COMPONENTS, "prefab_components", 20))
+        .register_diagnostic(Diagnostic::new(Self::BYTES, "prefab_bytes", 20).with_suffix("B"))
+        .register_diagnostic(Diagnostic::new(Self::INSTANCES, "prefab_instances", 20))
+        .register_diagnostic(Diagnostic::new(
+            Self::PENDING_SPAWNS,
+            "prefab_pending_spawns",
+            20,
+        ))
+        .register_diagnostic(Diagnostic::new(
+            Self::SPAWN_ENTITIES,
+            "prefab_spawn_entities",
+            20,
+        ))
+        .register_diagnostic(
+            Diagnostic::new(Self::MAINTAIN_TIME, "prefab_maintain_time", 20).with_suffix("ms"),
+        )
+        .init_resource::<PrefabSpawnerStats>()
+        .add_systems(
+            Update,
+            (
+                move |load_log: Res<PrefabLoadLog>, store: ResMut<DiagnosticsStore>| {
+                    prefab_diagnostics_system(load_log, store, log)
+                },
+                prefab_spawner_diagnostics_system,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// A measured quantity of a prefab load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrefabMetric {
+    /// Time spent in the loader, in milliseconds.
+    LoadTime,
+    /// Number of deserialized entities.
+    Entities,
+    /// Number of deserialized components, including lazy ones.
+    Components,
+    /// Size of the prefab file.
+    Bytes,
+}
+
+impl PrefabMetric {
+    const ALL: [Self; 4] = [
+        Self::LoadTime,
+        Self::Entities,
+        Self::Components,
+        Self::Bytes,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::LoadTime => "load_time",
+            Self::Entities => "entities",
+            Self::Components => "components",
+            Self::Bytes => "bytes",
+        }
+    }
+
+    fn suffix(self) -> &'static str {
+        match self {
+            Self::LoadTime => "ms",
+            Self::Bytes => "B",
+            Self::Entities | Self::Components => "",
+        }
+    }
+
+    fn value(self, record: &PrefabLoadRecord) -> f64 {
+        match self {
+            Self::LoadTime => record.duration.as_secs_f64() * 1000.0,
+            Self::Entities => record.entities as f64,
+            Self::Components => record.components as f64,
+            Self::Bytes => record.bytes as f64,
+        }
+    }
+}
+
+impl PrefabDiagnosticsPlugin {
+    pub const LOAD_TIME: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b01);
+    pub const ENTITIES: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b02);
+    pub const COMPONENTS: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b03);
+    pub const BYTES: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b04);
+    pub const INSTANCES: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b05);
+    pub const PENDING_SPAWNS: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b06);
+    pub const SPAWN_ENTITIES: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b07);
+    pub const MAINTAIN_TIME: DiagnosticId =
+        DiagnosticId::from_u128(0x6f0e_9b1c_3d2a_4e57_8a61_52c4_d7f3_0b08);
+
+    /// The diagnostic of one metric of the prefab at `path`, relative to the asset folder.
+    ///
+    /// It is registered when the prefab is loaded for the first time.
+    pub fn prefab(path: impl AsRef<Path>, metric: PrefabMetric) -> DiagnosticId {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.as_ref().hash(&mut hasher);
+        metric.hash(&mut hasher);
+        DiagnosticId(Uuid::from_u64_pair(hasher.finish(), 0x8a61_52c4_d7f3_0b00))
+    }
+
+    fn total(metric: PrefabMetric) -> DiagnosticId {
+        match metric {
+            PrefabMetric::LoadTime => Self::LOAD_TIME,
+            PrefabMetric::Entities => Self::ENTITIES,
+            PrefabMetric::Components => Self::COMPONENTS,
+            PrefabMetric::Bytes => Self::BYTES,
+        }
+    }
+}
+
+/// Metrics of a single prefab load.
+#[derive(Debug, Clone)]
+pub struct PrefabLoadRecord {
+    pub path: PathBuf,
+    pub duration: Duration,
+    pub entities: usize,
+    pub components: usize,
+    pub bytes: usize,
+}
+
+impl PrefabLoadRecord {
+    pub(crate) fn new(path: &Path, start: Instant, prefab: &Prefab, bytes: usize) -> Self {
+        let components = prefab.entities.iter();
+        let components = components.map(|entity| entity.components.len() + entity.serialized.len());
+        Self {
+            path: path.to_path_buf(),
+            duration: start.elapsed(),
+            entities: prefab.entities.len(),
+            components: components.sum(),
+            bytes,
+        }
+    }
+}
+
+/// Load records shared between the [`PrefabLoader`](super::PrefabLoader) and the diagnostics.
+///
+/// Nothing is recorded until the [`PrefabDiagnosticsPlugin`] enables it.
+#[derive(Clone, Default, Resource)]
+pub struct PrefabLoadLog {
+    records: Arc<Mutex<Option<Vec<PrefabLoadRecord>>>>,
+}
+
+impl PrefabLoadLog {
+    fn enable(&self) {
+        self.records.lock().unwrap().get_or_insert_with(Vec::new);
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.records.lock().unwrap().is_some()
+    }
+
+    pub(crate) fn record(&self, record: PrefabLoadRecord) {
+        if let Some(records) = self.records.lock().unwrap().as_mut() {
+            records.push(record);
+        }
+    }
+
+    fn drain(&self) -> Vec<PrefabLoadRecord> {
+        let mut records = self.records.lock().unwrap();
+        records.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for PrefabLoadLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrefabLoadLog")
+            .field("enabled", &self.is_enabled())
+            .finish()
+    }
+}
+
+fn prefab_diagnostics_system(
+    load_log: Res<PrefabLoadLog>,
+    mut store: ResMut<DiagnosticsStore>,
+    log: bool,
+) {
+    for record in load_log.drain() {
+        if log {
+            info!(
+                "loaded prefab {} in {:.2?}: {} entities, {} components, {} bytes",
+                record.path.display(),
+                record.duration,
+                record.entities,
+                record.components,
+                record.bytes,
+            );
+        }
+
+        let time = Instant::now();
+        for metric in PrefabMetric::ALL {
+            let value = metric.value(&record);
+
+            let id = PrefabDiagnosticsPlugin::prefab(&record.path, metric);
+            if store.get(id).is_none() {
+                let name = format!("prefab/{}/{}", record.path.display(), metric.name());
+                store.add(Diagnostic::new(id, name, 20).with_suffix(metric.suffix()));
+            }
+
+            for id in [id, PrefabDiagnosticsPlugin::total(metric)] {
+                if let Some(diagnostic) = store.get_mut(id) {
+                    diagnostic.add_measurement(DiagnosticMeasurement { time, value });
+                }
+            }
+        }
+    }
+}
+
+/// Copy the stats of the last maintenance to the [`PrefabSpawnerStats`] resource and record them.
+fn prefab_spawner_diagnostics_system(
+    spawner: Option<Res<PrefabSpawner>>,
+    mut stats: ResMut<PrefabSpawnerStats>,
+    mut store: ResMut<DiagnosticsStore>,
+) {
+    let Some(spawner) = spawner else {
+        return;
+    };
+    let current = spawner.stats();
+
+    let time = Instant::now();
+    let mut measure = |id, value| {
+        if let Some(diagnostic) = store.get_mut(id) {
+            diagnostic.add_measurement(DiagnosticMeasurement { time, value });
+        }
+    };
+    measure(PrefabDiagnosticsPlugin::INSTANCES, current.instances as f64);
+    measure(
+        PrefabDiagnosticsPlugin::PENDING_SPAWNS,
+        current.pending as f64,
+    );
+    if current.spawned > 0 {
+        let entities = current.spawned_entities as f64 / current.spawned as f64;
+        measure(PrefabDiagnosticsPlugin::SPAWN_ENTITIES, entities);
+    }
+    let maintain_time = current.maintain_time.as_secs_f64() * 1000.0;
+    measure(PrefabDiagnosticsPlugin::MAINTAIN_TIME, maintain_time);
+
+    if *stats != *current {
+        *stats = current.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrefabDiagnosticsPlugin, PrefabLoadLog, PrefabLoadRecord, PrefabMetric};
+    use crate::prefab::{test_utils, Prefab, PrefabSpawner, PrefabSpawnerStats};
+    use bevy::{diagnostic::DiagnosticsStore, utils::Instant};
+    use std::path::Path;
+
+    #[test]
+    fn record_load() {
+        let mut app = test_utils::test_app();
+        app.add_plugins(PrefabDiagnosticsPlugin::default());
+
+        let prefab = Prefab::deserialize_ron_lazy(b"{ 0: {}, 1: { \"a\": () } }").unwrap();
+        let path = Path::new("prefabs/tree.prefab");
+        let record = PrefabLoadRecord::new(path, Instant::now(), &prefab, 27);
+        app.world.resource::<PrefabLoadLog>().record(record);
+        app.update();
+
+        let store = app.world.resource::<DiagnosticsStore>();
+        let value = |id| store.get(id).and_then(|diagnostic| diagnostic.value());
+        assert_eq!(value(PrefabDiagnosticsPlugin::ENTITIES), Some(2.0));
+        assert_eq!(value(PrefabDiagnosticsPlugin::COMPONENTS), Some(1.0));
+        let bytes = PrefabDiagnosticsPlugin::prefab(path, PrefabMetric::Bytes);
+        assert_eq!(value(bytes), Some(27.0));
+    }
+
+    #[test]
+    fn record_spawner() {
+        let mut app = test_utils::test_app();
+        app.add_plugins(PrefabDiagnosticsPlugin::default());
+
+        let handle = test_utils::load_prefab_str(&mut app, "{ 0: {}, 1: {} }").unwrap();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.spawn(handle.clone(), None);
+        spawner.spawn(handle, None);
+        app.update();
+
+        let stats = app.world.resource::<PrefabSpawnerStats>();
+        assert_eq!(stats.instances, 2);
+        assert_eq!(stats.pending, 0);
+        assert_eq!(stats.spawned, 2);
+        assert_eq!(stats.spawned_entities, 4);
+        let store = app.world.resource::<DiagnosticsStore>();
+        let value = |id| store.get(id).and_then(|diagnostic| diagnostic.value());
+        assert_eq!(value(PrefabDiagnosticsPlugin::INSTANCES), Some(2.0));
+        assert_eq!(value(PrefabDiagnosticsPlugin::SPAWN_ENTITIES), Some(2.0));
+        assert!(value(PrefabDiagnosticsPlugin::MAINTAIN_TIME).is_some());
+    }
+}
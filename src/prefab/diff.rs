@@ -0,0 +1,409 @@
+use super::{
+    clone_compact, write::is_hierarchy, Patch, PatchEntity, PatchParent, Prefab, PrefabEntity,
+    PrefabError,
+};
+use bevy::{
+    ecs::entity::Entity,
+    hierarchy::Parent,
+    reflect::{Reflect, ReflectRef, TypeRegistryInternal},
+    utils::{HashMap, HashSet},
+};
+
+/// Field-level changes between two values, keyed by reflect path.
+///
+/// It has the same shape as the per-component maps of [`PatchEntity::modify`](super::PatchEntity).
+pub type FieldDelta = HashMap<String, Box<dyn Reflect>>;
+
+/// Compute the fields of `b` that differ from `a`.
+///
+/// Applying every value of the returned delta to a clone of `a` at its path
+/// produces a value equal to `b`.
+/// Lists of a different length, mismatched enum variants and opaque values
+/// are replaced as a whole rather than diffed.
+/// If the types of `a` and `b` differ, the delta contains `b` at the empty path.
+pub fn reflect_diff(a: &dyn Reflect, b: &dyn Reflect) -> FieldDelta {
+    let mut delta = FieldDelta::default();
+    diff(&mut String::new(), a, b, &mut delta);
+    delta
+}
+
+impl Patch {
+    /// Compute the patch that turns `base` into `modified`.
+    ///
+    /// Entities are matched by id. Entities missing from `modified` are ignored,
+    /// new entities and components are appended, missing components are removed,
+    /// and components in both prefabs are modified field by field, see [`reflect_diff`].
+    /// Components replaced as a whole, like enums with another variant, are appended again.
+    /// Serialized components are deserialized, which fails for unregistered types.
+    pub fn diff(
+        base: &Prefab,
+        modified: &Prefab,
+        registry: &TypeRegistryInternal,
+    ) -> Result<Patch, PrefabError> {
+        let mut patch = Patch::default();
+
+        let ids: HashSet<u32> = modified.entities.iter().map(|e| e.entity).collect();
+        for entity in &base.entities {
+            if !ids.contains(&entity.entity) {
+                patch.ignore.insert(entity.entity);
+            }
+        }
+
+        let base: HashMap<u32, &PrefabEntity> =
+            base.entities.iter().map(|e| (e.entity, e)).collect();
+        for entity in &modified.entities {
+            let components = components_of(entity, registry)?;
+            let mut entry = PatchEntity {
+                entity: entity.entity,
+                uuid: None,
+                name: None,
+                append: Vec::new(),
+                modify: HashMap::default(),
+                remove: HashSet::default(),
+                parent: None,
+            };
+
+            let Some(base) = base.get(&entity.entity) else {
+                let append = components.iter().map(|c| clone_compact(*c, registry));
+                entry.append = append.collect();
+                patch.modify.push(entry);
+                continue;
+            };
+
+            let base = components_of(base, registry)?;
+            for component in &components {
+                let type_name = component.type_name();
+                match base.iter().find(|c| c.type_name() == type_name) {
+                    Some(base) => {
+                        let delta = reflect_diff(*base, *component);
+                        if delta.contains_key("") {
+                            // A changed variant or opaque value has no field to patch.
+                            entry.append.push(clone_compact(*component, registry));
+                        } else if !delta.is_empty() {
+                            entry.modify.insert(type_name.to_string(), delta);
+                        }
+                    }
+                    None => entry.append.push(clone_compact(*component, registry)),
+                }
+            }
+            for component in &base {
+                let type_name = component.type_name();
+                if !components.iter().any(|c| c.type_name() == type_name) {
+                    entry.remove.insert(type_name.to_string());
+                }
+            }
+
+            if !entry.append.is_empty() || !entry.modify.is_empty() || !entry.remove.is_empty() {
+                patch.modify.push(entry);
+            }
+        }
+
+        Ok(patch)
+    }
+
+    /// [`Patch::diff`] with the changes of the hierarchy recorded as [`PatchEntity::parent`]
+    /// overrides, rather than as changes of [`Parent`] and [`Children`](bevy::hierarchy::Children).
+    pub(crate) fn diff_overrides(
+        base: &Prefab,
+        modified: &Prefab,
+        registry: &TypeRegistryInternal,
+    ) -> Result<Patch, PrefabError> {
+        let mut patch = Patch::diff(base, modified, registry)?;
+        for entity in &mut patch.modify {
+            entity.append.retain(|c| !is_hierarchy(c.type_name()));
+            entity
+                .modify
+                .retain(|type_name, _| !is_hierarchy(type_name));
+            entity.remove.retain(|type_name| !is_hierarchy(type_name));
+        }
+
+        let mut parents = HashMap::default();
+        for entity in &base.entities {
+            parents.insert(entity.entity, parent_id(entity, registry)?);
+        }
+        for entity in &modified.entities {
+            let parent = parent_id(entity, registry)?;
+            if parents.get(&entity.entity).copied().flatten() == parent {
+                continue;
+            }
+            let index = patch.modify.iter().position(|e| e.entity == entity.entity);
+            let index = index.unwrap_or_else(|| {
+                patch.modify.push(PatchEntity::new(entity.entity));
+                patch.modify.len() - 1
+            });
+            patch.modify[index].parent =
+                Some(parent.map_or(PatchParent::Root, PatchParent::Entity));
+        }
+
+        patch.modify.retain(|entity| {
+            !entity.append.is_empty()
+                || !entity.modify.is_empty()
+                || !entity.remove.is_empty()
+                || entity.parent.is_some()
+        });
+        Ok(patch)
+    }
+}
+
+/// The prefab id of the parent of a prefab entity.
+fn parent_id(
+    entity: &PrefabEntity,
+    registry: &TypeRegistryInternal,
+) -> Result<Option<u32>, PrefabError> {
+    let type_name = std::any::type_name::<Parent>();
+    let components = components_of(entity, registry)?;
+    let parent = components.into_iter().find(|c| c.type_name() == type_name);
+    let parent = parent.and_then(|parent| match parent.reflect_ref() {
+        ReflectRef::TupleStruct(parent) => parent.field(0)?.downcast_ref::<Entity>().copied(),
+        _ => None,
+    });
+    Ok(parent.map(|parent| parent.index()))
+}
+
+/// All components of an entity, deserializing the serialized ones.
+pub(super) fn components_of<'a>(
+    entity: &'a PrefabEntity,
+    registry: &TypeRegistryInternal,
+) -> Result<Vec<&'a dyn Reflect>, PrefabError> {
+    let mut components: Vec<_> = entity.components.iter().map(AsRef::as_ref).collect();
+    for component in &entity.serialized {
+        components.push(component.materialize(registry)?);
+    }
+    Ok(components)
+}
+
+fn diff(path: &mut String, a: &dyn Reflect, b: &dyn Reflect, delta: &mut FieldDelta) {
+    if a.type_name() != b.type_name() {
+        delta.insert(path.clone(), b.clone_value());
+        return;
+    }
+
+    match (a.reflect_ref(), b.reflect_ref()) {
+        (ReflectRef::Struct(a), ReflectRef::Struct(b)) => {
+            for (index, field) in a.iter_fields().enumerate() {
+                let Some(name) = a.name_at(index) else {
+                    continue;
+                };
+                if let Some(other) = b.field(name) {
+                    diff_field(path, name, field, other, delta);
+                }
+            }
+        }
+        (ReflectRef::TupleStruct(a), ReflectRef::TupleStruct(b)) => {
+            for (index, field) in a.iter_fields().enumerate() {
+                if let Some(other) = b.field(index) {
+                    diff_field(path, &index.to_string(), field, other, delta);
+                }
+            }
+        }
+        (ReflectRef::Tuple(a), ReflectRef::Tuple(b)) => {
+            for (index, field) in a.iter_fields().enumerate() {
+                if let Some(other) = b.field(index) {
+                    diff_field(path, &index.to_string(), field, other, delta);
+                }
+            }
+        }
+        (ReflectRef::List(a), ReflectRef::List(b)) if a.len() == b.len() => {
+            for (index, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                diff_index(path, index, a, b, delta);
+            }
+        }
+        (ReflectRef::Array(a), ReflectRef::Array(b)) if a.len() == b.len() => {
+            for (index, (a, b)) in a.iter().zip(b.iter()).enumerate() {
+                diff_index(path, index, a, b, delta);
+            }
+        }
+        (ReflectRef::Enum(a), ReflectRef::Enum(b))
+            if a.variant_name() == b.variant_name() && a.variant_type() == b.variant_type() =>
+        {
+            for (index, field) in a.iter_fields().enumerate() {
+                match field.name() {
+                    Some(name) => {
+                        if let Some(other) = b.field(name) {
+                            diff_field(path, name, field.value(), other, delta);
+                        }
+                    }
+                    None => {
+                        if let Some(other) = b.field_at(index) {
+                            diff_field(path, &index.to_string(), field.value(), other, delta);
+                        }
+                    }
+                }
+            }
+        }
+        _ => {
+            if a.reflect_partial_eq(b) != Some(true) {
+                delta.insert(path.clone(), b.clone_value());
+            }
+        }
+    }
+}
+
+fn diff_field(
+    path: &mut String,
+    name: &str,
+    a: &dyn Reflect,
+    b: &dyn Reflect,
+    delta: &mut FieldDelta,
+) {
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(name);
+    diff(path, a, b, delta);
+    path.truncate(len);
+}
+
+fn diff_index(
+    path: &mut String,
+    index: usize,
+    a: &dyn Reflect,
+    b: &dyn Reflect,
+    delta: &mut FieldDelta,
+) {
+    let len = path.len();
+    path.push_str(&format!("[{}]", index));
+    diff(path, a, b, delta);
+    path.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reflect_diff;
+    use crate::prefab::{Patch, Prefab, PrefabEntity};
+    use bevy::reflect::{GetPath, Reflect, TypeRegistryInternal};
+
+    #[derive(Reflect, Default, Clone, PartialEq, Debug)]
+    struct Inner {
+        a: f32,
+        b: String,
+    }
+
+    #[derive(Reflect, Default, Clone, PartialEq, Debug)]
+    enum Shape {
+        #[default]
+        Empty,
+        Circle {
+            radius: f32,
+        },
+    }
+
+    #[derive(Reflect, Default, Clone, PartialEq, Debug)]
+    struct Outer {
+        inner: Inner,
+        list: Vec<u32>,
+        shape: Shape,
+    }
+
+    #[test]
+    fn equal_values_have_no_delta() {
+        let value = Outer::default();
+        assert!(reflect_diff(&value, &value.clone()).is_empty());
+    }
+
+    #[test]
+    fn nested_fields() {
+        let a = Outer {
+            list: vec![1, 2, 3],
+            shape: Shape::Circle { radius: 1.0 },
+            ..Default::default()
+        };
+        let mut b = a.clone();
+        b.inner.b = String::from("changed");
+        b.list[1] = 5;
+        b.shape = Shape::Circle { radius: 2.0 };
+
+        let delta = reflect_diff(&a, &b);
+        let mut keys: Vec<_> = delta.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["inner.b", "list[1]", "shape.radius"]);
+
+        let mut patched = a.clone();
+        for (path, value) in &delta {
+            patched
+                .reflect_path_mut(path)
+                .unwrap()
+                .apply(value.as_ref());
+        }
+        assert_eq!(patched, b);
+    }
+
+    #[test]
+    fn replace_whole_values() {
+        let a = Outer {
+            list: vec![1, 2, 3],
+            ..Default::default()
+        };
+        let b = Outer {
+            list: vec![1],
+            shape: Shape::Circle { radius: 1.0 },
+            ..Default::default()
+        };
+
+        let delta = reflect_diff(&a, &b);
+        assert_eq!(delta.len(), 2);
+        assert!(delta["list"].reflect_partial_eq(&vec![1u32]).unwrap());
+        assert!(delta["shape"]
+            .reflect_partial_eq(&Shape::Circle { radius: 1.0 })
+            .unwrap());
+    }
+
+    #[test]
+    fn diff_prefabs() {
+        let mut registry = TypeRegistryInternal::default();
+        registry.register::<Inner>();
+        registry.register::<Outer>();
+        registry.register::<Shape>();
+
+        let entity =
+            |entity, components: Vec<Box<dyn Reflect>>| PrefabEntity::new(entity, components);
+        let base = Prefab {
+            entities: vec![
+                entity(
+                    0,
+                    vec![
+                        Box::new(Outer::default()),
+                        Box::new(Inner::default()),
+                        Box::new(Shape::Empty),
+                    ],
+                ),
+                entity(1, vec![Box::new(Outer::default())]),
+            ],
+        };
+        let changed = Outer {
+            list: vec![1],
+            ..Default::default()
+        };
+        let modified = Prefab {
+            entities: vec![
+                entity(
+                    0,
+                    vec![
+                        Box::new(changed.clone()),
+                        Box::new(Shape::Circle { radius: 1.0 }),
+                    ],
+                ),
+                entity(2, vec![Box::new(Inner::default())]),
+            ],
+        };
+
+        let patch = Patch::diff(&base, &modified, &registry).unwrap();
+        assert_eq!(patch.ignore.iter().collect::<Vec<_>>(), [&1]);
+        assert_eq!(patch.modify.len(), 2);
+
+        let first = patch.modify.iter().find(|e| e.entity == 0).unwrap();
+        let outer = &first.modify[std::any::type_name::<Outer>()];
+        assert!(outer["list"].reflect_partial_eq(&vec![1u32]).unwrap());
+        assert!(first.remove.contains(std::any::type_name::<Inner>()));
+        // The variant changed, the whole component is replaced rather than patched at "".
+        assert!(!first.modify.contains_key(std::any::type_name::<Shape>()));
+        assert_eq!(first.append.len(), 1);
+        assert!(first.append[0]
+            .reflect_partial_eq(&Shape::Circle { radius: 1.0 })
+            .unwrap());
+
+        let appended = patch.modify.iter().find(|e| e.entity == 2).unwrap();
+        assert_eq!(appended.append.len(), 1);
+    }
+}
@@ -0,0 +1,208 @@
+use super::{Patch, Prefab, PrefabError, PrefabInstance, PrefabSpawner};
+use bevy::{
+    asset::Handle,
+    ecs::{
+        entity::Entity,
+        event::{Event, EventReader},
+        system::{Command, ResMut},
+        world::World,
+    },
+};
+use std::sync::Arc;
+
+/// Request to spawn a prefab, handled by [`prefab_event_system`].
+///
+/// It is the same as calling [`PrefabSpawner::spawn_with_patch`],
+/// or [`PrefabSpawner::spawn_with_id`] when an id is given.
+///
+/// It is also a [`Command`], queuing the spawn when the commands are applied:
+/// `commands.add(SpawnPrefab::new(handle))`.
+/// Give it an id to refer to the instance before that.
+#[derive(Event, Clone)]
+pub struct SpawnPrefab {
+    pub handle: Handle<Prefab>,
+    pub parent: Option<Entity>,
+    pub patch: Patch,
+    pub id: Option<PrefabInstance>,
+}
+
+impl SpawnPrefab {
+    pub fn new(handle: Handle<Prefab>) -> Self {
+        Self {
+            handle,
+            parent: None,
+            patch: Patch::default(),
+            id: None,
+        }
+    }
+
+    pub fn with_parent(mut self, parent: Entity) -> Self {
+        self.parent = Some(parent);
+        self
+    }
+
+    pub fn with_patch(mut self, patch: Patch) -> Self {
+        self.patch = patch;
+        self
+    }
+
+    pub fn with_id(mut self, id: PrefabInstance) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    fn queue(self, spawner: &mut PrefabSpawner) -> PrefabInstance {
+        let SpawnPrefab {
+            handle,
+            parent,
+            patch,
+            id,
+        } = self;
+        match id {
+            Some(id) => spawner.spawn_with_id(id, handle, parent, patch),
+            None => spawner.spawn_with_patch(handle, parent, patch),
+        }
+    }
+}
+
+impl Command for SpawnPrefab {
+    fn apply(self, world: &mut World) {
+        self.queue(&mut world.resource_mut::<PrefabSpawner>());
+    }
+}
+
+/// Request to despawn a prefab instance, handled by [`prefab_event_system`].
+///
+/// It is also a [`Command`], the same as calling [`PrefabSpawner::despawn`].
+#[derive(Event)]
+pub struct DespawnPrefab {
+    pub instance: PrefabInstance,
+}
+
+impl Command for DespawnPrefab {
+    fn apply(self, world: &mut World) {
+        world
+            .resource_mut::<PrefabSpawner>()
+            .despawn(&self.instance);
+    }
+}
+
+/// Sent by the [`PrefabSpawner`] when spawning, updating or patching an instance fails.
+///
+/// The error is also logged, and kept in the [`PrefabInstanceState`](super::PrefabInstanceState)
+/// of instances that failed to spawn or update.
+#[derive(Event, Clone, Debug)]
+pub struct PrefabSpawnError {
+    pub instance: PrefabInstance,
+    pub handle: Handle<Prefab>,
+    pub error: Arc<PrefabError>,
+}
+
+/// Sent by the [`PrefabSpawner`] when the prefab asset of an instance is removed,
+/// see [`PrefabRemovalPolicy::Orphan`](super::PrefabRemovalPolicy::Orphan).
+#[derive(Event, Clone, Debug)]
+pub struct PrefabOrphaned {
+    pub instance: PrefabInstance,
+    /// The removed asset, the handle is weak.
+    pub handle: Handle<Prefab>,
+}
+
+/// Sent by the [`PrefabSpawner`] when a queued instance is spawned.
+#[derive(Event, Clone, Debug)]
+pub struct PrefabInstanceReady {
+    pub instance: PrefabInstance,
+    /// See [`PrefabInstanceInfo::root`](super::PrefabInstanceInfo::root).
+    pub root: Option<Entity>,
+    /// Number of entities of the instance.
+    pub entities: usize,
+}
+
+/// System that queues spawns and despawns requested through [`SpawnPrefab`] and [`DespawnPrefab`].
+pub fn prefab_event_system(
+    mut spawn: EventReader<SpawnPrefab>,
+    mut despawn: EventReader<DespawnPrefab>,
+    mut spawner: ResMut<PrefabSpawner>,
+) {
+    for event in despawn.iter() {
+        spawner.despawn(&event.instance);
+    }
+
+    for event in spawn.iter() {
+        event.clone().queue(&mut spawner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DespawnPrefab, PrefabInstanceReady, SpawnPrefab};
+    use crate::prefab::{test_utils, PrefabInstance, PrefabSpawner};
+    use bevy::ecs::system::{CommandQueue, Commands};
+    use bevy::ecs::{
+        component::Component, entity::Entity, event::Events, reflect::ReflectComponent,
+    };
+    use bevy::reflect::Reflect;
+    use bevy::utils::Uuid;
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Health(u32);
+
+    #[test]
+    fn spawn_from_event() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Health>();
+
+        let ron = format!(
+            "{{ 0: {{ \"{}\": (3) }} }}",
+            std::any::type_name::<Health>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+
+        app.world.send_event(SpawnPrefab::new(handle.clone()));
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.instances().count(), 1);
+        let mut query = app.world.query::<(Entity, &Health)>();
+        let (entity, health) = query.single(&app.world);
+        assert_eq!(health, &Health(3));
+
+        let events = app.world.resource::<Events<PrefabInstanceReady>>();
+        let ready: Vec<_> = events.get_reader().iter(events).cloned().collect();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].root, Some(entity));
+        assert_eq!(ready[0].entities, 1);
+    }
+
+    #[test]
+    fn spawn_from_commands() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Health>();
+
+        let ron = format!(
+            "{{ 0: {{ \"{}\": (3) }} }}",
+            std::any::type_name::<Health>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+
+        let instance = PrefabInstance::from_uuid(Uuid::from_u128(1));
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        commands.add(SpawnPrefab::new(handle).with_id(instance));
+        queue.apply(&mut app.world);
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&instance).unwrap().is_ready());
+        test_utils::assert_component(&app, &instance, 0, &Health(3));
+
+        let mut commands = Commands::new(&mut queue, &app.world);
+        commands.add(DespawnPrefab { instance });
+        queue.apply(&mut app.world);
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&instance).is_none());
+        assert_eq!(app.world.query::<&Health>().iter(&app.world).count(), 0);
+    }
+}
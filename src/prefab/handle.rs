@@ -0,0 +1,336 @@
+use super::serde::FieldName;
+use bevy::asset::{Asset, AssetPath, AssetServer, Handle, HandleId};
+use bevy::reflect::{
+    DynamicArray, DynamicEnum, DynamicList, DynamicStruct, DynamicTuple, DynamicTupleStruct,
+    DynamicVariant, FromType, GetPath, Reflect, ReflectDeserialize, ReflectRef, ReflectSerialize,
+    TypeInfo, TypeRegistryInternal, VariantInfo, VariantType,
+};
+use serde::de::{IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+
+/// Marks the `Handle<T>` types written as the path of their asset, see [`register_handle_path`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ReflectHandlePath;
+
+/// Write handles to assets of type `T` in prefabs as the path of their asset.
+///
+/// Fields of type `Handle<T>` are then read from paths like `"models/tree.glb"`,
+/// or from their raw id like `(id: AssetPathId(...))`, as a [`HandlePath`].
+/// The [`PrefabLoader`](super::PrefabLoader) loads the paths as dependencies of the prefab.
+///
+/// Handles are only written as paths by a [`PrefabSerializer`](super::PrefabSerializer)
+/// given the [`AssetServer`] that loaded them, as it knows their path.
+/// Handles inside of maps are left as they are.
+pub fn register_handle_path<T: Asset>(registry: &mut TypeRegistryInternal) {
+    registry.register::<Handle<T>>();
+    registry.register::<HandlePath>();
+    registry.register::<HandleId>();
+    let registration = registry.get_mut(TypeId::of::<Handle<T>>()).unwrap();
+    registration.insert(ReflectHandlePath);
+    registration.insert(<ReflectDeserialize as FromType<HandlePath>>::from_type());
+}
+
+/// A handle read from or written to a prefab as the path of its asset.
+///
+/// It applies to `Handle<T>` fields like a weak handle with the same id would.
+#[derive(Reflect, Clone, Debug, PartialEq)]
+#[reflect(Serialize, Deserialize)]
+pub struct HandlePath {
+    id: HandleId,
+    #[reflect(ignore)]
+    path: Option<AssetPath<'static>>,
+}
+
+impl HandlePath {
+    pub fn new(path: AssetPath<'static>) -> Self {
+        Self {
+            id: HandleId::from(path.clone()),
+            path: Some(path),
+        }
+    }
+
+    pub fn id(&self) -> HandleId {
+        self.id
+    }
+
+    /// The path of the asset, unless the handle was read from its raw id.
+    pub fn path(&self) -> Option<&AssetPath<'static>> {
+        self.path.as_ref()
+    }
+}
+
+impl Serialize for HandlePath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        if let Some(path) = &self.path {
+            let mut text = path.path().to_string_lossy().replace('\\', "/");
+            if let Some(label) = path.label() {
+                text.push('#');
+                text.push_str(label);
+            }
+            return serializer.serialize_str(&text);
+        }
+        let mut state = serializer.serialize_struct("Handle", 1)?;
+        state.serialize_field("id", &self.id)?;
+        state.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HandlePath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(HandlePathVisitor)
+    }
+}
+
+struct HandlePathVisitor;
+
+impl<'de> Visitor<'de> for HandlePathVisitor {
+    type Value = HandlePath;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("asset path or handle")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, value: &str) -> Result<Self::Value, E> {
+        Ok(HandlePath::new(AssetPath::from(value).to_owned()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut id = None;
+        while let Some(FieldName(key)) = map.next_key()? {
+            match &*key {
+                "id" => id = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        let id = id.ok_or_else(|| serde::de::Error::missing_field("id"))?;
+        Ok(HandlePath { id, path: None })
+    }
+}
+
+/// Replace the handles in `value` by their [`HandlePath`], when `asset_server` knows it.
+///
+/// Returns `None` when there is nothing to replace.
+pub(crate) fn with_handle_paths(
+    value: &dyn Reflect,
+    registry: &TypeRegistryInternal,
+    asset_server: &AssetServer,
+) -> Option<Box<dyn Reflect>> {
+    rewrite(value, &mut |value| {
+        registry
+            .get_with_name(value.type_name())?
+            .data::<ReflectHandlePath>()?;
+        let id = *value.reflect_path("id").ok()?.downcast_ref::<HandleId>()?;
+        let path = asset_server.get_handle_path(id)?.to_owned();
+        Some(Box::new(HandlePath::new(path)))
+    })
+}
+
+/// The paths of the [`HandlePath`] values in `value`.
+pub(crate) fn handle_paths(value: &dyn Reflect, paths: &mut Vec<AssetPath<'static>>) {
+    rewrite(value, &mut |value| {
+        let path = value.downcast_ref::<HandlePath>()?.path()?;
+        paths.push(path.clone());
+        None
+    });
+}
+
+/// Can values of this type hold handles written as paths, see [`register_handle_path`]?
+///
+/// Like when handles are written, maps are not looked into.
+pub(crate) fn has_handle_paths(type_id: TypeId, registry: &TypeRegistryInternal) -> bool {
+    fn visit(type_id: TypeId, registry: &TypeRegistryInternal, visited: &mut Vec<TypeId>) -> bool {
+        // Recursive types are only visited once.
+        if visited.contains(&type_id) {
+            return false;
+        }
+        visited.push(type_id);
+
+        let Some(registration) = registry.get(type_id) else {
+            return false;
+        };
+        if registration.data::<ReflectHandlePath>().is_some() {
+            return true;
+        }
+        let fields: Vec<TypeId> = match registration.type_info() {
+            TypeInfo::Struct(info) => info.iter().map(|field| field.type_id()).collect(),
+            TypeInfo::TupleStruct(info) => info.iter().map(|field| field.type_id()).collect(),
+            TypeInfo::Tuple(info) => info.iter().map(|field| field.type_id()).collect(),
+            TypeInfo::List(info) => vec![info.item_type_id()],
+            TypeInfo::Array(info) => vec![info.item_type_id()],
+            TypeInfo::Enum(info) => (info.iter())
+                .flat_map(|variant| match variant {
+                    VariantInfo::Struct(variant) => {
+                        variant.iter().map(|field| field.type_id()).collect()
+                    }
+                    VariantInfo::Tuple(variant) => {
+                        variant.iter().map(|field| field.type_id()).collect()
+                    }
+                    VariantInfo::Unit(_) => Vec::new(),
+                })
+                .collect(),
+            TypeInfo::Map(_) | TypeInfo::Value(_) => Vec::new(),
+        };
+        fields
+            .into_iter()
+            .any(|field| visit(field, registry, visited))
+    }
+
+    visit(type_id, registry, &mut Vec::new())
+}
+
+/// Replaces a value visited by [`rewrite`], or returns `None` to look into it.
+type Rewriter<'a> = &'a mut dyn FnMut(&dyn Reflect) -> Option<Box<dyn Reflect>>;
+
+/// Rebuild `value` with the values replaced by `f`.
+///
+/// Returns `None` when `f` replaced nothing. Maps are not visited.
+fn rewrite(value: &dyn Reflect, f: Rewriter) -> Option<Box<dyn Reflect>> {
+    if let Some(value) = f(value) {
+        return Some(value);
+    }
+
+    let represented = value.get_represented_type_info();
+    match value.reflect_ref() {
+        ReflectRef::Struct(value) => {
+            let fields = rewrite_all(value.iter_fields(), f)?;
+            let mut rewritten = DynamicStruct::default();
+            rewritten.set_represented_type(represented);
+            for (index, field) in fields.into_iter().enumerate() {
+                rewritten.insert_boxed(value.name_at(index).unwrap(), field);
+            }
+            Some(Box::new(rewritten))
+        }
+        ReflectRef::TupleStruct(value) => {
+            let fields = rewrite_all(value.iter_fields(), f)?;
+            let mut rewritten = DynamicTupleStruct::default();
+            rewritten.set_represented_type(represented);
+            fields
+                .into_iter()
+                .for_each(|field| rewritten.insert_boxed(field));
+            Some(Box::new(rewritten))
+        }
+        ReflectRef::Tuple(value) => {
+            let fields = rewrite_all(value.iter_fields(), f)?;
+            let mut rewritten = DynamicTuple::default();
+            rewritten.set_represented_type(represented);
+            fields
+                .into_iter()
+                .for_each(|field| rewritten.insert_boxed(field));
+            Some(Box::new(rewritten))
+        }
+        ReflectRef::List(value) => {
+            let items = rewrite_all(value.iter(), f)?;
+            let mut rewritten = DynamicList::default();
+            rewritten.set_represented_type(represented);
+            items.into_iter().for_each(|item| rewritten.push_box(item));
+            Some(Box::new(rewritten))
+        }
+        ReflectRef::Array(value) => {
+            let items = rewrite_all(value.iter(), f)?;
+            let mut rewritten = DynamicArray::new(items.into_boxed_slice());
+            rewritten.set_represented_type(represented);
+            Some(Box::new(rewritten))
+        }
+        ReflectRef::Enum(value) => {
+            let fields = rewrite_all(value.iter_fields().map(|field| field.value()), f)?;
+            let variant = match value.variant_type() {
+                VariantType::Struct => {
+                    let mut variant = DynamicStruct::default();
+                    for (index, field) in fields.into_iter().enumerate() {
+                        variant.insert_boxed(value.name_at(index).unwrap(), field);
+                    }
+                    DynamicVariant::Struct(variant)
+                }
+                VariantType::Tuple => {
+                    let mut variant = DynamicTuple::default();
+                    fields
+                        .into_iter()
+                        .for_each(|field| variant.insert_boxed(field));
+                    DynamicVariant::Tuple(variant)
+                }
+                VariantType::Unit => DynamicVariant::Unit,
+            };
+            let name = value.variant_name();
+            let mut rewritten = DynamicEnum::new_with_index(value.variant_index(), name, variant);
+            rewritten.set_represented_type(represented);
+            Some(Box::new(rewritten))
+        }
+        ReflectRef::Map(_) | ReflectRef::Value(_) => None,
+    }
+}
+
+/// Rewrite all values, cloning the ones `f` didn't replace. `None` if it replaced nothing.
+fn rewrite_all<'a>(
+    values: impl Iterator<Item = &'a dyn Reflect>,
+    f: Rewriter,
+) -> Option<Vec<Box<dyn Reflect>>> {
+    let values: Vec<_> = values.map(|value| (value, rewrite(value, f))).collect();
+    if values.iter().all(|(_, rewritten)| rewritten.is_none()) {
+        return None;
+    }
+
+    let values = values
+        .into_iter()
+        .map(|(value, rewritten)| rewritten.unwrap_or_else(|| value.clone_value()));
+    Some(values.collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::register_handle_path;
+    use crate::prefab::Prefab;
+    use bevy::asset::{AssetPath, Handle, HandleId};
+    use bevy::ecs::reflect::AppTypeRegistry;
+    use bevy::reflect::{Reflect, TypePath, TypeUuid};
+
+    #[derive(TypeUuid, TypePath)]
+    #[uuid = "5b9c1f4e-8a0d-4d36-9d2e-7f1c3a6b2e90"]
+    struct Mesh;
+
+    #[derive(Reflect, Default)]
+    struct Tree {
+        mesh: Handle<Mesh>,
+    }
+
+    #[test]
+    fn handle_from_path() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Tree>();
+        register_handle_path::<Mesh>(&mut registry.write());
+
+        let input = format!(
+            "{{ 0: {{ \"{}\": (mesh: \"models/tree.glb#Mesh0\") }} }}",
+            std::any::type_name::<Tree>()
+        );
+        let mut prefab = Prefab::deserialize_ron(input.as_bytes(), &registry.0).unwrap();
+        let path = AssetPath::from("models/tree.glb#Mesh0");
+        assert_eq!(prefab.asset_paths(&registry.read()), vec![path.clone()]);
+        let saved = prefab.serialize_ron(&registry).unwrap();
+        assert!(saved.contains("mesh: \"models/tree.glb#Mesh0\""));
+
+        prefab.compact(&registry.read());
+        let tree = prefab.entities[0].components[0].downcast_ref::<Tree>();
+        assert_eq!(tree.unwrap().mesh.id(), HandleId::from(path.clone()));
+
+        // Lazy components holding handles are deserialized to find their paths.
+        let lazy = Prefab::deserialize_ron_lazy(input.as_bytes()).unwrap();
+        assert_eq!(lazy.asset_paths(&registry.read()), vec![path.clone()]);
+        assert!(lazy.entities[0].serialized[0].is_materialized());
+
+        // Without an asset server, handles are written and read back as raw ids.
+        let saved = prefab.serialize_ron(&registry).unwrap();
+        let mut prefab = Prefab::deserialize_ron(saved.as_bytes(), &registry.0).unwrap();
+        assert!(prefab.asset_paths(&registry.read()).is_empty());
+        prefab.compact(&registry.read());
+        let tree = prefab.entities[0].components[0].downcast_ref::<Tree>();
+        assert_eq!(
+            tree.unwrap().mesh.id(),
+            HandleId::from("models/tree.glb#Mesh0")
+        );
+    }
+}
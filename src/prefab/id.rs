@@ -0,0 +1,253 @@
+use super::{Patch, PatchEntity, Prefab, PrefabEntity};
+use bevy::core::Name;
+use bevy::ecs::{component::Component, reflect::ReflectComponent};
+use bevy::reflect::{FromReflect, GetPath, Reflect, ReflectDeserialize, ReflectSerialize};
+use bevy::utils::Uuid;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// A stable identifier of a prefab entity.
+///
+/// The `u32` ids of prefab entities are only positions in the file,
+/// extracting a world again with the [`PrefabBuilder`](super::PrefabBuilder) renumbers them.
+/// This component is kept by spawned entities and extracted with them,
+/// so patches can target it with [`PatchEntity::uuid`](super::PatchEntity::uuid) instead.
+#[derive(
+    Component, Reflect, Serialize, Deserialize, Clone, Copy, Default, Debug, PartialEq, Eq, Hash,
+)]
+#[reflect(Component, Serialize, Deserialize, PartialEq, Hash)]
+pub struct PrefabUuid(pub Uuid);
+
+impl PrefabUuid {
+    /// A new random identifier.
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+/// A well-known entity of a prefab, like the muzzle of a weapon or a socket to attach items to.
+///
+/// Code finds labeled entities with [`PrefabInstanceInfo::find_by_label`],
+/// without relying on entity ids, which change as the prefab is edited,
+/// or on [`Name`]s, which are free to change for the sake of the editor.
+///
+/// [`PrefabInstanceInfo::find_by_label`]: super::PrefabInstanceInfo::find_by_label
+#[derive(
+    Component, Reflect, Serialize, Deserialize, Clone, Default, Debug, PartialEq, Eq, Hash,
+)]
+#[reflect(Component, Serialize, Deserialize, PartialEq, Hash)]
+pub struct PrefabLabel(pub String);
+
+impl PrefabEntity {
+    /// The stable identifier of this entity, if it has one.
+    pub fn uuid(&self) -> Option<PrefabUuid> {
+        let type_name = std::any::type_name::<PrefabUuid>();
+        let component = self.components.iter().find(|c| c.type_name() == type_name);
+        if let Some(component) = component {
+            return PrefabUuid::from_reflect(component.as_ref());
+        }
+
+        // Lazy components are parsed without the registry.
+        let serialized = self
+            .serialized
+            .iter()
+            .find(|c| c.type_name() == type_name)?;
+        ron::de::from_str(serialized.data()).ok()
+    }
+
+    /// The [`PrefabLabel`] of this entity, if it has one.
+    pub fn label(&self) -> Option<PrefabLabel> {
+        let type_name = std::any::type_name::<PrefabLabel>();
+        let component = self.components.iter().find(|c| c.type_name() == type_name);
+        if let Some(component) = component {
+            return PrefabLabel::from_reflect(component.as_ref());
+        }
+
+        let serialized = self
+            .serialized
+            .iter()
+            .find(|c| c.type_name() == type_name)?;
+        ron::de::from_str(serialized.data()).ok()
+    }
+
+    /// The [`Name`] of this entity, if it has one.
+    pub fn name(&self) -> Option<String> {
+        let type_name = std::any::type_name::<Name>();
+        let component = self.components.iter().find(|c| c.type_name() == type_name);
+        if let Some(component) = component {
+            let name = component.reflect_path("name").ok()?;
+            return Some(name.downcast_ref::<Cow<'static, str>>()?.to_string());
+        }
+
+        #[derive(Deserialize)]
+        struct Named {
+            name: String,
+        }
+        let serialized = self
+            .serialized
+            .iter()
+            .find(|c| c.type_name() == type_name)?;
+        let named: Named = ron::de::from_str(serialized.data()).ok()?;
+        Some(named.name)
+    }
+}
+
+impl Prefab {
+    /// Find the id of the first entity with the given [`Name`].
+    pub fn entity_by_name(&self, name: &str) -> Option<u32> {
+        self.entities
+            .iter()
+            .find(|entity| entity.name().as_deref() == Some(name))
+            .map(|entity| entity.entity)
+    }
+
+    /// Find the id of the first entity with the given [`PrefabLabel`].
+    pub fn entity_by_label(&self, label: &str) -> Option<u32> {
+        self.entities
+            .iter()
+            .find(|entity| entity.label().is_some_and(|l| l.0 == label))
+            .map(|entity| entity.entity)
+    }
+
+    /// Find the id of the entity with the given stable identifier.
+    pub fn entity_by_uuid(&self, uuid: PrefabUuid) -> Option<u32> {
+        self.entities
+            .iter()
+            .find(|entity| entity.uuid() == Some(uuid))
+            .map(|entity| entity.entity)
+    }
+}
+
+impl Patch {
+    /// Retarget the entities patched by uuid or by name to their id in `prefab`.
+    ///
+    /// The uuid is looked up first, then the name.
+    /// Entities that are not found keep their id and are patched by it.
+    pub(crate) fn resolve(&self, prefab: &Prefab) -> Cow<'_, Self> {
+        let by_id = |patch: &PatchEntity| patch.uuid.is_none() && patch.name.is_none();
+        if self.modify.iter().all(by_id) {
+            return Cow::Borrowed(self);
+        }
+
+        let mut patch = self.clone();
+        for patch in &mut patch.modify {
+            let uuid = patch
+                .uuid
+                .take()
+                .and_then(|uuid| prefab.entity_by_uuid(uuid));
+            let name = patch.name.take();
+            let name = || prefab.entity_by_name(name.as_deref()?);
+            if let Some(entity) = uuid.or_else(name) {
+                patch.entity = entity;
+            }
+        }
+        Cow::Owned(patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrefabLabel, PrefabUuid};
+    use crate::prefab::{write_to_world, Patch, PatchEntity, Prefab, PrefabBuilder};
+    use bevy::core::Name;
+    use bevy::ecs::{entity::EntityMap, reflect::AppTypeRegistry, world::World};
+    use bevy::utils::{default, Uuid};
+    use std::borrow::Cow;
+
+    #[test]
+    fn patch_by_uuid() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<PrefabUuid>();
+        registry.write().register::<Uuid>();
+        let mut world = World::new();
+        world.insert_resource(registry.clone());
+
+        let first = PrefabUuid::new();
+        let second = PrefabUuid::new();
+        world.spawn_empty();
+        world.spawn(first);
+        world.spawn(second);
+        let mut builder = PrefabBuilder::from_world(&world).with_uuids();
+        builder.extract_entities(world.iter_entities().map(|entity| entity.id()));
+        let prefab = builder.build();
+        assert_eq!(prefab.entities.len(), 3);
+        assert!(prefab.entities.iter().all(|entity| entity.uuid().is_some()));
+
+        let text = prefab.serialize_ron(&registry).unwrap();
+        let prefab = Prefab::deserialize_ron(text.as_bytes(), &registry.0).unwrap();
+        let id = prefab.entity_by_uuid(second).unwrap();
+        assert_eq!(prefab.entity_by_uuid(first), Some(1));
+        assert_eq!(id, 2);
+
+        // The id in the patch is stale, the uuid wins.
+        let mut patch = Patch::default();
+        patch.modify.push(PatchEntity {
+            entity: 1,
+            uuid: Some(second),
+            name: None,
+            append: Vec::new(),
+            modify: default(),
+            remove: default(),
+            parent: None,
+        });
+        let resolved = patch.resolve(&prefab);
+        assert_eq!(resolved.modify[0].entity, id);
+
+        let lazy = Prefab::deserialize_ron_lazy(text.as_bytes()).unwrap();
+        assert_eq!(lazy.entity_by_uuid(second), Some(id));
+
+        let mut target = World::new();
+        target.insert_resource(registry);
+        let mut entity_map = EntityMap::default();
+        write_to_world(&Patch::default(), &prefab, &mut target, &mut entity_map).unwrap();
+        let mut query = target.query::<&PrefabUuid>();
+        assert_eq!(query.iter(&target).count(), 3);
+    }
+
+    #[test]
+    fn patch_by_name() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Name>();
+        registry.write().register::<Cow<'static, str>>();
+        let name = std::any::type_name::<Name>();
+        let text = format!("{{ 3: {{ {:?}: (name: \"weapon\") }} }}", name);
+        let prefab = Prefab::deserialize_ron(text.as_bytes(), &registry.0).unwrap();
+        let lazy = Prefab::deserialize_ron_lazy(text.as_bytes()).unwrap();
+        assert_eq!(prefab.entity_by_name("weapon"), Some(3));
+        assert_eq!(lazy.entity_by_name("weapon"), Some(3));
+
+        let mut patch = Patch::default();
+        for name in ["weapon", "shield"] {
+            patch.modify.push(PatchEntity {
+                entity: 0,
+                uuid: None,
+                name: Some(String::from(name)),
+                append: Vec::new(),
+                modify: default(),
+                remove: default(),
+                parent: None,
+            });
+        }
+        let resolved = patch.resolve(&prefab);
+        assert_eq!(resolved.modify[0].entity, 3);
+        assert_eq!(resolved.modify[1].entity, 0);
+    }
+
+    #[test]
+    fn find_by_label() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<PrefabLabel>();
+        let label = std::any::type_name::<PrefabLabel>();
+        let text = format!(
+            "{{ 0: {{}}, 2: {{ {0:?}: (\"Muzzle\") }}, 3: {{ {0:?}: (\"Grip\") }} }}",
+            label
+        );
+        let prefab = Prefab::deserialize_ron(text.as_bytes(), &registry.0).unwrap();
+        let lazy = Prefab::deserialize_ron_lazy(text.as_bytes()).unwrap();
+        assert_eq!(prefab.entity_by_label("Muzzle"), Some(2));
+        assert_eq!(lazy.entity_by_label("Grip"), Some(3));
+        assert_eq!(prefab.entity_by_label("Stock"), None);
+        let muzzle = PrefabLabel(String::from("Muzzle"));
+        assert_eq!(prefab.entities[1].label(), Some(muzzle));
+    }
+}
@@ -0,0 +1,61 @@
+use bevy::reflect::{TypeRegistration, TypeRegistryInternal};
+use bevy::utils::HashMap;
+use std::any::TypeId;
+use std::sync::{Arc, OnceLock, RwLock};
+
+static NAMES: OnceLock<RwLock<HashMap<TypeId, Arc<str>>>> = OnceLock::new();
+
+/// The name of a registered type, shared with every other caller asking for the same type.
+///
+/// Errors and serialized components about the same type point to a single allocation,
+/// instead of a copy of the name per entity. Names are kept once per type,
+/// so they are bounded by the registered types.
+pub fn intern_type_name(registration: &TypeRegistration) -> Arc<str> {
+    let names = NAMES.get_or_init(Default::default);
+    let type_id = registration.type_id();
+
+    if let Some(name) = names.read().unwrap().get(&type_id) {
+        return name.clone();
+    }
+
+    let mut names = names.write().unwrap();
+    let name = names.entry(type_id);
+    name.or_insert_with(|| Arc::from(registration.type_name()))
+        .clone()
+}
+
+/// The shared name of the type called `type_name`, or a new one if it isn't registered.
+pub(crate) fn shared_type_name(registry: &TypeRegistryInternal, type_name: &str) -> Arc<str> {
+    match registry.get_with_name(type_name) {
+        Some(registration) => intern_type_name(registration),
+        None => Arc::from(type_name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::shared_type_name;
+    use bevy::reflect::{Reflect, TypeRegistryInternal};
+    use std::sync::Arc;
+
+    #[derive(Reflect)]
+    struct Health(u32);
+
+    #[test]
+    fn same_type_same_name() {
+        let mut registry = TypeRegistryInternal::default();
+        registry.register::<Health>();
+
+        let type_name = std::any::type_name::<Health>();
+        let a = shared_type_name(&registry, &String::from(type_name));
+        let b = shared_type_name(&registry, type_name);
+        assert!(Arc::ptr_eq(&a, &b));
+        assert_eq!(&*a, type_name);
+
+        let unregistered = shared_type_name(&registry, "game::Missing");
+        assert!(!Arc::ptr_eq(
+            &unregistered,
+            &shared_type_name(&registry, "game::Missing")
+        ));
+    }
+}
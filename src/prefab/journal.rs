@@ -0,0 +1,469 @@
+use bevy::{
+    ecs::{
+        entity::Entity,
+        reflect::{AppTypeRegistry, ReflectComponent},
+        system::Resource,
+        world::World,
+    },
+    reflect::{GetPath, Reflect, TypeRegistryInternal},
+};
+
+/// A reversible mutation recorded by the [`Journal`].
+pub enum JournalOp {
+    /// An entity was spawned with the given components.
+    Spawn {
+        entity: Entity,
+        components: Vec<Box<dyn Reflect>>,
+    },
+    /// An entity was despawned, these are the components it had.
+    Despawn {
+        entity: Entity,
+        components: Vec<Box<dyn Reflect>>,
+    },
+    /// A component was inserted or replaced. `previous` is the value it had before, if any.
+    Insert {
+        entity: Entity,
+        component: Box<dyn Reflect>,
+        previous: Option<Box<dyn Reflect>>,
+    },
+    /// A component was removed, this is the value it had.
+    Remove {
+        entity: Entity,
+        component: Box<dyn Reflect>,
+    },
+}
+
+impl JournalOp {
+    /// The entity this operation applies to.
+    pub fn entity(&self) -> Entity {
+        match self {
+            JournalOp::Spawn { entity, .. } => *entity,
+            JournalOp::Despawn { entity, .. } => *entity,
+            JournalOp::Insert { entity, .. } => *entity,
+            JournalOp::Remove { entity, .. } => *entity,
+        }
+    }
+
+    fn remap(&mut self, from: Entity, to: Entity) {
+        let entity = match self {
+            JournalOp::Spawn { entity, .. } => entity,
+            JournalOp::Despawn { entity, .. } => entity,
+            JournalOp::Insert { entity, .. } => entity,
+            JournalOp::Remove { entity, .. } => entity,
+        };
+        if *entity == from {
+            *entity = to;
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum JournalError {
+    #[error("entity {0:?} does not exist")]
+    NoSuchEntity(Entity),
+    #[error("the component `{type_name}` is not registered with `#[reflect(Component)]`")]
+    UnregisteredComponent { type_name: String },
+    #[error("entity {entity:?} doesn't have the component `{type_name}`")]
+    MissingComponent { entity: Entity, type_name: String },
+    #[error("the component `{type_name}` doesn't have a field at `{path}`: {err}")]
+    WrongPath {
+        type_name: String,
+        path: String,
+        err: String,
+    },
+}
+
+/// Records reversible world mutations so they can be undone and redone.
+///
+/// Only changes made through the journal's own methods are recorded,
+/// all components are handled through reflection using the [`AppTypeRegistry`].
+/// Entities despawned and respawned by undo/redo get new ids,
+/// every recorded operation is remapped to follow them.
+#[derive(Default, Resource)]
+pub struct Journal {
+    undo: Vec<JournalOp>,
+    redo: Vec<JournalOp>,
+}
+
+impl Journal {
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget all recorded operations.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    /// Spawn a new entity with the given components.
+    pub fn spawn(
+        &mut self,
+        world: &mut World,
+        components: Vec<Box<dyn Reflect>>,
+    ) -> Result<Entity, JournalError> {
+        let entity = spawn_with(world, &components)?;
+        self.push(JournalOp::Spawn { entity, components });
+        Ok(entity)
+    }
+
+    /// Despawn an entity, remembering all of its reflected components.
+    pub fn despawn(&mut self, world: &mut World, entity: Entity) -> Result<(), JournalError> {
+        let components = capture(world, entity)?;
+        world.despawn(entity);
+        self.push(JournalOp::Despawn { entity, components });
+        Ok(())
+    }
+
+    /// Insert a component, replacing the current value if the entity already has one.
+    pub fn insert(
+        &mut self,
+        world: &mut World,
+        entity: Entity,
+        component: Box<dyn Reflect>,
+    ) -> Result<(), JournalError> {
+        let previous = insert(world, entity, component.as_ref())?;
+        self.push(JournalOp::Insert {
+            entity,
+            component,
+            previous,
+        });
+        Ok(())
+    }
+
+    /// Set a single field of an existing component.
+    pub fn set(
+        &mut self,
+        world: &mut World,
+        entity: Entity,
+        type_name: &str,
+        path: &str,
+        value: &dyn Reflect,
+    ) -> Result<(), JournalError> {
+        let mut component =
+            get(world, entity, type_name)?.ok_or_else(|| JournalError::MissingComponent {
+                entity,
+                type_name: type_name.to_string(),
+            })?;
+
+        let field = component.reflect_path_mut(path);
+        let field = field.map_err(|err| JournalError::WrongPath {
+            type_name: type_name.to_string(),
+            path: path.to_string(),
+            err: err.to_string(),
+        })?;
+        field.apply(value);
+
+        self.insert(world, entity, component)
+    }
+
+    /// Remove a component by its type name.
+    pub fn remove(
+        &mut self,
+        world: &mut World,
+        entity: Entity,
+        type_name: &str,
+    ) -> Result<(), JournalError> {
+        let component = remove(world, entity, type_name)?;
+        let component = component.ok_or_else(|| JournalError::MissingComponent {
+            entity,
+            type_name: type_name.to_string(),
+        })?;
+        self.push(JournalOp::Remove { entity, component });
+        Ok(())
+    }
+
+    /// Revert the last recorded operation.
+    ///
+    /// Returns `Ok(false)` if there is nothing to undo.
+    pub fn undo(&mut self, world: &mut World) -> Result<bool, JournalError> {
+        let Some(op) = self.undo.pop() else {
+            return Ok(false);
+        };
+
+        let result = match &op {
+            JournalOp::Spawn { entity, .. } => despawn(world, *entity).map(|()| None),
+            JournalOp::Despawn { components, .. } => spawn_with(world, components).map(Some),
+            JournalOp::Insert {
+                entity,
+                previous: Some(previous),
+                ..
+            } => insert(world, *entity, previous.as_ref()).map(|_| None),
+            JournalOp::Insert {
+                entity,
+                component,
+                previous: None,
+            } => remove(world, *entity, component.type_name()).map(|_| None),
+            JournalOp::Remove { entity, component } => {
+                insert(world, *entity, component.as_ref()).map(|_| None)
+            }
+        };
+
+        self.finish(op, result, true)
+    }
+
+    /// Re-apply the last undone operation.
+    ///
+    /// Returns `Ok(false)` if there is nothing to redo.
+    pub fn redo(&mut self, world: &mut World) -> Result<bool, JournalError> {
+        let Some(op) = self.redo.pop() else {
+            return Ok(false);
+        };
+
+        let result = match &op {
+            JournalOp::Spawn { components, .. } => spawn_with(world, components).map(Some),
+            JournalOp::Despawn { entity, .. } => despawn(world, *entity).map(|()| None),
+            JournalOp::Insert {
+                entity, component, ..
+            } => insert(world, *entity, component.as_ref()).map(|_| None),
+            JournalOp::Remove { entity, component } => {
+                remove(world, *entity, component.type_name()).map(|_| None)
+            }
+        };
+
+        self.finish(op, result, false)
+    }
+
+    fn push(&mut self, op: JournalOp) {
+        self.undo.push(op);
+        self.redo.clear();
+    }
+
+    fn finish(
+        &mut self,
+        mut op: JournalOp,
+        result: Result<Option<Entity>, JournalError>,
+        undo: bool,
+    ) -> Result<bool, JournalError> {
+        let (done, todo) = if undo {
+            (&mut self.redo, &mut self.undo)
+        } else {
+            (&mut self.undo, &mut self.redo)
+        };
+
+        match result {
+            Ok(respawned) => {
+                if let Some(to) = respawned {
+                    let from = op.entity();
+                    for op in std::iter::once(&mut op)
+                        .chain(done.iter_mut())
+                        .chain(todo.iter_mut())
+                    {
+                        op.remap(from, to);
+                    }
+                }
+                done.push(op);
+                Ok(true)
+            }
+            Err(err) => {
+                // Keep the operation where it was, so the stacks stay consistent.
+                todo.push(op);
+                Err(err)
+            }
+        }
+    }
+}
+
+fn registry(world: &World) -> AppTypeRegistry {
+    world.resource::<AppTypeRegistry>().clone()
+}
+
+fn reflect_component<'a>(
+    registry: &'a TypeRegistryInternal,
+    type_name: &str,
+) -> Result<&'a ReflectComponent, JournalError> {
+    registry
+        .get_with_name(type_name)
+        .and_then(|registration| registration.data::<ReflectComponent>())
+        .ok_or_else(|| JournalError::UnregisteredComponent {
+            type_name: type_name.to_string(),
+        })
+}
+
+fn get(
+    world: &World,
+    entity: Entity,
+    type_name: &str,
+) -> Result<Option<Box<dyn Reflect>>, JournalError> {
+    let registry = registry(world);
+    let registry = registry.read();
+    let reflect = reflect_component(&registry, type_name)?;
+    let entity = world
+        .get_entity(entity)
+        .ok_or(JournalError::NoSuchEntity(entity))?;
+    Ok(reflect.reflect(entity).map(Reflect::clone_value))
+}
+
+fn capture(world: &World, entity: Entity) -> Result<Vec<Box<dyn Reflect>>, JournalError> {
+    let registry = registry(world);
+    let registry = registry.read();
+    let entity = world
+        .get_entity(entity)
+        .ok_or(JournalError::NoSuchEntity(entity))?;
+
+    let components = entity.archetype().components().filter_map(|component_id| {
+        let info = world.components().get_info(component_id)?;
+        let registration = registry.get(info.type_id()?)?;
+        let reflect = registration.data::<ReflectComponent>()?;
+        reflect.reflect(entity).map(Reflect::clone_value)
+    });
+
+    Ok(components.collect())
+}
+
+fn spawn_with(world: &mut World, components: &[Box<dyn Reflect>]) -> Result<Entity, JournalError> {
+    let registry = registry(world);
+    let registry = registry.read();
+    let reflects = components
+        .iter()
+        .map(|component| reflect_component(&registry, component.type_name()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut entity = world.spawn_empty();
+    for (reflect, component) in reflects.into_iter().zip(components) {
+        reflect.insert(&mut entity, component.as_ref());
+    }
+    Ok(entity.id())
+}
+
+fn despawn(world: &mut World, entity: Entity) -> Result<(), JournalError> {
+    if world.despawn(entity) {
+        Ok(())
+    } else {
+        Err(JournalError::NoSuchEntity(entity))
+    }
+}
+
+fn insert(
+    world: &mut World,
+    entity: Entity,
+    component: &dyn Reflect,
+) -> Result<Option<Box<dyn Reflect>>, JournalError> {
+    let registry = registry(world);
+    let registry = registry.read();
+    let reflect = reflect_component(&registry, component.type_name())?;
+    let previous = world
+        .get_entity(entity)
+        .ok_or(JournalError::NoSuchEntity(entity))
+        .map(|entity| reflect.reflect(entity).map(Reflect::clone_value))?;
+
+    let mut entity = world.entity_mut(entity);
+    // Remove first so that the value is fully replaced rather than patched.
+    reflect.remove(&mut entity);
+    reflect.insert(&mut entity, component);
+    Ok(previous)
+}
+
+fn remove(
+    world: &mut World,
+    entity: Entity,
+    type_name: &str,
+) -> Result<Option<Box<dyn Reflect>>, JournalError> {
+    let registry = registry(world);
+    let registry = registry.read();
+    let reflect = reflect_component(&registry, type_name)?;
+    let previous = world
+        .get_entity(entity)
+        .ok_or(JournalError::NoSuchEntity(entity))
+        .map(|entity| reflect.reflect(entity).map(Reflect::clone_value))?;
+
+    let mut entity = world.entity_mut(entity);
+    reflect.remove(&mut entity);
+    Ok(previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Journal;
+    use bevy::ecs::{
+        component::Component,
+        reflect::{AppTypeRegistry, ReflectComponent},
+        world::World,
+    };
+    use bevy::reflect::Reflect;
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Health(u32);
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Marker;
+
+    fn world() -> World {
+        let mut world = World::default();
+        let atr = AppTypeRegistry::default();
+        {
+            let mut registry = atr.write();
+            registry.register::<Health>();
+            registry.register::<Marker>();
+        }
+        world.insert_resource(atr);
+        world
+    }
+
+    #[test]
+    fn undo_redo_components() {
+        let mut world = world();
+        let mut journal = Journal::default();
+
+        let entity = journal
+            .spawn(&mut world, vec![Box::new(Health(10))])
+            .unwrap();
+        journal
+            .set(
+                &mut world,
+                entity,
+                std::any::type_name::<Health>(),
+                "0",
+                &5u32,
+            )
+            .unwrap();
+        journal
+            .insert(&mut world, entity, Box::new(Marker))
+            .unwrap();
+        journal
+            .remove(&mut world, entity, std::any::type_name::<Health>())
+            .unwrap();
+        assert!(world.get::<Health>(entity).is_none());
+
+        assert!(journal.undo(&mut world).unwrap());
+        assert_eq!(world.get::<Health>(entity), Some(&Health(5)));
+        assert!(journal.undo(&mut world).unwrap());
+        assert!(world.get::<Marker>(entity).is_none());
+        assert!(journal.undo(&mut world).unwrap());
+        assert_eq!(world.get::<Health>(entity), Some(&Health(10)));
+
+        assert!(journal.redo(&mut world).unwrap());
+        assert_eq!(world.get::<Health>(entity), Some(&Health(5)));
+        assert!(journal.can_redo());
+    }
+
+    #[test]
+    fn undo_despawn_remaps_entity() {
+        let mut world = world();
+        let mut journal = Journal::default();
+
+        let entity = journal
+            .spawn(&mut world, vec![Box::new(Health(1))])
+            .unwrap();
+        journal.despawn(&mut world, entity).unwrap();
+        assert!(world.get_entity(entity).is_none());
+
+        assert!(journal.undo(&mut world).unwrap());
+        assert!(journal.undo(&mut world).unwrap());
+        assert!(!journal.can_undo());
+        assert_eq!(world.entities().len(), 0);
+
+        assert!(journal.redo(&mut world).unwrap());
+        assert!(journal.redo(&mut world).unwrap());
+        assert_eq!(world.entities().len(), 0);
+
+        assert!(journal.undo(&mut world).unwrap());
+        assert_eq!(world.query::<&Health>().single(&world), &Health(1));
+    }
+}
@@ -0,0 +1,193 @@
+use super::{
+    compact::to_concrete, handle::has_handle_paths, intern_type_name, stream::split_map, Prefab,
+    PrefabEntity, PrefabError,
+};
+use bevy::reflect::{serde::TypedReflectDeserializer, Reflect, TypeRegistryInternal};
+use bevy::utils::HashMap;
+use serde::de::DeserializeSeed;
+use std::sync::{Arc, OnceLock};
+
+impl Prefab {
+    /// Deserialize prefab from rust object notation (ron), keeping components serialized.
+    ///
+    /// Components are only deserialized when the prefab is spawned for the first time,
+    /// see [`SerializedComponent`]. Unregistered or malformed components are reported then.
+    pub fn deserialize_ron_lazy(input: &[u8]) -> Result<Self, ron::Error> {
+        let mut entities = Vec::new();
+        // Components of the same type share their name.
+        let mut names: HashMap<String, Arc<str>> = HashMap::default();
+
+        for entry in split_map(input)? {
+            let (entity, body) = split_entry::<u32>(std::str::from_utf8(&entry)?)?;
+
+            let mut serialized = Vec::new();
+            for entry in split_map(body.as_bytes())? {
+                let (type_name, data) = split_entry::<String>(std::str::from_utf8(&entry)?)?;
+                let type_name = names
+                    .entry(type_name)
+                    .or_insert_with_key(|type_name| Arc::from(type_name.as_str()));
+                serialized.push(SerializedComponent::new(type_name.clone(), data));
+            }
+
+            entities.push(PrefabEntity {
+                entity,
+                components: Vec::new(),
+                serialized,
+            });
+        }
+
+        Ok(Self { entities })
+    }
+}
+
+/// A component kept in its serialized form until it is needed.
+///
+/// The value is deserialized on first use and cached for later spawns.
+pub struct SerializedComponent {
+    /// Shared by the components of the same type, see [`intern_type_name`](super::intern_type_name).
+    type_name: Arc<str>,
+    data: String,
+    value: OnceLock<Box<dyn Reflect>>,
+}
+
+impl SerializedComponent {
+    /// Create a component from its type name and its value in rust object notation (ron).
+    pub fn new(type_name: impl Into<Arc<str>>, data: impl Into<String>) -> Self {
+        Self {
+            type_name: type_name.into(),
+            data: data.into(),
+            value: OnceLock::new(),
+        }
+    }
+
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// The serialized value.
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+
+    /// Has the value already been deserialized?
+    pub fn is_materialized(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// The value, if it has already been deserialized.
+    pub(crate) fn materialized(&self) -> Option<&dyn Reflect> {
+        self.value.get().map(AsRef::as_ref)
+    }
+
+    /// Get the value, deserializing it on first use.
+    pub fn materialize(
+        &self,
+        registry: &TypeRegistryInternal,
+    ) -> Result<&dyn Reflect, PrefabError> {
+        if let Some(value) = self.value.get() {
+            return Ok(value.as_ref());
+        }
+
+        let registration = registry.get_with_name(&self.type_name).ok_or_else(|| {
+            PrefabError::UnregisteredType {
+                type_name: self.type_name.clone(),
+            }
+        })?;
+
+        let invalid = |err: ron::Error| PrefabError::InvalidComponent {
+            type_name: intern_type_name(registration),
+            err: err.to_string(),
+        };
+        let mut deserializer =
+            ron::de::Deserializer::from_str(&self.data).map_err(|err| invalid(err.into()))?;
+        let value = TypedReflectDeserializer::new(registration, registry)
+            .deserialize(&mut deserializer)
+            .map_err(invalid)?;
+        deserializer.end().map_err(invalid)?;
+
+        // Handles written as paths are kept until the paths are collected,
+        // the concrete value would only hold their ids.
+        let value = match has_handle_paths(registration.type_id(), registry) {
+            true => value,
+            false => to_concrete(value.as_ref(), registry).unwrap_or(value),
+        };
+
+        // Another thread may have won the race, both values are the same.
+        let _ = self.value.set(value);
+        Ok(self.value.get().unwrap().as_ref())
+    }
+}
+
+/// Split a `key: value` map entry.
+pub(super) fn split_entry<'a, K: serde::Deserialize<'a>>(
+    entry: &'a str,
+) -> Result<(K, &'a str), ron::Error> {
+    let mut deserializer = ron::de::Deserializer::from_str(entry)?;
+    let key = K::deserialize(&mut deserializer)?;
+    let value = &entry[entry.len() - deserializer.remainder().len()..];
+    let value = value.trim_start().strip_prefix(':');
+    Ok((key, value.ok_or(ron::Error::ExpectedMapColon)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prefab::{test_utils, Prefab, PrefabError};
+    use bevy::asset::Assets;
+    use bevy::ecs::{component::Component, reflect::AppTypeRegistry, reflect::ReflectComponent};
+    use bevy::reflect::Reflect;
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Label {
+        text: String,
+    }
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Unregistered;
+
+    #[test]
+    fn materialize_on_spawn() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Label>();
+
+        let input = format!(
+            "{{ 0: {{ \"{}\": (text: \"a, b: {{}}\") }}, /* : */ 1: {{}} }}",
+            std::any::type_name::<Label>()
+        );
+        let prefab = Prefab::deserialize_ron_lazy(input.as_bytes()).unwrap();
+        assert_eq!(prefab.entities.len(), 2);
+        assert_eq!(prefab.entities[0].serialized.len(), 1);
+        assert!(!prefab.entities[0].serialized[0].is_materialized());
+
+        let handle = app.world.resource_mut::<Assets<Prefab>>().add(prefab);
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let label = Label {
+            text: String::from("a, b: {}"),
+        };
+        test_utils::assert_component(&app, &instance, 0, &label);
+
+        let prefabs = app.world.resource::<Assets<Prefab>>();
+        let component = &prefabs.get(&handle).unwrap().entities[0].serialized[0];
+        assert!(component.is_materialized());
+    }
+
+    #[test]
+    fn errors_on_spawn() {
+        let registry = AppTypeRegistry::default();
+        let registry = registry.read();
+
+        let input = format!(
+            "{{ 0: {{ \"{}\": () }} }}",
+            std::any::type_name::<Unregistered>()
+        );
+        let prefab = Prefab::deserialize_ron_lazy(input.as_bytes()).unwrap();
+        let component = &prefab.entities[0].serialized[0];
+        assert!(matches!(
+            component.materialize(&registry),
+            Err(PrefabError::UnregisteredType { .. })
+        ));
+
+        assert!(Prefab::deserialize_ron_lazy(b"{ 0 {} }").is_err());
+    }
+}
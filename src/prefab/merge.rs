@@ -0,0 +1,165 @@
+use super::{Prefab, PrefabEntity, PrefabError, SerializedComponent};
+use bevy::utils::HashMap;
+use std::sync::Arc;
+
+/// What [`Prefab::merge`] does when both prefabs have the same component on an entity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergePolicy {
+    /// Keep the component of the prefab merged into.
+    #[default]
+    Keep,
+    /// Replace it with the component of the other prefab.
+    Replace,
+    /// Fail with [`PrefabError::MergeConflict`].
+    Error,
+}
+
+/// Conflict policies of [`Prefab::merge`], per component type.
+#[derive(Debug, Clone, Default)]
+pub struct MergeOptions {
+    /// The policy of components without their own.
+    pub default: MergePolicy,
+    /// Policies by component type name.
+    pub components: HashMap<String, MergePolicy>,
+}
+
+impl MergeOptions {
+    pub fn new(default: MergePolicy) -> Self {
+        Self {
+            default,
+            components: HashMap::default(),
+        }
+    }
+
+    /// Use `policy` for components of type `T`.
+    pub fn with<T: 'static>(mut self, policy: MergePolicy) -> Self {
+        let type_name = std::any::type_name::<T>().to_string();
+        self.components.insert(type_name, policy);
+        self
+    }
+
+    pub fn policy(&self, type_name: &str) -> MergePolicy {
+        self.components
+            .get(type_name)
+            .copied()
+            .unwrap_or(self.default)
+    }
+}
+
+impl Prefab {
+    /// Merge the entities of `other` into this prefab, matching them by id.
+    ///
+    /// Entities missing from this prefab are added,
+    /// and so are the components missing from entities of both prefabs.
+    /// Components in both are resolved by `options`.
+    /// Nothing is merged if any conflict is an error.
+    pub fn merge(&mut self, other: &Prefab, options: &MergeOptions) -> Result<(), PrefabError> {
+        let mut index: HashMap<u32, usize> = HashMap::default();
+        for (i, entity) in self.entities.iter().enumerate() {
+            index.insert(entity.entity, i);
+        }
+
+        for entity in &other.entities {
+            let Some(&i) = index.get(&entity.entity) else {
+                continue;
+            };
+            let conflict = type_names(entity).find(|type_name| {
+                options.policy(type_name) == MergePolicy::Error
+                    && type_names(&self.entities[i]).any(|t| t == *type_name)
+            });
+            if let Some(type_name) = conflict {
+                return Err(PrefabError::MergeConflict {
+                    entity: entity.entity,
+                    type_name: Arc::from(type_name),
+                });
+            }
+        }
+
+        for entity in &other.entities {
+            let Some(&i) = index.get(&entity.entity) else {
+                index.insert(entity.entity, self.entities.len());
+                self.entities.push(clone_entity(entity, |_| true));
+                continue;
+            };
+
+            // Replaced components are removed first, then the missing ones are added.
+            let target = &mut self.entities[i];
+            let incoming: Vec<&str> = type_names(entity).collect();
+            let replaced = |type_name: &str| {
+                options.policy(type_name) == MergePolicy::Replace && incoming.contains(&type_name)
+            };
+            target.components.retain(|c| !replaced(c.type_name()));
+            target.serialized.retain(|c| !replaced(c.type_name()));
+
+            let existing: Vec<&str> = type_names(target).collect();
+            let merged = clone_entity(entity, |type_name| !existing.contains(&type_name));
+            target.components.extend(merged.components);
+            target.serialized.extend(merged.serialized);
+        }
+
+        Ok(())
+    }
+}
+
+fn type_names(entity: &PrefabEntity) -> impl Iterator<Item = &str> {
+    let components = entity.components.iter().map(|c| c.type_name());
+    components.chain(entity.serialized.iter().map(|c| c.type_name()))
+}
+
+/// Clone the components of `entity` for which `filter` returns `true`.
+fn clone_entity(entity: &PrefabEntity, filter: impl Fn(&str) -> bool) -> PrefabEntity {
+    let components = entity.components.iter().filter(|c| filter(c.type_name()));
+    let serialized = entity.serialized.iter().filter(|c| filter(c.type_name()));
+    let serialized = serialized.map(|c| SerializedComponent::new(c.type_name(), c.data()));
+    PrefabEntity {
+        entity: entity.entity,
+        components: components.map(|c| c.clone_value()).collect(),
+        serialized: serialized.collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MergeOptions, MergePolicy};
+    use crate::prefab::{Prefab, PrefabEntity, PrefabError};
+    use bevy::reflect::Reflect;
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Mesh(u32);
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Loot(u32);
+
+    fn prefab(entities: Vec<(u32, Vec<Box<dyn Reflect>>)>) -> Prefab {
+        let entities = entities
+            .into_iter()
+            .map(|(entity, components)| PrefabEntity::new(entity, components));
+        Prefab {
+            entities: entities.collect(),
+        }
+    }
+
+    #[test]
+    fn merge_policies() {
+        let art = || prefab(vec![(0, vec![Box::new(Mesh(1)), Box::new(Loot(1))])]);
+        let markup = prefab(vec![
+            (0, vec![Box::new(Mesh(2)), Box::new(Loot(2))]),
+            (1, vec![Box::new(Loot(3))]),
+        ]);
+
+        let mut merged = art();
+        let options = MergeOptions::default().with::<Loot>(MergePolicy::Replace);
+        merged.merge(&markup, &options).unwrap();
+        assert_eq!(merged.entities.len(), 2);
+        let components = &merged.entities[0].components;
+        assert_eq!(components.len(), 2);
+        assert!(components[0].reflect_partial_eq(&Mesh(1)).unwrap());
+        assert!(components[1].reflect_partial_eq(&Loot(2)).unwrap());
+
+        let mut merged = art();
+        let options = MergeOptions::new(MergePolicy::Error);
+        let err = merged.merge(&markup, &options).unwrap_err();
+        assert!(matches!(err, PrefabError::MergeConflict { entity: 0, .. }));
+        assert_eq!(merged.entities.len(), 1);
+    }
+}
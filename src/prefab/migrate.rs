@@ -0,0 +1,270 @@
+use super::{
+    value::{Parser, PrefabValue},
+    Prefab, PrefabSerializer,
+};
+use bevy::ecs::{reflect::AppTypeRegistry, system::Resource};
+use serde::ser::SerializeStruct;
+use std::borrow::Cow;
+use std::sync::{Arc, RwLock};
+
+/// Upgrade the entities of a serialized prefab from the given version to the next one.
+///
+/// The value is the map of entities, like `{ 0: { "my_game::Health": (10) } }`.
+pub type PrefabMigration = fn(u32, &mut PrefabValue) -> Result<(), ron::Error>;
+
+/// Migrations run by the [`PrefabLoader`](super::PrefabLoader) on ron prefabs saved with an older version.
+///
+/// The first migration upgrades prefabs from version 0 to 1, the second one from 1 to 2 and so on,
+/// so the current version is the number of migrations.
+/// Versioned prefabs look like `(version: 2, entities: { ... })`, see [`Prefab::serialize_ron_versioned`].
+/// Prefabs without a version are at version 0.
+///
+/// Only older prefabs are parsed into a [`PrefabValue`] for the migrations,
+/// prefabs at the current version are loaded as they are.
+/// Migrations added after the loader was created still apply to prefabs loaded later.
+#[derive(Clone, Default, Resource)]
+pub struct PrefabMigrations {
+    migrations: Arc<RwLock<Vec<PrefabMigration>>>,
+}
+
+impl PrefabMigrations {
+    /// Add the migration from the current version to the next one.
+    pub fn add(&self, migration: PrefabMigration) {
+        self.migrations.write().unwrap().push(migration);
+    }
+
+    /// The current version of prefabs.
+    pub fn version(&self) -> u32 {
+        self.migrations.read().unwrap().len() as u32
+    }
+
+    /// Upgrade a ron prefab to the current version and return its entities, as ron.
+    ///
+    /// The entities of a prefab at the current version are returned without being parsed.
+    pub fn migrate<'a>(&self, input: &'a [u8]) -> Result<Cow<'a, [u8]>, ron::Error> {
+        let migrations = self.migrations.read().unwrap();
+        let (version, entities) = split_version(input)?;
+        if version as usize > migrations.len() {
+            return Err(ron::Error::Message(format!(
+                "prefab version {} is newer than the current version {}",
+                version,
+                migrations.len()
+            )));
+        }
+        if version as usize == migrations.len() {
+            return Ok(Cow::Borrowed(entities));
+        }
+
+        let mut entities = PrefabValue::from_ron(entities)?;
+        for (version, migration) in migrations.iter().enumerate().skip(version as usize) {
+            migration(version as u32, &mut entities)?;
+        }
+
+        Ok(Cow::Owned(entities.to_string().into_bytes()))
+    }
+}
+
+impl std::fmt::Debug for PrefabMigrations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrefabMigrations")
+            .field("version", &self.version())
+            .finish()
+    }
+}
+
+/// Split a prefab into its version and its entities, without parsing the entities.
+///
+/// The version must come first: `(version: 2, entities: { ... })`.
+fn split_version(input: &[u8]) -> Result<(u32, &[u8]), ron::Error> {
+    let text = std::str::from_utf8(input)?;
+    let Some(text) = text.trim_start().strip_prefix('(') else {
+        return Ok((0, input));
+    };
+
+    let mut parser = Parser::new(text);
+    expect_field(&mut parser, "version")?;
+    let version = parser.parse()?;
+    parser.expect(',', ron::Error::ExpectedComma)?;
+    expect_field(&mut parser, "entities")?;
+
+    let entities = parser.rest().trim_end().strip_suffix(')');
+    let entities = entities
+        .ok_or(ron::Error::ExpectedStructLikeEnd)?
+        .trim_end();
+    let entities = entities.strip_suffix(',').unwrap_or(entities);
+    Ok((version, entities.as_bytes()))
+}
+
+fn expect_field(parser: &mut Parser, name: &str) -> Result<(), ron::Error> {
+    if parser.ident()? != Some(name) {
+        let message = format!("expected `{}` in versioned prefab", name);
+        return Err(ron::Error::Message(message));
+    }
+    parser.expect(':', ron::Error::ExpectedMapColon)
+}
+
+impl Prefab {
+    /// Serialize this prefab into rust object notation (ron), with the current version.
+    ///
+    /// See [`PrefabMigrations`].
+    pub fn serialize_ron_versioned(
+        &self,
+        registry: &AppTypeRegistry,
+        migrations: &PrefabMigrations,
+    ) -> Result<String, ron::Error> {
+        let registry = &registry.read();
+        let value = Versioned {
+            version: migrations.version(),
+            entities: PrefabSerializer::new(self, registry),
+        };
+        let config = ron::ser::PrettyConfig::default()
+            .indentor(String::from("  "))
+            .new_line(String::from("\n"));
+        ron::ser::to_string_pretty(&value, config)
+    }
+}
+
+struct Versioned<'a> {
+    version: u32,
+    entities: PrefabSerializer<'a>,
+}
+
+impl<'a> serde::Serialize for Versioned<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Prefab", 2)?;
+        state.serialize_field("version", &self.version)?;
+        state.serialize_field("entities", &self.entities)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefabMigrations;
+    use crate::prefab::{test_utils, Prefab, PrefabLoader, PrefabValue};
+    use bevy::ecs::{reflect::AppTypeRegistry, world::FromWorld};
+    use bevy::reflect::Reflect;
+    use std::borrow::Cow;
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Health(u32);
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    enum Mood {
+        #[default]
+        Calm,
+        Angry,
+        Tired {
+            hours: u32,
+        },
+    }
+
+    /// Version 0 called the component `Hp`.
+    fn rename_hp(_: u32, entities: &mut PrefabValue) -> Result<(), ron::Error> {
+        let PrefabValue::Map(entities) = entities else {
+            return Ok(());
+        };
+        for (_, components) in entities {
+            let PrefabValue::Map(components) = components else {
+                continue;
+            };
+            for (type_name, _) in components {
+                if *type_name == PrefabValue::String(String::from("Hp")) {
+                    let name = std::any::type_name::<Health>().to_string();
+                    *type_name = PrefabValue::String(name);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn migrate_old_prefab() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Health>();
+        let migrations = PrefabMigrations::default();
+
+        let input = b"{ 0: { \"Hp\": (10) } }";
+        assert!(matches!(migrations.migrate(input), Ok(Cow::Borrowed(_))));
+
+        migrations.add(rename_hp);
+        let migrated = migrations.migrate(input).unwrap();
+        let prefab = Prefab::deserialize_ron(&migrated, &registry.0).unwrap();
+        assert!(prefab.entities[0].components[0]
+            .reflect_partial_eq(&Health(10))
+            .unwrap());
+
+        // Prefabs at the current version are not migrated again.
+        let saved = prefab
+            .serialize_ron_versioned(&registry, &migrations)
+            .unwrap();
+        assert!(saved.starts_with("(\n  version: 1,"));
+        let migrated = migrations.migrate(saved.as_bytes()).unwrap();
+        assert!(matches!(migrated, Cow::Borrowed(_)));
+        let prefab = Prefab::deserialize_ron(&migrated, &registry.0).unwrap();
+        assert!(prefab.entities[0].components[0]
+            .reflect_partial_eq(&Health(10))
+            .unwrap());
+
+        let newer = b"(version: 2, entities: {})";
+        assert!(migrations.migrate(newer).is_err());
+    }
+
+    #[test]
+    fn migrate_enum_components() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Health>();
+        registry.write().register::<Mood>();
+        let migrations = PrefabMigrations::default();
+        migrations.add(rename_hp);
+
+        let input = format!(
+            "{{ 0: {{ \"Hp\": (10), {0:?}: Angry }}, 1: {{ {0:?}: Tired(hours: 2) }} }}",
+            std::any::type_name::<Mood>()
+        );
+        let migrated = migrations.migrate(input.as_bytes()).unwrap();
+        let prefab = Prefab::deserialize_ron(&migrated, &registry.0).unwrap();
+        let components = &prefab.entities[0].components;
+        assert!(components[0].reflect_partial_eq(&Health(10)).unwrap());
+        assert!(components[1].reflect_partial_eq(&Mood::Angry).unwrap());
+        let components = &prefab.entities[1].components;
+        let tired = Mood::Tired { hours: 2 };
+        assert!(components[0].reflect_partial_eq(&tired).unwrap());
+
+        let saved = prefab
+            .serialize_ron_versioned(&registry, &migrations)
+            .unwrap();
+        let current = migrations.migrate(saved.as_bytes()).unwrap();
+        let prefab = Prefab::deserialize_ron(&current, &registry.0).unwrap();
+        let components = &prefab.entities[0].components;
+        assert!(components[1].reflect_partial_eq(&Mood::Angry).unwrap());
+    }
+
+    #[test]
+    fn migrate_labeled_prefabs() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Health>();
+        app.world.resource::<PrefabMigrations>().add(rename_hp);
+
+        let input = format!(
+            "{{ \"Old\": {{ 0: {{ \"Hp\": (10) }} }}, \
+            \"Current\": (version: 1, entities: {{ 0: {{ {:?}: (20) }} }}) }}",
+            std::any::type_name::<Health>()
+        );
+        let loader = PrefabLoader::from_world(&mut app.world);
+        let prefabs = loader.read_labeled(input.as_bytes()).unwrap();
+        let labels: Vec<&str> = prefabs.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["Old", "Current"]);
+        let components = &prefabs[0].1.entities[0].components;
+        assert!(components[0].reflect_partial_eq(&Health(10)).unwrap());
+        let components = &prefabs[1].1.entities[0].components;
+        assert!(components[0].reflect_partial_eq(&Health(20)).unwrap());
+
+        let loader = loader.with_lazy_components(true);
+        let prefabs = loader.read_labeled(input.as_bytes()).unwrap();
+        for (_, prefab) in &prefabs {
+            let serialized = &prefab.entities[0].serialized;
+            assert_eq!(serialized[0].type_name(), std::any::type_name::<Health>());
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use super::Prefab;
+use bevy::reflect::TypeRegistryArc;
+use memmap2::Mmap;
+use std::{fs::File, path::Path};
+
+impl Prefab {
+    /// Deserialize prefab from a rust object notation (ron) file mapped into memory.
+    ///
+    /// The file is paged in by the OS as it is parsed instead of being read into a buffer first,
+    /// so loading a huge prefab doesn't hold both the file contents and the prefab in memory.
+    ///
+    /// The file must not be modified while it is being loaded.
+    ///
+    /// The [`PrefabLoader`](super::PrefabLoader) doesn't map files: the asset server reads
+    /// the whole file before handing its bytes to the loader. Map huge prefabs with this instead
+    /// and add them to the [`Assets<Prefab>`](bevy::asset::Assets) yourself.
+    pub fn map_ron(path: impl AsRef<Path>, registry: &TypeRegistryArc) -> Result<Self, ron::Error> {
+        let map = map(path.as_ref())?;
+        Self::deserialize_ron(&map, registry)
+    }
+
+    /// Same as [`Prefab::map_ron`], but keeps components serialized until they are spawned.
+    ///
+    /// See [`Prefab::deserialize_ron_lazy`].
+    pub fn map_ron_lazy(path: impl AsRef<Path>) -> Result<Self, ron::Error> {
+        let map = map(path.as_ref())?;
+        Self::deserialize_ron_lazy(&map)
+    }
+}
+
+fn map(path: &Path) -> std::io::Result<Mmap> {
+    let file = File::open(path)?;
+    // SAFETY: the mapping is only read while parsing and dropped right after,
+    // callers are asked not to modify the file in the meantime.
+    unsafe { Mmap::map(&file) }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prefab::Prefab;
+    use bevy::reflect::{Reflect, TypeRegistryArc};
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Size(u32);
+
+    #[test]
+    fn map_file() {
+        let registry = TypeRegistryArc::default();
+        registry.write().register::<Size>();
+
+        let entities = (0..100).map(|entity| {
+            let type_name = std::any::type_name::<Size>();
+            format!("{}: {{ \"{}\": ({}) }}", entity, type_name, entity)
+        });
+        let input = format!("{{ {} }}", entities.collect::<Vec<_>>().join(", "));
+
+        let path = std::env::temp_dir().join(format!("map_file_{}.prefab", std::process::id()));
+        std::fs::write(&path, input).unwrap();
+
+        let prefab = Prefab::map_ron(&path, &registry);
+        let lazy = Prefab::map_ron_lazy(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let prefab = prefab.unwrap();
+        assert_eq!(prefab.entities.len(), 100);
+        assert!(prefab.entities[99].components[0]
+            .reflect_partial_eq(&Size(99))
+            .unwrap());
+        assert_eq!(
+            lazy.unwrap().entities[99].serialized[0].data().trim(),
+            "(99)"
+        );
+
+        assert!(Prefab::map_ron(path, &registry).is_err());
+    }
+}
@@ -2,202 +2,351 @@
 
 mod asset;
 mod builder;
+mod compact;
+mod debug;
+mod deps;
+mod diagnostics;
+mod diff;
+mod events;
+mod handle;
+mod id;
+mod intern;
+mod journal;
+mod lazy;
+mod merge;
+mod migrate;
+#[cfg(not(target_arch = "wasm32"))]
+mod mmap;
+mod patch_builder;
+mod path;
+mod save;
+mod scene;
 mod serde;
 mod spawner;
-
-use std::any::TypeId;
+mod stream;
+pub mod test_utils;
+mod typed_patch;
+mod validate;
+mod value;
+mod variant;
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+mod watch;
+mod write;
 
 pub use self::asset::{
-    Patch, PatchEntity, Prefab, PrefabComponent, PrefabEntity, PrefabLoader, ReflectPrefabComponent,
+    Patch, PatchEntity, PatchParent, Prefab, PrefabComponent, PrefabEntity, PrefabHook,
+    PrefabLoader, PrefabLoaderHooks, ReflectPrefabComponent,
+};
+pub use self::builder::{PrefabBuilder, ReflectPrefabSkip};
+pub use self::compact::clone_compact;
+pub use self::debug::{PrefabDebugConfig, PrefabDebugEntry, PrefabDebugPlugin, PrefabDebugStats};
+pub use self::deps::{PrefabAssetPaths, ReflectPrefabAssetPaths};
+pub use self::diagnostics::{
+    PrefabDiagnosticsPlugin, PrefabLoadLog, PrefabLoadRecord, PrefabMetric,
+};
+pub use self::diff::{reflect_diff, FieldDelta};
+pub use self::events::{
+    prefab_event_system, DespawnPrefab, PrefabInstanceReady, PrefabOrphaned, PrefabSpawnError,
+    SpawnPrefab,
+};
+pub use self::handle::{register_handle_path, HandlePath, ReflectHandlePath};
+pub use self::id::{PrefabLabel, PrefabUuid};
+pub use self::intern::intern_type_name;
+pub use self::journal::{Journal, JournalError, JournalOp};
+pub use self::lazy::SerializedComponent;
+pub use self::merge::{MergeOptions, MergePolicy};
+pub use self::migrate::{PrefabMigration, PrefabMigrations};
+pub use self::patch_builder::{PatchBuilder, PatchBuilderError};
+pub use self::path::{FieldPath, FieldPathBuilder, FieldPathError};
+pub use self::save::{
+    prefab_writer_system, PrefabSaveError, PrefabSaved, PrefabWriter, PrefabWriterError, SavePrefab,
 };
-pub use self::builder::PrefabBuilder;
 pub use self::serde::{
-    ComponentsDeserializer, ComponentsSerializer, PrefabDeserializer, PrefabSerializer,
+    ComponentsDeserializer, ComponentsSerializer, LabeledPrefabsDeserializer, PatchDeserializer,
+    PrefabDeserializer, PrefabSerializer, PrefabVariantDeserializer,
 };
 pub use self::spawner::{
-    prefab_spawner_maintain_system, prefab_update_system, PrefabBundle, PrefabInstance,
-    PrefabInstanceInfo, PrefabSpawner,
+    prefab_instance_state_system, prefab_spawner_maintain_system, prefab_update_system,
+    KeepOnPrefabDespawn, PatchedPrefabBundle, PendingLimit, PrefabBundle, PrefabEviction,
+    PrefabHooks, PrefabInstance, PrefabInstanceInfo, PrefabInstanceState, PrefabRemovalPolicy,
+    PrefabRoot, PrefabSpawnHook, PrefabSpawner, PrefabSpawnerStats, PrefabUpdatePolicy,
+    SpawnBudget,
 };
+pub use self::stream::PrefabEntityReader;
+pub use self::typed_patch::PrefabPatch;
+pub use self::value::PrefabValue;
+pub use self::variant::{PrefabVariant, PrefabVariantLoader};
+#[cfg(not(any(target_arch = "wasm32", target_os = "android")))]
+pub use self::watch::{PrefabWatchPlugin, PrefabWatcher};
+pub use self::write::{write_to_commands, write_to_world, write_to_world_with, WriteOptions};
+pub use bevy_nursery_macros::PrefabPatch;
 
 use bevy::{
-    app::{App, Plugin, PreUpdate, Update},
+    app::{App, Plugin, PreUpdate},
     asset::{AddAsset, Handle},
-    ecs::entity::{Entity, EntityMap},
-    ecs::reflect::{AppTypeRegistry, ReflectComponent, ReflectMapEntities},
-    ecs::world::World,
-    reflect::GetPath,
-    utils::{tracing::error, HashMap},
+    core::Name,
+    ecs::schedule::{apply_deferred, IntoSystemConfigs, IntoSystemSetConfigs, SystemSet},
+    ecs::world::FromWorld,
+    render::view::ComputedVisibility,
+    transform::components::GlobalTransform,
+    utils::{Duration, Uuid},
 };
+use std::borrow::Cow;
+use std::sync::Arc;
+
+#[derive(Default)]
+pub struct PrefabPlugin {
+    /// Don't register [`prefab_spawner_maintain_system`].
+    ///
+    /// Queued spawns are then only resolved when [`PrefabSpawner::maintain`] is called,
+    /// which lets turn-based or fixed-timestep games pick the exact point in their loop.
+    pub manual_maintenance: bool,
+    /// Keep components of loaded prefabs serialized until they are spawned for the first time.
+    ///
+    /// Saves load time and memory when many prefabs are loaded but few of them are spawned.
+    /// See [`Prefab::deserialize_ron_lazy`].
+    pub lazy_components: bool,
+    /// Fail loading prefabs with unregistered components, reporting all of them at once.
+    ///
+    /// See [`PrefabLoader::with_strict`] and [`PrefabSpawner::set_strict`].
+    pub strict: bool,
+    /// Despawn the instances of a prefab once all strong handles to it are dropped.
+    ///
+    /// See [`PrefabSpawner::set_despawn_unused`].
+    pub despawn_unused: bool,
+    /// Give the spawned entities a [`Name`] made from the prefab and instance.
+    ///
+    /// See [`PrefabSpawner::set_name_entities`].
+    pub name_entities: bool,
+    /// Also despawn the descendants of despawned instances, like children added at runtime.
+    ///
+    /// See [`PrefabSpawner::set_despawn_recursive`].
+    pub despawn_recursive: bool,
+    /// Spread the spawns of large prefabs over several frames.
+    ///
+    /// See [`PrefabSpawner::set_spawn_budget`].
+    pub spawn_budget: Option<SpawnBudget>,
+}
 
-pub struct PrefabPlugin;
+/// The [`PrefabPlugin`](struct@PrefabPlugin) with its default settings,
+/// so it is still added like a unit struct: `app.add_plugins(PrefabPlugin)`.
+#[allow(non_upper_case_globals)]
+pub const PrefabPlugin: PrefabPlugin = PrefabPlugin {
+    manual_maintenance: false,
+    lazy_components: false,
+    strict: false,
+    despawn_unused: false,
+    name_entities: false,
+    despawn_recursive: false,
+    spawn_budget: None,
+};
 
 impl Plugin for PrefabPlugin {
     fn build(&self, app: &mut App) {
+        let loader = PrefabLoader::from_world(&mut app.world);
+        let variants = PrefabVariantLoader::from_world(&mut app.world).with_strict(self.strict);
         app.add_asset::<Prefab>()
-            .init_asset_loader::<PrefabLoader>()
+            .add_asset::<Patch>()
+            .add_asset::<PrefabVariant>()
+            .init_resource::<PrefabLoaderHooks>()
+            .init_resource::<PrefabMigrations>()
+            .register_type::<PrefabUuid>()
+            .register_type::<Uuid>()
+            .register_type::<PrefabLabel>()
+            .register_type::<Name>()
+            .register_type::<Cow<'static, str>>()
+            .register_type::<GlobalTransform>()
+            .register_type_data::<GlobalTransform, ReflectPrefabSkip>()
+            .register_type::<ComputedVisibility>()
+            .register_type_data::<ComputedVisibility, ReflectPrefabSkip>()
+            .add_asset_loader(
+                loader
+                    .with_lazy_components(self.lazy_components)
+                    .with_strict(self.strict),
+            )
+            .add_asset_loader(variants)
             .init_resource::<PrefabSpawner>()
-            .add_systems(PreUpdate, self::prefab_update_system)
-            .add_systems(Update, self::prefab_spawner_maintain_system);
+            .init_resource::<PrefabHooks>()
+            .init_resource::<PrefabWriter>()
+            .add_event::<SpawnPrefab>()
+            .add_event::<DespawnPrefab>()
+            .add_event::<PrefabSpawnError>()
+            .add_event::<PrefabInstanceReady>()
+            .add_event::<PrefabOrphaned>()
+            .add_event::<PrefabSaved>()
+            .add_event::<PrefabSaveError>()
+            .configure_sets(
+                PreUpdate,
+                (PrefabSet::Queue, PrefabSet::QueueFlush, PrefabSet::Spawn).chain(),
+            )
+            .add_systems(
+                PreUpdate,
+                (
+                    (
+                        self::prefab_update_system,
+                        self::prefab_event_system,
+                        self::prefab_writer_system,
+                    )
+                        .in_set(PrefabSet::Queue),
+                    apply_deferred.in_set(PrefabSet::QueueFlush),
+                ),
+            );
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_despawn_unused(self.despawn_unused);
+        spawner.set_name_entities(self.name_entities);
+        spawner.set_despawn_recursive(self.despawn_recursive);
+        spawner.set_spawn_budget(self.spawn_budget);
+        spawner.set_strict(self.strict);
+
+        if self.manual_maintenance {
+            app.add_systems(
+                PreUpdate,
+                self::prefab_instance_state_system.in_set(PrefabSet::Spawn),
+            );
+        } else {
+            app.add_systems(
+                PreUpdate,
+                (
+                    self::prefab_spawner_maintain_system,
+                    self::prefab_instance_state_system,
+                )
+                    .chain()
+                    .in_set(PrefabSet::Spawn),
+            );
+        }
     }
 }
 
+/// System sets of the [`PrefabPlugin`](struct@PrefabPlugin), all of them run in [`PreUpdate`] in this order.
+///
+/// Instances are written to the world by the exclusive [`PrefabSet::Spawn`] system
+/// in one go, so systems in [`Update`](bevy::app::Update) never observe a partially spawned instance:
+/// an instance requested this frame is either complete or not there at all.
+/// The exception are instances spawned with a [`SpawnBudget`], their entities exist
+/// partially written until the instance is [`PrefabInstanceState::Ready`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
+pub enum PrefabSet {
+    /// Spawn requests from [`PrefabBundle`] and [`SpawnPrefab`] are queued.
+    Queue,
+    /// Commands from [`PrefabSet::Queue`] are applied.
+    QueueFlush,
+    /// Queued spawns, despawns and hot reloads are resolved.
+    Spawn,
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum PrefabError {
     #[error("prefab contains the unregistered component `{type_name}`. consider adding `#[reflect(Component)]` to your type")]
-    UnregisteredComponent { type_name: String },
+    UnregisteredComponent { type_name: Arc<str> },
     #[error("prefab contains the unregistered type `{type_name}`. consider registering the type using `app.register_type::<T>()`")]
-    UnregisteredType { type_name: String },
+    UnregisteredType { type_name: Arc<str> },
+    #[error("prefab contains unregistered components: {}", .type_names.join(", "))]
+    UnregisteredComponents { type_names: Vec<Arc<str>> },
     #[error("prefab does not exist")]
     NonExistentPrefab { handle: Handle<Prefab> },
-    #[error("prefab patch contains the wrong path")]
-    PatchContainsWrongPath { path: String, err: String },
+    #[error("prefab patch does not exist")]
+    NonExistentPatch { handle: Handle<Patch> },
+    #[error("prefab instance is not spawned")]
+    NotSpawned { instance: PrefabInstance },
+    #[error("prefab contains an invalid `{type_name}` component: {err}")]
+    InvalidComponent { type_name: Arc<str>, err: String },
+    #[error("prefab patch contains the wrong path `{path}`: {err}, valid paths are: {}", .valid.join(", "))]
+    PatchContainsWrongPath {
+        path: String,
+        err: String,
+        /// The paths into the patched component, to spot typos.
+        valid: Vec<String>,
+    },
+    #[error("prefab entity {entity} is mapped to an entity that doesn't exist")]
+    MissingEntity { entity: u32 },
+    #[error("prefab assets were not loaded after {attempts} attempts in {waited:?}")]
+    PendingTimeout { attempts: u32, waited: Duration },
+    #[error("prefab reached its limit of {max} instances")]
+    TooManyInstances { max: usize },
+    #[error("both merged prefabs have a `{type_name}` component on entity {entity}")]
+    MergeConflict { entity: u32, type_name: Arc<str> },
+    #[error("{error} (in {context})")]
+    InPrefab {
+        context: PrefabErrorContext,
+        error: Box<PrefabError>,
+    },
 }
 
-pub fn write_to_world(
-    patch: &Patch,
-    prefab: &Prefab,
-    world: &mut World,
-    entity_map: &mut EntityMap,
-) -> Result<(), PrefabError> {
-    let registry = world.resource::<AppTypeRegistry>().clone();
-    let registry = registry.read();
-
-    let mut patch_map: HashMap<_, _> = patch.modify.iter().map(|e| (e.entity, e)).collect();
-
-    // For each component types that reference other entities, we keep track
-    // of which entities in the scene use that component.
-    // This is so we can update the scene-internal references to references
-    // of the actual entities in the world.
-    let mut scene_mappings: HashMap<TypeId, Vec<Entity>> = HashMap::default();
-
-    for prefab_entity in &prefab.entities {
-        // ignore despawned entities
-        if patch.ignore.contains(&prefab_entity.entity) {
-            continue;
+impl PrefabError {
+    /// The error without its context, see [`PrefabError::InPrefab`].
+    pub fn kind(&self) -> &PrefabError {
+        match self {
+            Self::InPrefab { error, .. } => error.kind(),
+            error => error,
         }
+    }
 
-        // Fetch the entity with the given entity id from the `entity_map`
-        let entity = entity_map.entry(Entity::from_raw(prefab_entity.entity));
-        // or spawn a new entity with a transiently unique id if there is no corresponding entry.
-        let entity = *entity.or_insert_with(|| world.spawn_empty().id());
-        let mut entity = world.entity_mut(entity);
-
-        let patch = patch_map.remove(&prefab_entity.entity);
-
-        // Combine components
-        let components = patch.map(|p| p.append.iter()).into_iter();
-        let components = prefab_entity.components.iter().chain(components.flatten());
-
-        // Apply/ add each component to the given entity.
-        for mut component in components.map(AsRef::as_ref) {
-            let type_name = component.type_name();
-
-            let mut _clone;
-            if let Some(patch) = patch {
-                // ignore removed components
-                if patch.remove.contains(type_name) {
-                    continue;
-                }
-
-                // patch component fields
-                if let Some(modify) = patch.modify.get(type_name) {
-                    _clone = component.clone_value();
-
-                    for (path, value) in modify {
-                        let field = _clone.reflect_path_mut(path);
-                        let field = field.map_err(|err| PrefabError::PatchContainsWrongPath {
-                            path: path.clone(),
-                            err: err.to_string(),
-                        })?;
-                        field.apply(value.as_ref());
-                    }
-
-                    component = _clone.as_ref();
-                }
-            }
-
-            let registration = registry.get_with_name(type_name);
-            let registration = registration.ok_or_else(|| PrefabError::UnregisteredType {
-                type_name: type_name.to_string(),
-            })?;
-
-            if let Some(proxy) = registration.data::<ReflectPrefabComponent>() {
-                proxy.apply_insert(&mut entity, component);
-                continue;
-            }
-
-            let reflect = registration.data::<ReflectComponent>();
-            let reflect = reflect.ok_or_else(|| PrefabError::UnregisteredComponent {
-                type_name: type_name.to_string(),
-            })?;
-
-            // If this component references entities in the scene, track it
-            // so we can update it to the entity in the world.
-            if registration.data::<ReflectMapEntities>().is_some() {
-                scene_mappings
-                    .entry(registration.type_id())
-                    .or_insert(Vec::new())
-                    .push(entity.id());
-            }
-
-            // If the entity already has the given component attached,
-            // just apply the (possibly) new value,
-            // otherwise add the component to the entity.
-            reflect.apply_or_insert(&mut entity, component);
+    /// Where the error happened, if it is known.
+    pub fn context(&self) -> Option<&PrefabErrorContext> {
+        match self {
+            Self::InPrefab { context, .. } => Some(context),
+            _ => None,
         }
     }
 
-    for patch in patch_map.into_values() {
-        // Fetch the entity with the given entity id from the `entity_map`
-        let entity = entity_map.entry(Entity::from_raw(patch.entity));
-        // or spawn a new entity with a transiently unique id if there is no corresponding entry.
-        let entity = *entity.or_insert_with(|| world.spawn_empty().id());
-        let mut entity = world.entity_mut(entity);
-
-        for component in patch.append.iter().map(AsRef::as_ref) {
-            let type_name = component.type_name();
-
-            let registration = registry.get_with_name(type_name);
-            let registration = registration.ok_or_else(|| PrefabError::UnregisteredType {
-                type_name: type_name.to_string(),
-            })?;
+    /// Add the asset path of the prefab to the context of the error, unless it has one.
+    pub fn in_file(self, path: impl Into<String>) -> Self {
+        self.with_context(|context| {
+            context.path.get_or_insert_with(|| path.into());
+        })
+    }
 
-            if let Some(proxy) = registration.data::<ReflectPrefabComponent>() {
-                proxy.apply_insert(&mut entity, component);
-                continue;
+    /// Add the prefab entity to the context of the error, unless it has one.
+    pub fn in_entity(self, entity: u32, name: Option<String>) -> Self {
+        self.with_context(|context| {
+            if context.entity.is_none() {
+                context.entity = Some(entity);
+                context.name = name;
             }
+        })
+    }
 
-            let reflect = registration.data::<ReflectComponent>().ok_or_else(|| {
-                PrefabError::UnregisteredComponent {
-                    type_name: type_name.to_string(),
+    fn with_context(self, f: impl FnOnce(&mut PrefabErrorContext)) -> Self {
+        match self {
+            Self::InPrefab { mut context, error } => {
+                f(&mut context);
+                Self::InPrefab { context, error }
+            }
+            error => {
+                let mut context = PrefabErrorContext::default();
+                f(&mut context);
+                Self::InPrefab {
+                    context,
+                    error: Box::new(error),
                 }
-            })?;
-
-            // If this component references entities in the scene, track it
-            // so we can update it to the entity in the world.
-            if registration.data::<ReflectMapEntities>().is_some() {
-                scene_mappings
-                    .entry(registration.type_id())
-                    .or_insert(Vec::new())
-                    .push(entity.id());
             }
-
-            // If the entity already has the given component attached,
-            // just apply the (possibly) new value,
-            // otherwise add the component to the entity.
-            reflect.apply_or_insert(&mut entity, component);
         }
     }
+}
 
-    // Updates references to entities in the scene to entities in the world
-    for (type_id, entities) in scene_mappings.into_iter() {
-        let registration = registry
-            .get(type_id)
-            .expect("we should be getting TypeId from this TypeRegistration in the first place");
-        if let Some(map_entities_reflect) = registration.data::<ReflectMapEntities>() {
-            map_entities_reflect.map_entities(world, entity_map, &entities);
+/// The prefab file and entity a [`PrefabError`] comes from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrefabErrorContext {
+    /// Asset path of the prefab.
+    pub path: Option<String>,
+    /// Id of the prefab entity.
+    pub entity: Option<u32>,
+    /// [`Name`] of the prefab entity.
+    pub name: Option<String>,
+}
+
+impl std::fmt::Display for PrefabErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        if let Some(path) = &self.path {
+            parts.push(format!("`{}`", path));
         }
+        match (self.entity, &self.name) {
+            (Some(entity), Some(name)) => parts.push(format!("entity {} `{}`", entity, name)),
+            (Some(entity), None) => parts.push(format!("entity {}", entity)),
+            _ => {}
+        }
+        f.write_str(&parts.join(", "))
     }
-
-    Ok(())
 }
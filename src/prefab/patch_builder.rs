@@ -0,0 +1,281 @@
+use super::{FieldPath, FieldPathError, Patch, PatchEntity, PatchParent, ReflectPrefabComponent};
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::reflect::{Reflect, TypeRegistration, TypeRegistryInternal};
+use std::any::TypeId;
+
+/// Builder for a [`Patch`], checking components and field values against the registry.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy::reflect::TypeRegistryInternal;
+/// # use bevy_nursery::prefab::PatchBuilder;
+/// let mut registry = TypeRegistryInternal::default();
+/// registry.register::<Transform>();
+/// registry.register::<Vec3>();
+/// registry.register::<PointLight>();
+///
+/// let patch = PatchBuilder::new(&registry)
+///     .entity(3)
+///     .remove::<PointLight>()
+///     .set("Transform.translation.x", 5.0f32)
+///     .build()
+///     .unwrap();
+/// assert_eq!(patch.modify[0].entity, 3);
+///
+/// // Fields are `f32`, not `f64`.
+/// let patch = PatchBuilder::new(&registry)
+///     .entity(3)
+///     .set("Transform.translation.x", 5.0)
+///     .build();
+/// assert!(patch.is_err());
+/// ```
+///
+/// Every step works on the entity selected last.
+/// The first error is kept and returned from [`PatchBuilder::build`].
+pub struct PatchBuilder<'r> {
+    registry: &'r TypeRegistryInternal,
+    patch: Patch,
+    /// Index of the selected entity in the entities of the patch.
+    current: Option<usize>,
+    error: Option<PatchBuilderError>,
+}
+
+impl<'r> PatchBuilder<'r> {
+    pub fn new(registry: &'r TypeRegistryInternal) -> Self {
+        Self {
+            registry,
+            patch: Patch::default(),
+            current: None,
+            error: None,
+        }
+    }
+
+    /// Select the prefab entity with this id.
+    pub fn entity(self, entity: u32) -> Self {
+        self.select(entity, None)
+    }
+
+    /// Select the first prefab entity with this [`Name`](bevy::core::Name),
+    /// see [`PatchEntity::name`].
+    pub fn named(self, name: impl Into<String>) -> Self {
+        self.select(0, Some(name.into()))
+    }
+
+    /// Don't spawn the prefab entity with this id.
+    pub fn ignore(mut self, entity: u32) -> Self {
+        self.patch.ignore.insert(entity);
+        self
+    }
+
+    /// Add a component to the selected entity.
+    pub fn append<T: Reflect>(self, component: T) -> Self {
+        self.edit(|registry, entity| {
+            component_registration(registry, TypeId::of::<T>(), component.type_name())?;
+            entity.append.push(Box::new(component));
+            Ok(())
+        })
+    }
+
+    /// Remove the component of type `T` from the selected entity.
+    pub fn remove<T: Reflect>(self) -> Self {
+        self.edit(|registry, entity| {
+            let type_name = std::any::type_name::<T>();
+            component_registration(registry, TypeId::of::<T>(), type_name)?;
+            entity.remove.insert(type_name.to_string());
+            Ok(())
+        })
+    }
+
+    /// Set a field of a component of the selected entity.
+    ///
+    /// The path starts with the name of the component, short or full, like `Transform.translation.x`.
+    /// The value must have the type of the field, unless the path goes through an enum.
+    pub fn set(self, path: &str, value: impl Reflect) -> Self {
+        self.edit(|registry, entity| {
+            let (type_name, field) = path.split_once('.').unwrap_or((path, ""));
+            let registration = registry.get_with_short_name(type_name);
+            let registration = registration.or_else(|| registry.get_with_name(type_name));
+            let registration = registration.ok_or_else(|| PatchBuilderError::UnknownComponent {
+                type_name: type_name.to_string(),
+            })?;
+            let registration = component_registration(registry, registration.type_id(), type_name)?;
+
+            let field = FieldPath::parse_with_info(registration.type_info(), registry, field)?;
+            if let Some(leaf) = field.leaf_type_id() {
+                if leaf != value.type_id() {
+                    return Err(PatchBuilderError::TypeMismatch {
+                        path: path.to_string(),
+                        expected: registry.get(leaf).map_or("?", |r| r.type_name()),
+                        found: value.type_name().to_string(),
+                    });
+                }
+            }
+
+            let fields = entity.modify.entry(registration.type_name().to_string());
+            let fields = fields.or_default();
+            fields.insert(field.into_string(), Box::new(value));
+            Ok(())
+        })
+    }
+
+    /// Move the selected entity in the hierarchy, see [`PatchEntity::parent`].
+    pub fn parent(self, parent: PatchParent) -> Self {
+        self.edit(|_, entity| {
+            entity.parent = Some(parent);
+            Ok(())
+        })
+    }
+
+    pub fn build(self) -> Result<Patch, PatchBuilderError> {
+        match self.error {
+            Some(err) => Err(err),
+            None => Ok(self.patch),
+        }
+    }
+
+    fn select(mut self, entity: u32, name: Option<String>) -> Self {
+        let index = (self.patch.modify.iter())
+            .position(|patch| patch.entity == entity && patch.uuid.is_none() && patch.name == name);
+        let index = index.unwrap_or_else(|| {
+            self.patch.modify.push(PatchEntity {
+                entity,
+                uuid: None,
+                name,
+                append: Vec::new(),
+                modify: Default::default(),
+                remove: Default::default(),
+                parent: None,
+            });
+            self.patch.modify.len() - 1
+        });
+        self.current = Some(index);
+        self
+    }
+
+    fn edit(
+        mut self,
+        f: impl FnOnce(&TypeRegistryInternal, &mut PatchEntity) -> Result<(), PatchBuilderError>,
+    ) -> Self {
+        if self.error.is_some() {
+            return self;
+        }
+        let result = match self.current {
+            Some(index) => f(self.registry, &mut self.patch.modify[index]),
+            None => Err(PatchBuilderError::NoEntity),
+        };
+        self.error = result.err();
+        self
+    }
+}
+
+/// The registration of a component type, or an error if it isn't one.
+fn component_registration<'r>(
+    registry: &'r TypeRegistryInternal,
+    type_id: TypeId,
+    type_name: &str,
+) -> Result<&'r TypeRegistration, PatchBuilderError> {
+    let registration = registry.get(type_id).filter(|registration| {
+        registration.data::<ReflectComponent>().is_some()
+            || registration.data::<ReflectPrefabComponent>().is_some()
+    });
+    registration.ok_or_else(|| PatchBuilderError::UnknownComponent {
+        type_name: type_name.to_string(),
+    })
+}
+
+/// An error produced while building a [`Patch`], see [`PatchBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PatchBuilderError {
+    #[error("no entity is selected")]
+    NoEntity,
+    #[error("`{type_name}` is not a registered component")]
+    UnknownComponent { type_name: String },
+    #[error(transparent)]
+    Path(#[from] FieldPathError),
+    #[error("`{path}` is a `{expected}`, not a `{found}`")]
+    TypeMismatch {
+        path: String,
+        expected: &'static str,
+        found: String,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PatchBuilder, PatchBuilderError};
+    use crate::prefab::{write_to_world, Prefab, PrefabEntity};
+    use bevy::ecs::{
+        component::Component,
+        entity::{Entity, EntityMap},
+        reflect::{AppTypeRegistry, ReflectComponent},
+        world::World,
+    };
+    use bevy::reflect::Reflect;
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Health {
+        max: u32,
+    }
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Loot(u32);
+
+    #[derive(Reflect, Default)]
+    struct NotComponent;
+
+    #[test]
+    fn build_patch() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Health>();
+        registry.write().register::<Loot>();
+        registry.write().register::<NotComponent>();
+
+        let patch = PatchBuilder::new(&registry.read())
+            .entity(0)
+            .set("Health.max", 50u32)
+            .remove::<Loot>()
+            .entity(1)
+            .append(Loot(3))
+            .build()
+            .unwrap();
+
+        let prefab = Prefab {
+            entities: vec![PrefabEntity::new(
+                0,
+                vec![Box::new(Health { max: 10 }), Box::new(Loot(1))],
+            )],
+        };
+        let mut world = World::new();
+        world.insert_resource(registry.clone());
+        let mut entity_map = EntityMap::default();
+        write_to_world(&patch, &prefab, &mut world, &mut entity_map).unwrap();
+        let get = |entity| entity_map.get(Entity::from_raw(entity)).unwrap();
+        assert_eq!(world.get::<Health>(get(0)), Some(&Health { max: 50 }));
+        assert_eq!(world.get::<Loot>(get(0)), None);
+        assert_eq!(world.get::<Loot>(get(1)), Some(&Loot(3)));
+
+        let registry = registry.read();
+        let err = PatchBuilder::new(&registry).remove::<Loot>().build();
+        assert!(matches!(err, Err(PatchBuilderError::NoEntity)));
+        let err = PatchBuilder::new(&registry)
+            .entity(0)
+            .set("Health.max", 50i32)
+            .build();
+        assert!(matches!(err, Err(PatchBuilderError::TypeMismatch { .. })));
+        let err = PatchBuilder::new(&registry)
+            .entity(0)
+            .append(NotComponent)
+            .build();
+        assert!(matches!(
+            err,
+            Err(PatchBuilderError::UnknownComponent { .. })
+        ));
+        let err = PatchBuilder::new(&registry)
+            .entity(0)
+            .set("Health.min", 0u32)
+            .build();
+        assert!(matches!(err, Err(PatchBuilderError::Path(_))));
+    }
+}
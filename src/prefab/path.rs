@@ -0,0 +1,436 @@
+use super::PrefabError;
+use bevy::reflect::{TypeInfo, TypeRegistryInternal, Typed};
+use std::any::TypeId;
+
+/// A reflect path that was checked against the [`TypeInfo`] of the type it starts at.
+///
+/// The string form is the same one accepted by [`GetPath`](bevy::reflect::GetPath),
+/// so it can be used directly as a key of [`PatchEntity::modify`](super::PatchEntity).
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy::reflect::TypeRegistryInternal;
+/// # use bevy_nursery::prefab::FieldPath;
+/// let mut registry = TypeRegistryInternal::default();
+/// registry.register::<Transform>();
+/// registry.register::<Vec3>();
+///
+/// let path = FieldPath::builder::<Transform>(&registry)
+///     .field("translation")
+///     .field("x")
+///     .build()
+///     .unwrap();
+/// assert_eq!(path.as_str(), "translation.x");
+///
+/// assert!(FieldPath::parse::<Transform>(&registry, "translaton.x").is_err());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FieldPath {
+    type_name: &'static str,
+    path: String,
+    leaf: Option<TypeId>,
+}
+
+impl FieldPath {
+    /// Start building a path into the type `T`.
+    pub fn builder<T: Typed>(registry: &TypeRegistryInternal) -> FieldPathBuilder<'_> {
+        FieldPathBuilder::new(T::type_info(), registry)
+    }
+
+    /// Parse and validate a path into the type `T`.
+    pub fn parse<T: Typed>(
+        registry: &TypeRegistryInternal,
+        path: &str,
+    ) -> Result<Self, FieldPathError> {
+        Self::parse_with_info(T::type_info(), registry, path)
+    }
+
+    /// Parse and validate a path into the type described by `info`.
+    pub fn parse_with_info(
+        info: &'static TypeInfo,
+        registry: &TypeRegistryInternal,
+        path: &str,
+    ) -> Result<Self, FieldPathError> {
+        let mut builder = FieldPathBuilder::new(info, registry);
+        for segment in Segment::parse(path)? {
+            builder = match segment {
+                Segment::Field(name) => builder.field(name),
+                Segment::Index(index) => builder.index(index),
+            };
+        }
+        builder.build()
+    }
+
+    /// The name of the type this path starts at.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The [`TypeId`] of the value this path points to.
+    ///
+    /// It is `None` if the path goes through an enum,
+    /// as the active variant is only known at runtime.
+    pub fn leaf_type_id(&self) -> Option<TypeId> {
+        self.leaf
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    pub fn into_string(self) -> String {
+        self.path
+    }
+}
+
+impl AsRef<str> for FieldPath {
+    fn as_ref(&self) -> &str {
+        &self.path
+    }
+}
+
+impl From<FieldPath> for String {
+    fn from(path: FieldPath) -> Self {
+        path.path
+    }
+}
+
+impl std::fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.path)
+    }
+}
+
+/// Builder for a [`FieldPath`], see [`FieldPath::builder`].
+///
+/// Every step is checked against the [`TypeInfo`] of the current value.
+/// The first error is kept and returned from [`FieldPathBuilder::build`].
+pub struct FieldPathBuilder<'r> {
+    registry: &'r TypeRegistryInternal,
+    type_name: &'static str,
+    path: String,
+    current: Result<Option<&'static TypeInfo>, FieldPathError>,
+}
+
+impl<'r> FieldPathBuilder<'r> {
+    pub fn new(info: &'static TypeInfo, registry: &'r TypeRegistryInternal) -> Self {
+        Self {
+            registry,
+            type_name: info.type_name(),
+            path: String::new(),
+            current: Ok(Some(info)),
+        }
+    }
+
+    /// Access a named field of a struct, or a field of a tuple (struct) by its index.
+    pub fn field(mut self, name: &str) -> Self {
+        if !self.path.is_empty() {
+            self.path.push('.');
+        }
+        self.path.push_str(name);
+        self.step(Segment::Field(name))
+    }
+
+    /// Access an element of a list or an array.
+    pub fn index(mut self, index: usize) -> Self {
+        self.path.push_str(&format!("[{}]", index));
+        self.step(Segment::Index(index))
+    }
+
+    pub fn build(self) -> Result<FieldPath, FieldPathError> {
+        let leaf = self.current?.map(TypeInfo::type_id);
+        Ok(FieldPath {
+            type_name: self.type_name,
+            path: self.path,
+            leaf,
+        })
+    }
+
+    fn step(mut self, segment: Segment) -> Self {
+        if let Ok(Some(info)) = self.current {
+            self.current = resolve(self.registry, info, segment);
+        }
+        self
+    }
+}
+
+/// An error produced while validating a [`FieldPath`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum FieldPathError {
+    #[error("malformed path `{path}`")]
+    Syntax { path: String },
+    #[error("`{type_name}` doesn't have a field `{field}`")]
+    UnknownField {
+        type_name: &'static str,
+        field: String,
+    },
+    #[error("`{type_name}` doesn't have an element at index {index}")]
+    InvalidIndex {
+        type_name: &'static str,
+        index: usize,
+    },
+    #[error("`{type_name}` can't be accessed with `{segment}`")]
+    NotTraversable {
+        type_name: &'static str,
+        segment: String,
+    },
+    #[error("the type `{type_name}` is not registered")]
+    UnregisteredType { type_name: &'static str },
+}
+
+/// Depth of the paths listed by [`valid_paths`].
+const VALID_PATHS_DEPTH: usize = 4;
+
+/// The paths into the type described by `info`, for error messages.
+///
+/// Fields of structs and tuples are entered, lists, maps and enums are listed but not entered.
+pub(crate) fn valid_paths(info: &TypeInfo, registry: &TypeRegistryInternal) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_paths(info, registry, "", VALID_PATHS_DEPTH, &mut paths);
+    paths
+}
+
+fn collect_paths(
+    info: &TypeInfo,
+    registry: &TypeRegistryInternal,
+    prefix: &str,
+    depth: usize,
+    paths: &mut Vec<String>,
+) {
+    let fields: Vec<(String, TypeId)> = match info {
+        TypeInfo::Struct(info) => info
+            .iter()
+            .map(|field| (field.name().to_string(), field.type_id()))
+            .collect(),
+        TypeInfo::TupleStruct(info) => info
+            .iter()
+            .map(|field| (field.index().to_string(), field.type_id()))
+            .collect(),
+        TypeInfo::Tuple(info) => info
+            .iter()
+            .map(|field| (field.index().to_string(), field.type_id()))
+            .collect(),
+        _ => return,
+    };
+
+    for (name, type_id) in fields {
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        paths.push(path.clone());
+        if let Some(info) = registry.get_type_info(type_id).filter(|_| depth > 1) {
+            collect_paths(info, registry, &path, depth - 1, paths);
+        }
+    }
+}
+
+/// [`PrefabError::PatchContainsWrongPath`] for a patched value described by `info`.
+pub(crate) fn wrong_path(
+    info: Option<&TypeInfo>,
+    path: &str,
+    err: impl ToString,
+    registry: &TypeRegistryInternal,
+) -> PrefabError {
+    PrefabError::PatchContainsWrongPath {
+        path: path.to_string(),
+        err: err.to_string(),
+        valid: info.map_or_else(Vec::new, |info| valid_paths(info, registry)),
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Segment<'a> {
+    Field(&'a str),
+    Index(usize),
+}
+
+impl<'a> Segment<'a> {
+    fn parse(path: &'a str) -> Result<Vec<Self>, FieldPathError> {
+        let syntax = || FieldPathError::Syntax {
+            path: path.to_string(),
+        };
+
+        let mut segments = Vec::new();
+        let mut rest = path.strip_prefix('.').unwrap_or(path);
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix('[') {
+                let end = tail.find(']').ok_or_else(syntax)?;
+                let index = tail[..end].parse().map_err(|_| syntax())?;
+                segments.push(Segment::Index(index));
+                rest = &tail[end + 1..];
+                rest = rest.strip_prefix('.').unwrap_or(rest);
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                if end == 0 {
+                    return Err(syntax());
+                }
+                segments.push(Segment::Field(&rest[..end]));
+                rest = &rest[end..];
+                if let Some(tail) = rest.strip_prefix('.') {
+                    if tail.is_empty() {
+                        return Err(syntax());
+                    }
+                    rest = tail;
+                }
+            }
+        }
+
+        Ok(segments)
+    }
+}
+
+impl std::fmt::Display for Segment<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Field(name) => write!(f, ".{}", name),
+            Segment::Index(index) => write!(f, "[{}]", index),
+        }
+    }
+}
+
+/// Resolve the type of the value behind `segment`.
+///
+/// Returns `None` when the value can't be known statically (e.g. inside an enum).
+fn resolve(
+    registry: &TypeRegistryInternal,
+    info: &'static TypeInfo,
+    segment: Segment,
+) -> Result<Option<&'static TypeInfo>, FieldPathError> {
+    let type_name = info.type_name();
+    let not_traversable = || FieldPathError::NotTraversable {
+        type_name,
+        segment: segment.to_string(),
+    };
+
+    let (field_type_name, field_type_id) = match (info, segment) {
+        (TypeInfo::Struct(info), Segment::Field(name)) => {
+            let field = info
+                .field(name)
+                .ok_or_else(|| FieldPathError::UnknownField {
+                    type_name,
+                    field: name.to_string(),
+                })?;
+            (field.type_name(), field.type_id())
+        }
+        (TypeInfo::TupleStruct(info), Segment::Field(name)) => {
+            let field = name.parse().ok().and_then(|index| info.field_at(index));
+            let field = field.ok_or_else(|| FieldPathError::UnknownField {
+                type_name,
+                field: name.to_string(),
+            })?;
+            (field.type_name(), field.type_id())
+        }
+        (TypeInfo::Tuple(info), Segment::Field(name)) => {
+            let field = name.parse().ok().and_then(|index| info.field_at(index));
+            let field = field.ok_or_else(|| FieldPathError::UnknownField {
+                type_name,
+                field: name.to_string(),
+            })?;
+            (field.type_name(), field.type_id())
+        }
+        (TypeInfo::List(info), Segment::Index(_)) => (info.item_type_name(), info.item_type_id()),
+        (TypeInfo::Array(info), Segment::Index(index)) => {
+            if index >= info.capacity() {
+                return Err(FieldPathError::InvalidIndex { type_name, index });
+            }
+            (info.item_type_name(), info.item_type_id())
+        }
+        (TypeInfo::Enum(_), _) => return Ok(None),
+        _ => return Err(not_traversable()),
+    };
+
+    let info = registry.get_type_info(field_type_id);
+    let info = info.ok_or(FieldPathError::UnregisteredType {
+        type_name: field_type_name,
+    })?;
+    Ok(Some(info))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FieldPath, FieldPathError};
+    use bevy::reflect::{Reflect, TypeRegistryInternal};
+    use std::any::TypeId;
+
+    #[derive(Reflect, Default)]
+    struct Inner {
+        value: f32,
+    }
+
+    #[derive(Reflect, Default)]
+    struct Pair(u32, Inner);
+
+    #[derive(Reflect, Default)]
+    struct Outer {
+        inner: Inner,
+        pair: Pair,
+        list: Vec<Inner>,
+        array: [u8; 2],
+        option: Option<Inner>,
+    }
+
+    fn registry() -> TypeRegistryInternal {
+        let mut registry = TypeRegistryInternal::default();
+        registry.register::<Outer>();
+        registry.register::<Inner>();
+        registry.register::<Pair>();
+        registry.register::<Vec<Inner>>();
+        registry.register::<[u8; 2]>();
+        registry.register::<Option<Inner>>();
+        registry
+    }
+
+    #[test]
+    fn build_nested_path() {
+        let registry = registry();
+
+        let path = FieldPath::builder::<Outer>(&registry)
+            .field("inner")
+            .field("value")
+            .build()
+            .unwrap();
+        assert_eq!(path.as_str(), "inner.value");
+        assert_eq!(path.leaf_type_id(), Some(TypeId::of::<f32>()));
+
+        let path = FieldPath::builder::<Outer>(&registry)
+            .field("list")
+            .index(3)
+            .field("value")
+            .build()
+            .unwrap();
+        assert_eq!(path.as_str(), "list[3].value");
+    }
+
+    #[test]
+    fn parse_path() {
+        let registry = registry();
+
+        let path = FieldPath::parse::<Outer>(&registry, "pair.1.value").unwrap();
+        assert_eq!(path.leaf_type_id(), Some(TypeId::of::<f32>()));
+
+        let path = FieldPath::parse::<Outer>(&registry, "array[1]").unwrap();
+        assert_eq!(path.leaf_type_id(), Some(TypeId::of::<u8>()));
+
+        let path = FieldPath::parse::<Outer>(&registry, "option.0.whatever").unwrap();
+        assert_eq!(path.leaf_type_id(), None);
+    }
+
+    #[test]
+    fn reject_invalid_path() {
+        let registry = registry();
+
+        let err = FieldPath::parse::<Outer>(&registry, "inner.valeu").unwrap_err();
+        assert!(matches!(err, FieldPathError::UnknownField { field, .. } if field == "valeu"));
+
+        let err = FieldPath::parse::<Outer>(&registry, "array[2]").unwrap_err();
+        assert!(matches!(err, FieldPathError::InvalidIndex { index: 2, .. }));
+
+        let err = FieldPath::parse::<Outer>(&registry, "inner[0]").unwrap_err();
+        assert!(matches!(err, FieldPathError::NotTraversable { .. }));
+
+        let err = FieldPath::parse::<Outer>(&registry, "inner.").unwrap_err();
+        assert!(matches!(err, FieldPathError::Syntax { .. }));
+    }
+}
@@ -0,0 +1,219 @@
+use super::{Prefab, PrefabBuilder, PrefabSerializer};
+use bevy::{
+    asset::AssetServer,
+    ecs::{
+        entity::Entity,
+        event::{Event, EventWriter},
+        reflect::AppTypeRegistry,
+        system::{Command, ResMut, Resource},
+        world::{Mut, World},
+    },
+    log::{error, info_span},
+    tasks::{IoTaskPool, Task, TaskPool},
+};
+use futures_lite::future;
+use std::{path::PathBuf, sync::Arc};
+
+#[derive(Debug, thiserror::Error)]
+pub enum PrefabWriterError {
+    #[error("prefab could not be serialized: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("prefab could not be written: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Sent by the [`PrefabWriter`] once a prefab is written to disk.
+#[derive(Event, Clone, Debug)]
+pub struct PrefabSaved {
+    pub path: PathBuf,
+}
+
+/// Sent by the [`PrefabWriter`] when saving a prefab fails.
+///
+/// The error is also logged.
+#[derive(Event, Clone, Debug)]
+pub struct PrefabSaveError {
+    pub path: PathBuf,
+    pub error: Arc<PrefabWriterError>,
+}
+
+/// Saves prefabs to disk as ron, in the background.
+///
+/// Prefabs are serialized right away, sorted, handles as the path of their asset,
+/// and written on the [`IoTaskPool`]. Missing directories are created.
+/// [`prefab_writer_system`] sends [`PrefabSaved`] or [`PrefabSaveError`] once a write is done.
+#[derive(Default, Resource)]
+pub struct PrefabWriter {
+    tasks: Vec<Task<(PathBuf, Result<(), PrefabWriterError>)>>,
+}
+
+impl PrefabWriter {
+    /// Save a prefab to `path`.
+    pub fn save(&mut self, world: &World, prefab: &Prefab, path: impl Into<PathBuf>) {
+        let path = path.into();
+        let _span = info_span!("prefab_save", path = %path.display()).entered();
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let mut serializer = PrefabSerializer::new(prefab, &registry).with_sorted(true);
+        if let Some(asset_server) = world.get_resource::<AssetServer>() {
+            serializer = serializer.with_asset_server(asset_server);
+        }
+        let config = ron::ser::PrettyConfig::default()
+            .indentor(String::from("  "))
+            .new_line(String::from("\n"));
+        let text = ron::ser::to_string_pretty(&serializer, config);
+
+        let pool = IoTaskPool::init(TaskPool::default);
+        let task = pool.spawn(async move {
+            let written = text.map_err(PrefabWriterError::from).and_then(|text| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, text)?;
+                Ok(())
+            });
+            (path, written)
+        });
+        self.tasks.push(task);
+    }
+
+    /// Save entities of the world to `path`, with all their descendants.
+    ///
+    /// They are extracted with the [`PrefabBuilder`], renumbered from 0.
+    pub fn save_entities(
+        &mut self,
+        world: &World,
+        entities: impl IntoIterator<Item = Entity>,
+        path: impl Into<PathBuf>,
+    ) {
+        let mut builder = PrefabBuilder::from_world(world).with_dense_ids();
+        for entity in entities {
+            builder.extract_entity_recursive(entity);
+        }
+        self.save(world, &builder.build(), path);
+    }
+
+    /// Number of saves that are not written yet.
+    pub fn pending(&self) -> usize {
+        self.tasks.len()
+    }
+}
+
+/// Request to save a prefab, or entities of the world, to disk.
+///
+/// It is a [`Command`], the same as calling [`PrefabWriter::save`]
+/// or [`PrefabWriter::save_entities`]: `commands.add(SavePrefab::new(prefab, "level.prefab"))`.
+pub struct SavePrefab {
+    source: SaveSource,
+    path: PathBuf,
+}
+
+enum SaveSource {
+    Prefab(Prefab),
+    Entities(Vec<Entity>),
+}
+
+impl SavePrefab {
+    pub fn new(prefab: Prefab, path: impl Into<PathBuf>) -> Self {
+        Self {
+            source: SaveSource::Prefab(prefab),
+            path: path.into(),
+        }
+    }
+
+    /// Save entities with all their descendants, see [`PrefabWriter::save_entities`].
+    pub fn entities(entities: impl IntoIterator<Item = Entity>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            source: SaveSource::Entities(entities.into_iter().collect()),
+            path: path.into(),
+        }
+    }
+}
+
+impl Command for SavePrefab {
+    fn apply(self, world: &mut World) {
+        world.resource_scope(|world, mut writer: Mut<PrefabWriter>| match self.source {
+            SaveSource::Prefab(prefab) => writer.save(world, &prefab, self.path),
+            SaveSource::Entities(entities) => writer.save_entities(world, entities, self.path),
+        });
+    }
+}
+
+/// System that reports the saves of the [`PrefabWriter`] that are done.
+pub fn prefab_writer_system(
+    mut writer: ResMut<PrefabWriter>,
+    mut saved: EventWriter<PrefabSaved>,
+    mut failed: EventWriter<PrefabSaveError>,
+) {
+    writer.tasks.retain_mut(|task| {
+        let Some((path, written)) = future::block_on(future::poll_once(task)) else {
+            return true;
+        };
+        match written {
+            Ok(()) => saved.send(PrefabSaved { path }),
+            Err(err) => {
+                error!("failed to save prefab `{}`: {}", path.display(), err);
+                failed.send(PrefabSaveError {
+                    path,
+                    error: Arc::new(err),
+                });
+            }
+        }
+        false
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrefabSaveError, PrefabSaved, PrefabWriter, SavePrefab};
+    use crate::prefab::{test_utils, Prefab};
+    use bevy::ecs::{
+        component::Component,
+        event::Events,
+        reflect::{AppTypeRegistry, ReflectComponent},
+        system::{CommandQueue, Commands},
+    };
+    use bevy::reflect::Reflect;
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Health(u32);
+
+    #[test]
+    fn save_entities() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Health>();
+        let entity = app.world.spawn(Health(7)).id();
+
+        let dir = std::env::temp_dir().join(format!("prefab_save_{}", std::process::id()));
+        let path = dir.join("levels/level.prefab");
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        commands.add(SavePrefab::entities([entity], &path));
+        commands.add(SavePrefab::new(
+            Prefab::default(),
+            dir.join("nope\0.prefab"),
+        ));
+        queue.apply(&mut app.world);
+
+        let mut saved = app.world.resource::<Events<PrefabSaved>>().get_reader();
+        let mut failed = app.world.resource::<Events<PrefabSaveError>>().get_reader();
+        let (mut paths, mut failures) = (Vec::new(), 0);
+        while app.world.resource::<PrefabWriter>().pending() > 0 {
+            app.update();
+            let events = app.world.resource::<Events<PrefabSaved>>();
+            paths.extend(saved.iter(events).map(|saved| saved.path.clone()));
+            failures += failed.iter(app.world.resource()).count();
+        }
+        assert_eq!(paths, std::slice::from_ref(&path));
+        assert_eq!(failures, 1);
+
+        let registry = app.world.resource::<AppTypeRegistry>().0.clone();
+        let text = std::fs::read(&path).unwrap();
+        let prefab = Prefab::deserialize_ron(&text, &registry).unwrap();
+        assert_eq!(prefab.entities.len(), 1);
+        assert_eq!(prefab.entities[0].entity, 0);
+        let components = &prefab.entities[0].components;
+        assert!(components[0].reflect_partial_eq(&Health(7)).unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use super::{builder::map_value, clone_compact, Prefab, PrefabEntity, PrefabError};
+use bevy::ecs::entity::{Entity, EntityMap};
+use bevy::reflect::TypeRegistryInternal;
+use bevy::scene::{DynamicEntity, DynamicScene};
+
+impl Prefab {
+    /// Convert a [`DynamicScene`], like one loaded from a `.scn.ron` file, into a prefab.
+    ///
+    /// Entities keep their index as id, and references to them are mapped the same way.
+    /// Components referencing an entity that isn't in the scene are dropped,
+    /// like with [`PrefabBuilder::build_mapped`](super::PrefabBuilder::build_mapped).
+    /// Resources of the scene are dropped.
+    pub fn from_dynamic_scene(scene: &DynamicScene, registry: &TypeRegistryInternal) -> Self {
+        let mut map = EntityMap::default();
+        for entry in &scene.entities {
+            map.insert(entry.entity, Entity::from_raw(entry.entity.index()));
+        }
+
+        let entities = scene.entities.iter().map(|entry| {
+            let components = entry.components.iter().filter_map(|component| {
+                let mut component = clone_compact(component.as_ref(), registry);
+                map_value(component.as_mut(), &map).then_some(component)
+            });
+            PrefabEntity::new(entry.entity.index(), components.collect())
+        });
+
+        Self {
+            entities: entities.collect(),
+        }
+    }
+
+    /// Convert this prefab into a [`DynamicScene`], for tools built on top of `bevy_scene`.
+    ///
+    /// Entities are [`Entity::from_raw`] of their id, like references to them in components.
+    /// Serialized components are deserialized, which fails for unregistered types.
+    pub fn to_dynamic_scene(
+        &self,
+        registry: &TypeRegistryInternal,
+    ) -> Result<DynamicScene, PrefabError> {
+        let mut entities = Vec::with_capacity(self.entities.len());
+        for entry in &self.entities {
+            let mut components = Vec::with_capacity(entry.components.len());
+            for component in &entry.components {
+                components.push(clone_compact(component.as_ref(), registry));
+            }
+            for component in &entry.serialized {
+                let component = component.materialize(registry)?;
+                components.push(clone_compact(component, registry));
+            }
+            entities.push(DynamicEntity {
+                entity: Entity::from_raw(entry.entity),
+                components,
+            });
+        }
+
+        Ok(DynamicScene {
+            resources: Vec::new(),
+            entities,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prefab::Prefab;
+    use bevy::ecs::{
+        component::Component,
+        entity::Entity,
+        reflect::{AppTypeRegistry, ReflectComponent},
+        world::World,
+    };
+    use bevy::reflect::Reflect;
+    use bevy::scene::DynamicScene;
+
+    #[derive(Component, Reflect)]
+    #[reflect(Component)]
+    struct Target(Entity);
+
+    impl Default for Target {
+        fn default() -> Self {
+            Self(Entity::PLACEHOLDER)
+        }
+    }
+
+    #[test]
+    fn from_dynamic_scene() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Target>();
+        let mut world = World::new();
+        world.insert_resource(registry.clone());
+
+        // Bump the generation of the first entity.
+        let despawned = world.spawn_empty().id();
+        world.despawn(despawned);
+        let target = world.spawn_empty().id();
+        world.spawn(Target(target));
+        assert_ne!(target, Entity::from_raw(target.index()));
+
+        let scene = DynamicScene::from_world(&world);
+        let prefab = Prefab::from_dynamic_scene(&scene, &registry.read());
+        assert_eq!(prefab.entities.len(), 2);
+        let entity = prefab.entities.iter().find(|e| !e.components.is_empty());
+        let component = entity.unwrap().components[0].as_ref();
+        let expected = Entity::from_raw(target.index());
+        assert_eq!(component.downcast_ref::<Target>().unwrap().0, expected);
+
+        let scene = prefab.to_dynamic_scene(&registry.read()).unwrap();
+        assert_eq!(scene.entities.len(), 2);
+        let entity = scene.entities.iter().find(|e| !e.components.is_empty());
+        let component = entity.unwrap().components[0].downcast_ref::<Target>();
+        assert_eq!(component.unwrap().0, expected);
+        assert!(scene.entities.iter().any(|e| e.entity == expected));
+    }
+}
@@ -1,32 +1,77 @@
-use super::{Prefab, PrefabEntity};
+use super::{
+    compact::to_concrete,
+    handle::{has_handle_paths, with_handle_paths},
+    FieldPath, Patch, PatchEntity, Prefab, PrefabEntity, PrefabUuid, PrefabVariant,
+    SerializedComponent,
+};
+use bevy::asset::AssetServer;
 use bevy::reflect::{
     serde::{TypedReflectDeserializer, TypedReflectSerializer},
-    Reflect, TypeRegistryInternal,
+    Reflect, TypeRegistration, TypeRegistryInternal,
 };
+use bevy::utils::HashMap;
 use serde::{
     de::{DeserializeSeed, Error, MapAccess, Visitor},
-    ser::SerializeMap,
+    ser::{Error as _, SerializeMap},
 };
+use std::borrow::Cow;
 
 pub struct PrefabSerializer<'a> {
     prefab: &'a Prefab,
     registry: &'a TypeRegistryInternal,
+    asset_server: Option<&'a AssetServer>,
+    sorted: bool,
 }
 
 impl<'a> PrefabSerializer<'a> {
     pub fn new(prefab: &'a Prefab, registry: &'a TypeRegistryInternal) -> Self {
-        Self { prefab, registry }
+        Self {
+            prefab,
+            registry,
+            asset_server: None,
+            sorted: false,
+        }
+    }
+
+    /// Write handles as the path of their asset, when `asset_server` knows it.
+    ///
+    /// See [`register_handle_path`](super::register_handle_path).
+    pub fn with_asset_server(mut self, asset_server: &'a AssetServer) -> Self {
+        self.asset_server = Some(asset_server);
+        self
+    }
+
+    /// Write entities sorted by id and their components sorted by type name,
+    /// instead of in the order of the prefab.
+    ///
+    /// The same prefab then always gives the same output, whatever order it was built in,
+    /// which keeps files stable under version control.
+    pub fn with_sorted(mut self, sorted: bool) -> Self {
+        self.sorted = sorted;
+        self
     }
 }
 
 impl<'a> serde::Serialize for PrefabSerializer<'a> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let registry = self.registry;
-        let mut state = serializer.serialize_map(Some(self.prefab.entities.len()))?;
-        for PrefabEntity { entity, components } in &self.prefab.entities {
+        let mut entities: Vec<&PrefabEntity> = self.prefab.entities.iter().collect();
+        if self.sorted {
+            entities.sort_by_key(|prefab_entity| prefab_entity.entity);
+        }
+        let mut state = serializer.serialize_map(Some(entities.len()))?;
+        for prefab_entity in entities {
+            let PrefabEntity {
+                entity,
+                components,
+                serialized,
+            } = prefab_entity;
             let value = ComponentsSerializer {
                 components,
+                serialized,
                 registry,
+                asset_server: self.asset_server,
+                sorted: self.sorted,
             };
             state.serialize_entry(entity, &value)?;
         }
@@ -36,15 +81,48 @@ impl<'a> serde::Serialize for PrefabSerializer<'a> {
 
 pub struct ComponentsSerializer<'a> {
     components: &'a [Box<dyn Reflect>],
+    serialized: &'a [SerializedComponent],
     registry: &'a TypeRegistryInternal,
+    asset_server: Option<&'a AssetServer>,
+    sorted: bool,
 }
 
 impl<'a> serde::Serialize for ComponentsSerializer<'a> {
     fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut state = serializer.serialize_map(Some(self.components.len()))?;
+        let len = self.components.len() + self.serialized.len();
+        let mut state = serializer.serialize_map(Some(len))?;
+
+        let serialized = self.serialized.iter().map(|component| {
+            component
+                .materialize(self.registry)
+                .map_err(S::Error::custom)
+        });
+        let serialized = serialized.collect::<Result<Vec<_>, _>>()?;
+
+        let components = self.components.iter().map(AsRef::as_ref).chain(serialized);
+        let mut components: Vec<&dyn Reflect> = components.collect();
+        if self.sorted {
+            components.sort_by_key(|component| component.type_name());
+        }
 
-        for component in self.components.iter().map(AsRef::as_ref) {
-            let value = TypedReflectSerializer::new(component, self.registry);
+        for component in components {
+            // Enums read by the reflect deserializer don't know their variant index,
+            // which is serialized, their concrete value does. Handles written as paths are kept.
+            let concrete = match component.get_represented_type_info() {
+                Some(info)
+                    if component.as_any().type_id() != info.type_id()
+                        && !has_handle_paths(info.type_id(), self.registry) =>
+                {
+                    to_concrete(component, self.registry)
+                }
+                _ => None,
+            };
+            let component = concrete.as_deref().unwrap_or(component);
+            let with_paths = self
+                .asset_server
+                .and_then(|asset_server| with_handle_paths(component, self.registry, asset_server));
+            let value = with_paths.as_deref().unwrap_or(component);
+            let value = TypedReflectSerializer::new(value, self.registry);
             state.serialize_entry(component.type_name(), &value)?;
         }
 
@@ -91,13 +169,328 @@ impl<'a, 'de> Visitor<'de> for PrefabDeserializer<'a> {
         };
 
         while let Some((entity, components)) = map.next_entry_seed(kseed, vseed)? {
-            entities.push(PrefabEntity { entity, components })
+            entities.push(PrefabEntity::new(entity, components))
+        }
+
+        Ok(entities)
+    }
+}
+
+/// Deserialize several prefabs keyed by their label, like `{ "Barrel": { 0: { ... } } }`.
+pub struct LabeledPrefabsDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> LabeledPrefabsDeserializer<'a> {
+    pub fn new(registry: &'a TypeRegistryInternal) -> Self {
+        Self { registry }
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for LabeledPrefabsDeserializer<'a> {
+    type Value = Vec<(String, Prefab)>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for LabeledPrefabsDeserializer<'a> {
+    type Value = Vec<(String, Prefab)>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of labeled prefabs")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut prefabs = Vec::new();
+        while let Some(label) = map.next_key::<String>()? {
+            let prefab = map.next_value_seed(PrefabDeserializer::new(self.registry))?;
+            prefabs.push((label, prefab));
+        }
+        Ok(prefabs)
+    }
+}
+
+/// Deserialize a [`Patch`], like the one of a [`PrefabVariant`].
+///
+/// Entities are patched by id, or by `uuid` or `name` when they are given:
+/// `(ignore: [2], entities: { 0: (name: "weapon", modify: { "Type": { "field.path": value } }) })`.
+/// Entities also take `append`, components like in a prefab, `remove`, a list of type names,
+/// and `parent`, like `Entity(3)` or `Root`.
+/// Modified fields are read as the type of the field, found from its path in the component.
+pub struct PatchDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> PatchDeserializer<'a> {
+    pub fn new(registry: &'a TypeRegistryInternal) -> Self {
+        Self { registry }
+    }
+}
+
+const PATCH_FIELDS: &[&str] = &["ignore", "entities"];
+
+impl<'a, 'de> DeserializeSeed<'de> for PatchDeserializer<'a> {
+    type Value = Patch;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("Patch", PATCH_FIELDS, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for PatchDeserializer<'a> {
+    type Value = Patch;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("patch")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut patch = Patch::default();
+        while let Some(FieldName(key)) = map.next_key()? {
+            match &*key {
+                "ignore" => patch.ignore.extend(map.next_value::<Vec<u32>>()?),
+                "entities" => {
+                    let seed = PatchEntitiesDeserializer {
+                        registry: self.registry,
+                    };
+                    patch.modify = map.next_value_seed(seed)?;
+                }
+                _ => return Err(Error::unknown_field(&key, PATCH_FIELDS)),
+            }
         }
+        Ok(patch)
+    }
+}
+
+struct PatchEntitiesDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for PatchEntitiesDeserializer<'a> {
+    type Value = Vec<PatchEntity>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for PatchEntitiesDeserializer<'a> {
+    type Value = Vec<PatchEntity>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of patched entities")
+    }
 
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut entities = Vec::new();
+        while let Some(entity) = map.next_key::<u32>()? {
+            let seed = PatchEntityDeserializer {
+                registry: self.registry,
+                entity,
+            };
+            entities.push(map.next_value_seed(seed)?);
+        }
         Ok(entities)
     }
 }
 
+const PATCH_ENTITY_FIELDS: &[&str] = &["uuid", "name", "append", "modify", "remove", "parent"];
+
+struct PatchEntityDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+    entity: u32,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for PatchEntityDeserializer<'a> {
+    type Value = PatchEntity;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("PatchEntity", PATCH_ENTITY_FIELDS, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for PatchEntityDeserializer<'a> {
+    type Value = PatchEntity;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("patched entity")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let registry = self.registry;
+        let mut patch = PatchEntity {
+            entity: self.entity,
+            uuid: None,
+            name: None,
+            append: Vec::new(),
+            modify: HashMap::default(),
+            remove: Default::default(),
+            parent: None,
+        };
+        while let Some(FieldName(key)) = map.next_key()? {
+            match &*key {
+                "uuid" => patch.uuid = Some(map.next_value::<PrefabUuid>()?),
+                "name" => patch.name = Some(map.next_value()?),
+                "append" => {
+                    patch.append = map.next_value_seed(ComponentsDeserializer { registry })?
+                }
+                "modify" => patch.modify = map.next_value_seed(ModifyDeserializer { registry })?,
+                "remove" => patch.remove.extend(map.next_value::<Vec<String>>()?),
+                "parent" => patch.parent = Some(map.next_value()?),
+                _ => return Err(Error::unknown_field(&key, PATCH_ENTITY_FIELDS)),
+            }
+        }
+        Ok(patch)
+    }
+}
+
+/// Modified fields by component type name.
+struct ModifyDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for ModifyDeserializer<'a> {
+    type Value = HashMap<String, HashMap<String, Box<dyn Reflect>>>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for ModifyDeserializer<'a> {
+    type Value = HashMap<String, HashMap<String, Box<dyn Reflect>>>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of modified components")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut modify = HashMap::default();
+        while let Some(TypeName(type_name)) = map.next_key()? {
+            let registration = self.registry.get_with_name(&type_name).ok_or_else(|| {
+                Error::custom(format_args!("No registration found for `{}`", type_name))
+            })?;
+            let seed = FieldsDeserializer {
+                registration,
+                registry: self.registry,
+            };
+            modify.insert(type_name.into_owned(), map.next_value_seed(seed)?);
+        }
+        Ok(modify)
+    }
+}
+
+/// Modified fields of a component by their path, read as the type of the field.
+struct FieldsDeserializer<'a> {
+    registration: &'a TypeRegistration,
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for FieldsDeserializer<'a> {
+    type Value = HashMap<String, Box<dyn Reflect>>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for FieldsDeserializer<'a> {
+    type Value = HashMap<String, Box<dyn Reflect>>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("map of modified fields")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let info = self.registration.type_info();
+        let mut fields = HashMap::default();
+        while let Some(path) = map.next_key::<String>()? {
+            let field = FieldPath::parse_with_info(info, self.registry, &path);
+            let field = field.map_err(Error::custom)?;
+            let registration = field.leaf_type_id().and_then(|id| self.registry.get(id));
+            let registration = registration.ok_or_else(|| {
+                Error::custom(format_args!(
+                    "the type of `{}` is only known at runtime",
+                    path
+                ))
+            })?;
+            let seed = TypedReflectDeserializer::new(registration, self.registry);
+            fields.insert(path, map.next_value_seed(seed)?);
+        }
+        Ok(fields)
+    }
+}
+
+/// Deserialize a [`PrefabVariant`]: `(base: "goblin.prefab", patch: (...))`, see [`PatchDeserializer`].
+pub struct PrefabVariantDeserializer<'a> {
+    registry: &'a TypeRegistryInternal,
+}
+
+impl<'a> PrefabVariantDeserializer<'a> {
+    pub fn new(registry: &'a TypeRegistryInternal) -> Self {
+        Self { registry }
+    }
+}
+
+const VARIANT_FIELDS: &[&str] = &["base", "patch"];
+
+impl<'a, 'de> DeserializeSeed<'de> for PrefabVariantDeserializer<'a> {
+    type Value = PrefabVariant;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("PrefabVariant", VARIANT_FIELDS, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for PrefabVariantDeserializer<'a> {
+    type Value = PrefabVariant;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("prefab variant")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut base = None;
+        let mut patch = None;
+        while let Some(FieldName(key)) = map.next_key()? {
+            match &*key {
+                "base" => base = Some(map.next_value()?),
+                "patch" => {
+                    patch = Some(map.next_value_seed(PatchDeserializer::new(self.registry))?)
+                }
+                _ => return Err(Error::unknown_field(&key, VARIANT_FIELDS)),
+            }
+        }
+        Ok(PrefabVariant {
+            base: base.ok_or_else(|| Error::missing_field("base"))?,
+            patch: patch.unwrap_or_default(),
+        })
+    }
+}
+
 #[derive(Clone, Copy)]
 pub struct ComponentsDeserializer<'a> {
     pub registry: &'a TypeRegistryInternal,
@@ -124,8 +517,8 @@ impl<'a, 'de> Visitor<'de> for ComponentsDeserializer<'a> {
     fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
         let mut components = Vec::new();
 
-        while let Some(type_name) = map.next_key::<&str>()? {
-            let registration = self.registry.get_with_name(type_name).ok_or_else(|| {
+        while let Some(TypeName(type_name)) = map.next_key()? {
+            let registration = self.registry.get_with_name(&type_name).ok_or_else(|| {
                 Error::custom(format_args!("No registration found for `{}`", type_name))
             })?;
             let seed = TypedReflectDeserializer::new(registration, self.registry);
@@ -135,3 +528,141 @@ impl<'a, 'de> Visitor<'de> for ComponentsDeserializer<'a> {
         Ok(components)
     }
 }
+
+/// A type name, borrowed from the input when the format allows it.
+struct TypeName<'de>(Cow<'de, str>);
+
+/// A struct field name.
+///
+/// Read as a str, which ron accepts for the identifiers of struct fields, unlike a `String`.
+pub(super) struct FieldName<'de>(pub(super) Cow<'de, str>);
+
+impl<'de> serde::Deserialize<'de> for FieldName<'de> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let TypeName(name) = TypeName::deserialize(deserializer)?;
+        Ok(FieldName(name))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for TypeName<'de> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(TypeNameVisitor)
+    }
+}
+
+struct TypeNameVisitor;
+
+impl<'de> Visitor<'de> for TypeNameVisitor {
+    type Value = TypeName<'de>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("type name")
+    }
+
+    fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(TypeName(Cow::Borrowed(v)))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(TypeName(Cow::Owned(v.to_owned())))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(TypeName(Cow::Owned(v)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefabSerializer;
+    use crate::prefab::Prefab;
+    use bevy::ecs::reflect::AppTypeRegistry;
+    use bevy::reflect::Reflect;
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Size(u32);
+
+    #[test]
+    fn json_roundtrip() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Size>();
+
+        let input = format!(
+            "{{ \"3\": {{ \"{}\": [7] }} }}",
+            std::any::type_name::<Size>()
+        );
+        let prefab = Prefab::deserialize_json(input.as_bytes(), &registry.0).unwrap();
+        assert_eq!(prefab.entities[0].entity, 3);
+
+        let json = prefab.serialize_json(&registry).unwrap();
+        let prefab = Prefab::deserialize_json(json.as_bytes(), &registry.0).unwrap();
+        assert_eq!(prefab.entities[0].entity, 3);
+        assert!(prefab.entities[0].components[0]
+            .reflect_partial_eq(&Size(7))
+            .unwrap());
+    }
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Mass(u32);
+
+    #[test]
+    fn sorted_ron() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Size>();
+        registry.write().register::<Mass>();
+
+        let (size, mass) = (std::any::type_name::<Size>(), std::any::type_name::<Mass>());
+        let input = format!(
+            "{{ 5: {{ {0:?}: (1), {1:?}: (2) }}, 2: {{ {1:?}: (3) }} }}",
+            size, mass
+        );
+        let prefab = Prefab::deserialize_ron(input.as_bytes(), &registry.0).unwrap();
+        let input = format!(
+            "{{ 2: {{ {1:?}: (3) }}, 5: {{ {1:?}: (2), {0:?}: (1) }} }}",
+            size, mass
+        );
+        let shuffled = Prefab::deserialize_ron(input.as_bytes(), &registry.0).unwrap();
+
+        let serialize = |prefab: &Prefab| {
+            let registry = registry.read();
+            let value = PrefabSerializer::new(prefab, &registry).with_sorted(true);
+            ron::ser::to_string(&value).unwrap()
+        };
+        let ron = serialize(&prefab);
+        assert_eq!(ron, serialize(&shuffled));
+        assert!(ron.find("2:").unwrap() < ron.find("5:").unwrap());
+        assert!(ron.rfind(mass).unwrap() < ron.rfind(size).unwrap());
+    }
+
+    #[test]
+    fn deserialize_yaml() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Size>();
+
+        let input = format!("3:\n  {}: [7]\n", std::any::type_name::<Size>());
+        let prefab = Prefab::deserialize_yaml(input.as_bytes(), &registry.0).unwrap();
+        assert_eq!(prefab.entities[0].entity, 3);
+        assert!(prefab.entities[0].components[0]
+            .reflect_partial_eq(&Size(7))
+            .unwrap());
+    }
+
+    #[test]
+    fn deserialize_labeled() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Size>();
+
+        let input = format!(
+            "{{ \"Barrel\": {{ 0: {{ \"{0}\": (1) }} }}, \"Crate\": {{ 2: {{ \"{0}\": (2) }} }} }}",
+            std::any::type_name::<Size>()
+        );
+        let prefabs = Prefab::deserialize_ron_labeled(input.as_bytes(), &registry.0).unwrap();
+        let labels: Vec<_> = prefabs.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, ["Barrel", "Crate"]);
+        let (_, crate_prefab) = &prefabs[1];
+        assert_eq!(crate_prefab.entities[0].entity, 2);
+        assert!(crate_prefab.entities[0].components[0]
+            .reflect_partial_eq(&Size(2))
+            .unwrap());
+    }
+}
@@ -1,43 +1,108 @@
-use super::{Patch, Prefab, PrefabError};
+use super::{
+    reflect_diff,
+    write::{is_hierarchy, remove_component},
+    FieldDelta, Patch, PatchEntity, Prefab, PrefabBuilder, PrefabEntity, PrefabError,
+    PrefabInstanceReady, PrefabLoader, PrefabOrphaned, PrefabSpawnError, WriteOptions,
+};
 use bevy::{
-    asset::{AssetEvent, Assets, Handle},
+    asset::{AssetEvent, AssetServer, Assets, Error, Handle, HandleId},
+    core::Name,
     ecs::{
         bundle::Bundle,
-        component::Component,
+        component::{Component, Tick},
         entity::{Entity, EntityMap},
-        event::{Events, ManualEventReader},
-        query::Changed,
-        system::{Command, Commands, Query, ResMut, Resource},
-        world::{Mut, World},
+        event::{Event, Events, ManualEventReader},
+        query::{Changed, Or},
+        reflect::{AppTypeRegistry, ReflectComponent},
+        removal_detection::RemovedComponents,
+        system::{Command, Commands, Local, Query, Res, ResMut, Resource},
+        world::{FromWorld, Mut, World},
     },
-    hierarchy::{AddChild, Parent},
+    hierarchy::{AddChild, BuildWorldChildren, Children, Parent},
+    log::info_span,
+    math::Vec3,
+    reflect::{GetPath, Reflect, TypeRegistryInternal},
     render::view::{ComputedVisibility, Visibility},
     transform::components::{GlobalTransform, Transform},
-    utils::HashMap,
+    utils::{tracing::field::Empty, tracing::Span, Duration, HashMap, HashSet, Instant},
 };
+use std::{borrow::Cow, sync::Arc};
+
+mod hydrate;
+
+use self::hydrate::Hydrating;
+pub use self::hydrate::SpawnBudget;
 
 pub fn prefab_spawner_maintain_system(world: &mut World) {
     world.resource_scope(|world, mut spawner: Mut<PrefabSpawner>| spawner.maintain(world));
 }
 
-/// System that will spawn prefabs from [`PrefabBundle`].
+/// System that will spawn prefabs from [`PrefabBundle`] and [`PatchedPrefabBundle`].
+///
+/// Changing the handle of the prefab or of its patch replaces the instance,
+/// even if the previous one is still waiting for its asset.
+/// Removing the handle of the prefab, or despawning the entity, despawns the instance.
 #[allow(clippy::type_complexity)]
 pub fn prefab_update_system(
     mut commands: Commands,
     mut to_spawn: Query<
-        (Entity, &Handle<Prefab>, Option<&mut PrefabInstance>),
-        Changed<Handle<Prefab>>,
+        (
+            Entity,
+            &Handle<Prefab>,
+            Option<&Handle<Patch>>,
+            Option<&mut PrefabInstance>,
+        ),
+        Or<(Changed<Handle<Prefab>>, Changed<Handle<Patch>>)>,
     >,
+    mut removed: RemovedComponents<Handle<Prefab>>,
+    mut owners: Local<HashMap<Entity, PrefabInstance>>,
     mut spawner: ResMut<PrefabSpawner>,
 ) {
-    for (entity, prefab, instance) in &mut to_spawn {
-        let new = spawner.spawn(prefab.clone(), Some(entity));
+    for entity in removed.iter() {
+        let Some(instance) = owners.remove(&entity) else {
+            continue;
+        };
+        spawner.despawn(&instance);
+        if let Some(mut entity) = commands.get_entity(entity) {
+            entity.remove::<(PrefabInstance, PrefabInstanceState)>();
+        }
+    }
+
+    for (entity, prefab, patch, instance) in &mut to_spawn {
+        let new = match patch {
+            Some(patch) => {
+                spawner.spawn_with_patch_asset(prefab.clone(), Some(entity), patch.clone())
+            }
+            None => spawner.spawn(prefab.clone(), Some(entity)),
+        };
         if let Some(mut instance) = instance {
             spawner.despawn(&instance);
             *instance = new;
         } else {
             commands.entity(entity).insert(new);
         }
+        owners.insert(entity, new);
+    }
+}
+
+/// System that mirrors the [`PrefabInstanceState`] of every [`PrefabInstance`] component
+/// on the same entity.
+pub fn prefab_instance_state_system(
+    mut commands: Commands,
+    spawner: Res<PrefabSpawner>,
+    mut instances: Query<(Entity, &PrefabInstance, Option<&mut PrefabInstanceState>)>,
+) {
+    for (entity, instance, state) in &mut instances {
+        let Some(new) = spawner.state(instance) else {
+            continue;
+        };
+        match state {
+            Some(mut state) if *state != new => *state = new,
+            Some(_) => {}
+            None => {
+                commands.entity(entity).insert(new);
+            }
+        }
     }
 }
 
@@ -45,9 +110,198 @@ type Id = bevy::utils::Uuid;
 
 /// Instance identifier of a spawned prefab.
 /// It can be used with the [`PrefabSpawner`] to interact with the spawned prefab.
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct PrefabInstance(Id);
 
+impl PrefabInstance {
+    /// Use a known identifier, like one received over the network.
+    ///
+    /// See [`PrefabSpawner::spawn_with_id`].
+    pub fn from_uuid(id: Id) -> Self {
+        Self(id)
+    }
+
+    /// The identifier of the instance, the same on every peer when ids are deterministic.
+    pub fn uuid(&self) -> Id {
+        self.0
+    }
+}
+
+/// Keep this entity when the instance it descends from is despawned recursively,
+/// see [`PrefabSpawner::set_despawn_recursive`].
+///
+/// The entity is detached from its parent, its own descendants are kept with it.
+/// Entities of the instance itself are despawned in any case.
+#[derive(Component, Debug, Default, Clone, Copy)]
+pub struct KeepOnPrefabDespawn;
+
+/// Marks the roots of an instance, the entities without a parent among its entities.
+///
+/// Kept up to date when the instance is spawned, updated, patched or loses some of its entities.
+/// Roots reparented at runtime are not marked again until then.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrefabRoot {
+    pub instance: PrefabInstance,
+}
+
+/// A fix-up run on the entities of an instance, see [`PrefabHooks`].
+pub type PrefabSpawnHook = fn(&mut World, &PrefabInstanceInfo, &Handle<Prefab>);
+
+/// Hooks run right after an instance is spawned, and after it is updated on hot reload,
+/// in registration order.
+///
+/// Use them for fix-ups a component can't do on its own, like initializing physics bodies
+/// or linking navmeshes. They run while the [`PrefabSpawner`] is maintained,
+/// which is not in the world at that point, and before new instances are attached to their parent.
+#[derive(Clone, Default, Resource)]
+pub struct PrefabHooks {
+    hooks: Vec<PrefabSpawnHook>,
+}
+
+impl PrefabHooks {
+    pub fn add(&mut self, hook: PrefabSpawnHook) {
+        self.hooks.push(hook);
+    }
+
+    /// Run all hooks on an instance.
+    pub fn run(&self, world: &mut World, info: &PrefabInstanceInfo, handle: &Handle<Prefab>) {
+        for hook in &self.hooks {
+            hook(world, info, handle);
+        }
+    }
+}
+
+impl std::fmt::Debug for PrefabHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrefabHooks")
+            .field("hooks", &self.hooks.len())
+            .finish()
+    }
+}
+
+/// Run the [`PrefabHooks`] of the world on an instance.
+fn run_hooks(world: &mut World, info: &PrefabInstanceInfo, handle: &Handle<Prefab>) {
+    if let Some(hooks) = world.get_resource::<PrefabHooks>() {
+        hooks.clone().run(world, info, handle);
+    }
+}
+
+/// Lifecycle of a prefab instance, see [`PrefabSpawner::state`].
+///
+/// It is also kept up to date on entities with a [`PrefabInstance`] component.
+#[derive(Component, Debug, Clone)]
+pub enum PrefabInstanceState {
+    /// Waiting for the prefab asset, or its patch asset, to be loaded.
+    PendingAsset,
+    /// Queued to be spawned on the next maintenance.
+    Spawning,
+    /// Spawned in the world.
+    Ready,
+    /// The last spawn or hot reload of the instance failed.
+    Failed(Arc<PrefabError>),
+    /// The prefab asset was removed, the instance is left as it is.
+    ///
+    /// It is updated again once the asset is added back, see [`PrefabRemovalPolicy::Orphan`].
+    Orphaned,
+}
+
+impl PrefabInstanceState {
+    pub fn is_ready(&self) -> bool {
+        matches!(self, Self::Ready)
+    }
+}
+
+impl PartialEq for PrefabInstanceState {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Failed(a), Self::Failed(b)) => Arc::ptr_eq(a, b),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+/// What happens to the instances of a prefab asset that is removed,
+/// see [`PrefabSpawner::set_removal_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrefabRemovalPolicy {
+    /// Keep the instances as they are, their state becomes [`PrefabInstanceState::Orphaned`]
+    /// and a [`PrefabOrphaned`] event is sent for each of them.
+    ///
+    /// Adding the asset back with the same handle, like a reload, updates them again.
+    #[default]
+    Orphan,
+    /// Despawn the instances.
+    Despawn,
+}
+
+/// What to do when a prefab reached its instance limit, see [`PrefabSpawner::set_max_instances`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrefabEviction {
+    /// Don't spawn the new instance, it fails with [`PrefabError::TooManyInstances`].
+    Reject,
+    /// Despawn the oldest instance of the prefab.
+    Oldest,
+    /// Despawn the instance of the prefab that is the farthest from this entity, like the camera.
+    ///
+    /// Instances are located by the [`GlobalTransform`] of their parent, or of their first root.
+    /// The oldest instance is despawned if none of them can be located.
+    Farthest(Entity),
+}
+
+/// What updating an instance does with the changes made to it at runtime,
+/// when its prefab or patch asset is modified, see [`PrefabSpawner::set_update_policy`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrefabUpdatePolicy {
+    /// Write the whole prefab again, runtime changes are lost.
+    #[default]
+    Overwrite,
+    /// Don't update the components changed at runtime, found by their change ticks.
+    ///
+    /// Once changed, a component is no longer updated from the prefab.
+    KeepRuntimeChanges,
+    /// Write the prefab, then apply again the fields changed at runtime.
+    ///
+    /// Fields changed both at runtime and in the prefab keep the runtime value.
+    /// The values written by the last update are kept to find the changed fields.
+    Merge,
+}
+
+/// When queued spawns stop waiting for their assets, see [`PrefabSpawner::set_pending_limit`].
+///
+/// A spawn that gives up fails with [`PrefabError::PendingTimeout`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PendingLimit {
+    /// Give up after this many maintenances without the assets.
+    pub attempts: Option<u32>,
+    /// Give up once the spawn waited this long for the assets.
+    pub timeout: Option<Duration>,
+}
+
+/// What the last [`PrefabSpawner::maintain`] did, see [`PrefabSpawner::stats`].
+///
+/// The [`PrefabDiagnosticsPlugin`](super::PrefabDiagnosticsPlugin) keeps a copy of it
+/// as a resource and records it as diagnostics.
+#[derive(Debug, Clone, Default, PartialEq, Resource)]
+pub struct PrefabSpawnerStats {
+    /// Number of spawned instances.
+    pub instances: usize,
+    /// Number of queued spawns, see [`PrefabSpawner::pending_spawns`].
+    pub pending: usize,
+    /// Number of queued instances that became ready.
+    pub spawned: usize,
+    /// Number of entities of the instances that became ready.
+    pub spawned_entities: usize,
+    /// Time spent in the maintenance.
+    pub maintain_time: Duration,
+}
+
+/// A queued spawn waiting for its assets.
+struct Waiting {
+    /// Maintenances that found the assets missing.
+    attempts: u32,
+    since: Instant,
+}
+
 /// A component bundle for a [`Prefab`] root.
 ///
 /// The prefab from `prefab` will be spawn as a child of the entity with this component.
@@ -62,185 +316,3187 @@ pub struct PrefabBundle {
     pub computed_visibility: ComputedVisibility,
 }
 
+/// A [`PrefabBundle`] with a patch applied on top of the prefab, for per-placement overrides.
+///
+/// The patch can be loaded or added to the [`Assets<Patch>`] at runtime,
+/// see [`PrefabSpawner::spawn_with_patch_asset`].
+#[derive(Default, Bundle)]
+pub struct PatchedPrefabBundle {
+    /// Handle to the prefab to spawn
+    pub prefab: Handle<Prefab>,
+    /// Handle to the patch applied to the prefab
+    pub patch: Handle<Patch>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+}
+
 #[derive(Default)]
 pub struct PrefabInstanceInfo {
-    entity_map: EntityMap,
+    id: Id,
+    pub(crate) entity_map: EntityMap,
+    handle: Handle<Prefab>,
+    parent: Option<Entity>,
+    patch: Patch,
+    /// Patch asset applied under `patch`, see [`PrefabSpawner::spawn_with_patch_asset`].
+    patch_asset: Option<Handle<Patch>>,
+    /// Prefix of the names given to the entities, see [`PrefabSpawner::set_name_entities`].
+    name: Option<String>,
+    /// Types of the components written by the last spawn, by prefab entity id.
+    owned: HashMap<u32, Vec<&'static str>>,
+    /// Change tick of the last spawn, see [`PrefabUpdatePolicy::KeepRuntimeChanges`].
+    written: Option<Tick>,
+    /// Components changed at runtime, see [`PrefabUpdatePolicy::KeepRuntimeChanges`].
+    kept: HashSet<(u32, &'static str)>,
+    /// Values written by the last spawn, see [`PrefabUpdatePolicy::Merge`].
+    snapshot: HashMap<(u32, &'static str), Box<dyn Reflect>>,
+    /// Transform of the roots, see [`PrefabSpawner::spawn_at`].
+    root_transform: Option<Transform>,
 }
 
 impl PrefabInstanceInfo {
+    fn new(id: Id, handle: &Handle<Prefab>, patch: Patch) -> Self {
+        Self {
+            id,
+            entity_map: EntityMap::default(),
+            handle: handle.clone_weak(),
+            parent: None,
+            patch,
+            patch_asset: None,
+            name: None,
+            owned: HashMap::default(),
+            written: None,
+            kept: HashSet::default(),
+            snapshot: HashMap::default(),
+            root_transform: None,
+        }
+    }
+
+    /// The prefab this instance was spawned from.
+    pub fn handle(&self) -> &Handle<Prefab> {
+        &self.handle
+    }
+
     /// Get an iterator over the entities in an instance
     pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
         self.entity_map.values()
     }
 
-    fn spawn(&mut self, world: &mut World, handle: &Handle<Prefab>) -> Result<(), PrefabError> {
-        world.resource_scope(|world, prefabs: Mut<Assets<Prefab>>| {
-            let prefab = prefabs.get(handle);
-            let prefab = prefab.ok_or_else(|| PrefabError::NonExistentPrefab {
-                handle: handle.clone_weak(),
-            })?;
+    /// The entity spawned for a prefab entity, by its id in the prefab.
+    ///
+    /// `None` if the prefab has no such entity, or if it was ignored or despawned.
+    pub fn entity(&self, prefab_entity: u32) -> Option<Entity> {
+        self.entity_map.get(Entity::from_raw(prefab_entity))
+    }
 
-            let patch = Patch::default();
+    /// The entity spawned for the first prefab entity with the given [`Name`](bevy::core::Name),
+    /// see [`Prefab::entity_by_name`].
+    ///
+    /// The name is looked up in the prefab, so it is found even if the spawned entity
+    /// was renamed, like by [`PrefabSpawner::set_name_entities`].
+    pub fn entity_by_name(&self, prefabs: &Assets<Prefab>, name: &str) -> Option<Entity> {
+        let prefab = prefabs.get(&self.handle)?;
+        self.entity(prefab.entity_by_name(name)?)
+    }
 
-            super::write_to_world(&patch, prefab, world, &mut self.entity_map)
-        })
+    /// The entity spawned for the first prefab entity with the given [`PrefabLabel`](super::PrefabLabel),
+    /// see [`Prefab::entity_by_label`].
+    pub fn find_by_label(&self, prefabs: &Assets<Prefab>, label: &str) -> Option<Entity> {
+        let prefab = prefabs.get(&self.handle)?;
+        self.entity(prefab.entity_by_label(label)?)
     }
 
-    fn despawn(&mut self, world: &mut World) {
-        for entity in self.entity_map.values() {
-            let _ = world.despawn(entity);
+    /// The id of the prefab entity an entity of the instance was spawned for,
+    /// the reverse of [`PrefabInstanceInfo::entity`].
+    pub fn prefab_id_of(&self, entity: Entity) -> Option<u32> {
+        let mut entities = self.entity_map.iter();
+        let (prefab_entity, _) = entities.find(|&(_, spawned)| spawned == entity)?;
+        Some(prefab_entity.index())
+    }
+
+    /// The root of the instance with the lowest prefab entity id,
+    /// a root being an entity without a parent among the entities of the instance.
+    ///
+    /// See [`PrefabRoot`].
+    pub fn root(&self, world: &World) -> Option<Entity> {
+        let roots = self.entity_map.iter();
+        let roots = roots.filter(|&(_, entity)| self.is_root(world, entity));
+        let root = roots.min_by_key(|(prefab_entity, _)| prefab_entity.index());
+        root.map(|(_, entity)| entity)
+    }
+
+    /// The entity is marked as a root of this instance.
+    fn is_root(&self, world: &World, entity: Entity) -> bool {
+        let root = world.get::<PrefabRoot>(entity);
+        root.is_some_and(|root| root.instance.0 == self.id)
+    }
+
+    /// Mark the roots of the instance with [`PrefabRoot`], and unmark the entities that aren't anymore.
+    fn mark_roots(&self, world: &mut World) {
+        let roots: HashSet<Entity> = self.roots(world).into_iter().map(|(_, e)| e).collect();
+        let instance = PrefabInstance(self.id);
+        for entity in self.entities() {
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                continue;
+            };
+            if roots.contains(&entity.id()) {
+                entity.insert(PrefabRoot { instance });
+            } else if entity
+                .get::<PrefabRoot>()
+                .is_some_and(|r| r.instance == instance)
+            {
+                entity.remove::<PrefabRoot>();
+            }
         }
     }
-}
 
-#[derive(Default)]
-struct Spawned {
-    prefabs: HashMap<Handle<Prefab>, Vec<Id>>,
-    instances: HashMap<Id, PrefabInstanceInfo>,
-}
+    /// The roots of the instance with their prefab entity, found from their [`Parent`].
+    fn roots(&self, world: &World) -> Vec<(Entity, Entity)> {
+        let entities: HashSet<Entity> = self.entities().collect();
+        let roots = self.entity_map.iter().filter(|&(_, entity)| {
+            let parent = world.get::<Parent>(entity).map(Parent::get);
+            !parent.is_some_and(|parent| entities.contains(&parent))
+        });
+        roots.collect()
+    }
 
-impl Spawned {
-    fn spawn(&mut self, world: &mut World, handle: &Handle<Prefab>) -> Result<Id, PrefabError> {
-        let mut info = PrefabInstanceInfo::default();
-        info.spawn(world, handle)?;
+    /// Give the roots the transform of [`PrefabSpawner::spawn_at`], unless it is kept,
+    /// see [`PrefabUpdatePolicy::KeepRuntimeChanges`].
+    fn place_roots(&self, world: &mut World) {
+        let Some(transform) = self.root_transform else {
+            return;
+        };
+        let type_name = std::any::type_name::<Transform>();
+        for (prefab_entity, entity) in self.roots(world) {
+            if !self.kept.contains(&(prefab_entity.index(), type_name)) {
+                world.entity_mut(entity).insert(transform);
+            }
+        }
+    }
 
-        let id = self.generate_id();
-        self.instances.insert(id, info);
-        self.prefabs.entry(handle.clone()).or_default().push(id);
+    /// The entity the roots of this instance are attached to.
+    pub fn parent(&self) -> Option<Entity> {
+        self.parent
+    }
 
-        Ok(id)
+    /// The patch applied on top of the prefab for this instance.
+    ///
+    /// It is applied again every time the prefab is updated, like when its file changes on disk.
+    pub fn patch(&self) -> &Patch {
+        &self.patch
     }
 
-    fn generate_id(&self) -> Id {
-        Id::new_v4()
+    /// The patch asset applied under [`PrefabInstanceInfo::patch`], if the instance has one.
+    pub fn patch_asset(&self) -> Option<&Handle<Patch>> {
+        self.patch_asset.as_ref()
     }
 
-    fn update(&mut self, world: &mut World, handle: &Handle<Prefab>) {
-        if let Some(spawned_instances) = self.prefabs.get(handle) {
-            for id in spawned_instances {
-                if let Some(info) = self.instances.get_mut(id) {
-                    info.spawn(world, handle).unwrap();
-                }
-            }
+    /// Position of the parent of the instance, or of its first root if it has no parent.
+    fn position(&self, world: &World) -> Option<Vec3> {
+        let entity = self.parent.or_else(|| {
+            let mut roots = self
+                .entities()
+                .filter(|&e| world.get::<Parent>(e).is_none());
+            roots.next()
+        })?;
+        let transform = world.get::<GlobalTransform>(entity)?;
+        Some(transform.translation())
+    }
+
+    /// See [`PrefabSpawner::extract_instance`].
+    fn extract(&self, world: &World) -> Prefab {
+        let mut map = EntityMap::default();
+        for (prefab_entity, entity) in self.entity_map.iter() {
+            map.insert(entity, prefab_entity);
         }
+
+        let mut builder = PrefabBuilder::from_world(world);
+        builder.extract_entities(self.entities());
+        builder.build_mapped(&map)
     }
 
-    fn despawn(&mut self, world: &mut World, id: &Id) {
-        if let Some(mut info) = self.instances.remove(id) {
-            info.despawn(world);
+    /// See [`PrefabSpawner::capture_overrides`].
+    fn overrides(&self, world: &World) -> Result<Patch, PrefabError> {
+        let live = self.extract(world);
+        let prefabs = world.resource::<Assets<Prefab>>();
+        let prefab = prefabs.get(&self.handle);
+        let prefab = prefab.ok_or_else(|| PrefabError::NonExistentPrefab {
+            handle: self.handle.clone_weak(),
+        })?;
+
+        let registry = world.resource::<AppTypeRegistry>().read();
+        Patch::diff_overrides(prefab, &live, &registry)
+    }
+
+    fn write_options(&self) -> WriteOptions {
+        WriteOptions {
+            name_prefix: self.name.clone(),
+            ..Default::default()
         }
     }
-}
 
-#[derive(Default, Resource)]
-pub struct PrefabSpawner {
-    asset_event_reader: ManualEventReader<AssetEvent<Prefab>>,
+    fn spawn(&mut self, world: &mut World, policy: PrefabUpdatePolicy) -> Result<(), PrefabError> {
+        let patch = merge_patch_asset(&self.patch, self.patch_asset.as_ref(), world)?;
 
-    spawned: Spawned,
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        if policy == PrefabUpdatePolicy::KeepRuntimeChanges {
+            let changed = self.changed_components(world, &registry);
+            self.kept.extend(changed);
+        } else {
+            self.kept.clear();
+        }
+        let merged = match policy {
+            PrefabUpdatePolicy::Merge => self.runtime_deltas(world, &registry),
+            _ => Vec::new(),
+        };
 
-    to_spawn: Vec<(Handle<Prefab>, Id)>,
-    to_despawn: Vec<Id>,
+        let handle = &self.handle;
+        let options = self.write_options();
+        let kept = &self.kept;
+        let owned = world.resource_scope(|world, prefabs: Mut<Assets<Prefab>>| {
+            let prefab = prefabs.get(handle);
+            let prefab = prefab.ok_or_else(|| PrefabError::NonExistentPrefab {
+                handle: handle.clone_weak(),
+            })?;
 
-    with_parent: Vec<(Id, Entity)>,
-    updates: Vec<Handle<Prefab>>,
-}
+            // Kept components are not written, like components removed by the patch.
+            let written = if kept.is_empty() {
+                Cow::Borrowed(&*patch)
+            } else {
+                Cow::Owned(keep_components(&patch, prefab, kept))
+            };
+            super::write_to_world_with(&written, prefab, world, &mut self.entity_map, &options)?;
+            Ok::<_, PrefabError>(owned_components(prefab, &patch, &registry))
+        })?;
+        remove_placeholders(world, &mut self.entity_map, &patch.ignore);
+        self.written(world, owned, policy, merged, &registry);
+        Ok(())
+    }
 
-impl PrefabSpawner {
-    pub fn spawn(&mut self, handle: Handle<Prefab>, parent: Option<Entity>) -> PrefabInstance {
-        let id = self.spawned.generate_id();
-        self.to_spawn.push((handle, id));
-        if let Some(parent) = parent {
-            self.with_parent.push((id, parent));
+    /// Keep track of what a spawn wrote, once all the entities of the instance are written.
+    ///
+    /// `merged` are the runtime changes applied again, see [`PrefabUpdatePolicy::Merge`].
+    fn written(
+        &mut self,
+        world: &mut World,
+        owned: HashMap<u32, Vec<&'static str>>,
+        policy: PrefabUpdatePolicy,
+        merged: Vec<(u32, &'static str, FieldDelta)>,
+        registry: &TypeRegistryInternal,
+    ) {
+        self.remove_stale(world, &owned, registry);
+        self.owned = owned;
+        self.mark_roots(world);
+        self.place_roots(world);
+
+        self.written = Some(world.change_tick());
+        self.snapshot.clear();
+        if policy == PrefabUpdatePolicy::Merge {
+            self.snapshot = self.take_snapshot(world, registry);
+            self.apply_deltas(world, registry, merged);
         }
-        PrefabInstance(id)
     }
 
-    pub fn despawn(&mut self, id: &PrefabInstance) {
-        self.to_despawn.push(id.0);
+    /// Remove the components written by the last spawn that `owned` doesn't have anymore,
+    /// like components deleted from the prefab before it was hot reloaded.
+    ///
+    /// Entities that are not in `owned` keep their components.
+    /// Kept components are not removed, see [`PrefabUpdatePolicy::KeepRuntimeChanges`].
+    fn remove_stale(
+        &self,
+        world: &mut World,
+        owned: &HashMap<u32, Vec<&'static str>>,
+        registry: &TypeRegistryInternal,
+    ) {
+        for (prefab_entity, previous) in &self.owned {
+            let Some(current) = owned.get(prefab_entity) else {
+                continue;
+            };
+            let Some(entity) = self.entity_map.get(Entity::from_raw(*prefab_entity)) else {
+                continue;
+            };
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                continue;
+            };
+
+            // The hierarchy is rebuilt on its own.
+            let stale = (previous.iter()).filter(|&&type_name| {
+                !current.contains(&type_name)
+                    && !is_hierarchy(type_name)
+                    && !self.kept.contains(&(*prefab_entity, type_name))
+            });
+            for type_name in stale {
+                remove_component(&mut entity, type_name, registry);
+            }
+        }
     }
 
-    /// Check that an prefab instance spawned previously is ready to use
-    pub fn is_ready(&self, id: &PrefabInstance) -> bool {
-        self.spawned.instances.contains_key(&id.0)
+    /// The written components of live entities, with their prefab entity id and type name.
+    fn written_components<'a>(
+        &'a self,
+        world: &'a World,
+        registry: &'a TypeRegistryInternal,
+    ) -> impl Iterator<Item = (u32, &'static str, &'a dyn Reflect)> + 'a {
+        self.owned.iter().flat_map(move |(&prefab_entity, types)| {
+            let entity = self.entity_map.get(Entity::from_raw(prefab_entity));
+            let entity = entity.and_then(|entity| world.get_entity(entity));
+            types.iter().filter_map(move |&type_name| {
+                if is_hierarchy(type_name) {
+                    return None;
+                }
+                let reflect = registry
+                    .get_with_name(type_name)?
+                    .data::<ReflectComponent>()?;
+                Some((prefab_entity, type_name, reflect.reflect(entity?)?))
+            })
+        })
     }
 
-    pub fn info(&self, id: &PrefabInstance) -> Option<&PrefabInstanceInfo> {
-        self.spawned.instances.get(&id.0)
+    /// The written components changed since the last spawn.
+    fn changed_components(
+        &self,
+        world: &World,
+        registry: &TypeRegistryInternal,
+    ) -> Vec<(u32, &'static str)> {
+        let Some(written) = self.written else {
+            return Vec::new();
+        };
+        let this_run = world.read_change_tick();
+        let mut changed = Vec::new();
+        for (&prefab_entity, types) in &self.owned {
+            let Some(entity) = self.entity_map.get(Entity::from_raw(prefab_entity)) else {
+                continue;
+            };
+            let Some(entity) = world.get_entity(entity) else {
+                continue;
+            };
+            for &type_name in types {
+                let registration = registry.get_with_name(type_name);
+                let id = registration.and_then(|r| world.components().get_id(r.type_id()));
+                let ticks = id.and_then(|id| entity.get_change_ticks_by_id(id));
+                if ticks.is_some_and(|ticks| ticks.is_changed(written, this_run)) {
+                    changed.push((prefab_entity, type_name));
+                }
+            }
+        }
+        changed
     }
 
-    pub fn spawn_sync(
-        &mut self,
+    /// The fields of the written components changed since the last spawn.
+    fn runtime_deltas(
+        &self,
+        world: &World,
+        registry: &TypeRegistryInternal,
+    ) -> Vec<(u32, &'static str, FieldDelta)> {
+        let components = self.written_components(world, registry);
+        let deltas = components.filter_map(|(prefab_entity, type_name, live)| {
+            let written = self.snapshot.get(&(prefab_entity, type_name))?;
+            let delta = reflect_diff(written.as_ref(), live);
+            (!delta.is_empty()).then_some((prefab_entity, type_name, delta))
+        });
+        deltas.collect()
+    }
+
+    fn take_snapshot(
+        &self,
+        world: &World,
+        registry: &TypeRegistryInternal,
+    ) -> HashMap<(u32, &'static str), Box<dyn Reflect>> {
+        let components = self.written_components(world, registry);
+        let snapshot = components.map(|(prefab_entity, type_name, component)| {
+            let component = super::clone_compact(component, registry);
+            ((prefab_entity, type_name), component)
+        });
+        snapshot.collect()
+    }
+
+    /// Apply field changes to the written components, skipping the fields that are gone.
+    fn apply_deltas(
+        &self,
         world: &mut World,
-        handle: &Handle<Prefab>,
-    ) -> Result<PrefabInstance, PrefabError> {
-        self.spawned.spawn(world, handle).map(PrefabInstance)
+        registry: &TypeRegistryInternal,
+        deltas: Vec<(u32, &'static str, FieldDelta)>,
+    ) {
+        for (prefab_entity, type_name, delta) in deltas {
+            let Some(entity) = self.entity_map.get(Entity::from_raw(prefab_entity)) else {
+                continue;
+            };
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                continue;
+            };
+            let registration = registry.get_with_name(type_name);
+            let Some(reflect) = registration.and_then(|r| r.data::<ReflectComponent>()) else {
+                continue;
+            };
+            let Some(mut component) = reflect.reflect_mut(&mut entity) else {
+                continue;
+            };
+            for (path, value) in &delta {
+                if let Ok(field) = component.reflect_path_mut(path) {
+                    field.apply(value.as_ref());
+                }
+            }
+        }
     }
 
-    pub fn update_sync(&mut self, world: &mut World, handle: &Handle<Prefab>) {
-        self.spawned.update(world, handle);
+    /// Apply `patch` to the live entities and keep it for later updates of the prefab.
+    ///
+    /// Only the modified components are written, starting from their current values.
+    /// `resolved` is `patch` with its entities targeted by uuid or name resolved to ids,
+    /// the unresolved `patch` is kept so it is resolved again against updated prefabs.
+    fn apply_patch(
+        &mut self,
+        world: &mut World,
+        patch: &Patch,
+        resolved: &Patch,
+    ) -> Result<(), PrefabError> {
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let kept = patch;
+        let patch = resolved;
+
+        // The live values of the modified components, as a prefab to apply the patch to.
+        let mut live = Prefab::default();
+        {
+            let registry = registry.read();
+            for patch in &patch.modify {
+                let Some(entity) = self.entity_map.get(Entity::from_raw(patch.entity)) else {
+                    continue;
+                };
+                let Some(entity) = world.get_entity(entity) else {
+                    continue;
+                };
+
+                let components = patch.modify.keys().filter_map(|type_name| {
+                    let registration = registry.get_with_name(type_name)?;
+                    let reflect = registration.data::<ReflectComponent>()?;
+                    Some(reflect.reflect(entity)?.clone_value())
+                });
+                live.entities
+                    .push(PrefabEntity::new(patch.entity, components.collect()));
+            }
+        }
+
+        let options = self.write_options();
+        super::write_to_world_with(patch, &live, world, &mut self.entity_map, &options)?;
+
+        let registry = registry.read();
+        for patch in &patch.modify {
+            let Some(entity) = self.entity_map.get(Entity::from_raw(patch.entity)) else {
+                continue;
+            };
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                continue;
+            };
+            for type_name in &patch.remove {
+                remove_component(&mut entity, type_name, &registry);
+            }
+        }
+        drop(registry);
+
+        let ignore = patch.ignore.iter().copied().collect();
+        self.despawn_part(
+            world,
+            &Part {
+                entities: ignore,
+                recursive: false,
+            },
+        );
+        self.patch.merge(kept);
+        Ok(())
     }
 
-    pub fn despawn_sync(&mut self, world: &mut World, id: &PrefabInstance) {
-        self.spawned.despawn(world, &id.0);
+    /// Attach all root entities to `parent`, or detach them.
+    ///
+    /// Roots are the entities marked with [`PrefabRoot`].
+    fn set_parent(&mut self, world: &mut World, parent: Option<Entity>) {
+        self.parent = parent;
+        let roots: Vec<Entity> = self
+            .entities()
+            .filter(|&e| self.is_root(world, e))
+            .collect();
+        for child in roots {
+            let mut entity = world.entity_mut(child);
+            if entity.get::<Parent>().map(Parent::get) == parent {
+                continue;
+            }
+            match parent {
+                // Add the `Parent` component to the prefab root,
+                // and update the `Children` component of the prefab parent
+                Some(parent) => AddChild { parent, child }.apply(world),
+                None => {
+                    entity.remove_parent();
+                }
+            }
+        }
     }
 
-    fn maintain(&mut self, world: &mut World) {
-        let asset_events = world.resource::<Events<AssetEvent<Prefab>>>();
-        for event in self.asset_event_reader.iter(asset_events) {
-            if let AssetEvent::Modified { handle } = event {
-                if self.spawned.prefabs.contains_key(handle) {
-                    self.updates.push(handle.clone_weak());
+    /// Attach roots added since the last [`PrefabInstanceInfo::set_parent`].
+    fn attach_roots(&mut self, world: &mut World) {
+        if let Some(parent) = self.parent {
+            for child in self.entities() {
+                if self.is_root(world, child) && world.get::<Parent>(child).is_none() {
+                    AddChild { parent, child }.apply(world);
                 }
             }
         }
+    }
 
-        for id in self.to_despawn.drain(..) {
-            self.spawned.despawn(world, &id);
+    /// Despawn the entities of the instance, and their descendants if `recursive`.
+    ///
+    /// Descendants with [`KeepOnPrefabDespawn`] are detached and kept, with their own descendants.
+    fn despawn(&mut self, world: &mut World, recursive: bool) {
+        let entities: HashSet<Entity> = self.entities().collect();
+        let mut despawned = HashSet::default();
+        let mut stack: Vec<Entity> = self.entities().collect();
+        while let Some(entity) = stack.pop() {
+            if !despawned.insert(entity) || !recursive {
+                continue;
+            }
+            let children = world.get::<Children>(entity).map(|c| c.to_vec());
+            for child in children.into_iter().flatten() {
+                if !entities.contains(&child) && world.get::<KeepOnPrefabDespawn>(child).is_some() {
+                    world.entity_mut(child).remove_parent();
+                } else {
+                    stack.push(child);
+                }
+            }
         }
 
-        self.to_spawn.retain(|(handle, id)| {
-            let mut info = PrefabInstanceInfo::default();
+        for entity in despawned {
+            let _ = world.despawn(entity);
+        }
+    }
 
-            match info.spawn(world, handle) {
-                Ok(_) => {
-                    self.spawned.instances.insert(*id, info);
-                    let spawned = self.spawned.prefabs.entry(handle.clone()).or_default();
-                    spawned.push(*id);
-                    false
+    /// Despawn a part of the instance and ignore it when the prefab is updated.
+    fn despawn_part(&mut self, world: &mut World, part: &Part) {
+        let mut despawned = HashSet::default();
+        let mut stack: Vec<Entity> = part
+            .entities
+            .iter()
+            .filter_map(|&entity| self.entity_map.get(Entity::from_raw(entity)))
+            .collect();
+        while let Some(entity) = stack.pop() {
+            if despawned.insert(entity) && part.recursive {
+                if let Some(children) = world.get::<Children>(entity) {
+                    stack.extend(children.iter().copied());
                 }
-                Err(PrefabError::NonExistentPrefab { .. }) => true,
-                Err(err) => {
-                    bevy::log::error!("{}", err);
-                    false
+            }
+        }
+
+        // Detach the part from the rest of the hierarchy before despawning it.
+        for &entity in &despawned {
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                continue;
+            };
+            let parent = entity.get::<Parent>().map(Parent::get);
+            if parent.is_some_and(|parent| !despawned.contains(&parent)) {
+                entity.remove_parent();
+            }
+            let children = entity.get::<Children>().map(|c| c.to_vec());
+            for child in children.into_iter().flatten() {
+                if !despawned.contains(&child) {
+                    world.entity_mut(child).remove_parent();
                 }
             }
-        });
+        }
 
-        for handle in self.updates.drain(..) {
-            self.spawned.update(world, &handle);
+        for &entity in &despawned {
+            world.despawn(entity);
         }
 
-        self.with_parent.retain(|&(id, parent)| {
-            if let Some(info) = self.spawned.instances.get(&id) {
-                for child in info.entities() {
-                    // Add the `Parent` component to the prefab root,
-                    // and update the `Children` component of the prefab parent
-                    if !world
-                        .get_entity(child)
-                        // This will filter only the prefab root entity,
-                        // as all other from the prefab have a parent
-                        .map(|entity| entity.contains::<Parent>())
-                        // Default is true so that it won't run on an entity that wouldn't exist anymore
-                        // this case shouldn't happen anyway
-                        .unwrap_or(true)
-                    {
-                        AddChild { parent, child }.apply(world);
-                    }
-                }
-                false
-            } else {
-                true
+        let removed: Vec<Entity> = self
+            .entity_map
+            .iter()
+            .filter(|(_, entity)| despawned.contains(entity))
+            .map(|(prefab_entity, _)| prefab_entity)
+            .collect();
+        for prefab_entity in removed {
+            self.entity_map.remove(prefab_entity);
+            self.patch.ignore.insert(prefab_entity.index());
+        }
+        // Children of the despawned entities are roots now.
+        self.mark_roots(world);
+    }
+}
+
+/// Entities of an instance queued for despawn.
+struct Part {
+    entities: Vec<u32>,
+    /// Also despawn the descendants of the entities.
+    recursive: bool,
+}
+
+#[derive(Default)]
+struct Spawned {
+    prefabs: HashMap<Handle<Prefab>, Vec<Id>>,
+    instances: HashMap<Id, PrefabInstanceInfo>,
+    /// Instances whose last spawn or update failed.
+    failed: HashMap<Id, Arc<PrefabError>>,
+    /// Instances spawned under an entity of another instance, by their parent instance.
+    children: HashMap<Id, Vec<Id>>,
+    parents: HashMap<Id, Id>,
+    /// Keep only weak handles to prefabs, see [`PrefabSpawner::set_despawn_unused`].
+    despawn_unused: bool,
+    /// Prefabs added by the spawner itself, kept alive by their instances in any case.
+    owned: HashSet<HandleId>,
+    /// Name the spawned entities, see [`PrefabSpawner::set_name_entities`].
+    name_entities: bool,
+    /// Number of instances spawned so far for each prefab, used in entity names.
+    numbers: HashMap<HandleId, u32>,
+    /// Instance limits of prefabs, see [`PrefabSpawner::set_max_instances`].
+    limits: HashMap<HandleId, (usize, PrefabEviction)>,
+    /// Seed of deterministic ids, see [`PrefabSpawner::set_id_seed`].
+    id_seed: Option<u64>,
+    /// Number of deterministic ids generated since the seed was set.
+    next_id: u64,
+    /// See [`PrefabSpawner::set_update_policy`].
+    update_policy: PrefabUpdatePolicy,
+    /// Update policies of single instances, see [`PrefabSpawner::set_instance_update_policy`].
+    policies: HashMap<Id, PrefabUpdatePolicy>,
+    /// See [`PrefabSpawner::set_removal_policy`].
+    removal_policy: PrefabRemovalPolicy,
+    /// See [`PrefabSpawner::set_despawn_recursive`].
+    despawn_recursive: bool,
+    /// Instances whose prefab asset was removed, see [`PrefabRemovalPolicy::Orphan`].
+    orphaned: HashSet<Id>,
+    /// See [`PrefabSpawner::stats`].
+    stats: PrefabSpawnerStats,
+}
+
+impl Spawned {
+    fn spawn(&mut self, world: &mut World, handle: &Handle<Prefab>) -> Result<Id, PrefabError> {
+        let id = self.generate_id();
+        let span = info_span!("prefab_spawn", path = Empty, instance = %id, entities = Empty);
+        record_path(&span, world, handle);
+        let _span = span.enter();
+
+        self.make_room(world, handle)?;
+        let mut info = PrefabInstanceInfo::new(id, handle, Patch::default());
+        info.name = self.instance_name(world, handle);
+        let spawned = info.spawn(world, self.policy(&id));
+        spawned.map_err(|err| err.in_file(asset_path(world, handle)))?;
+        span.record("entities", info.entity_map.len());
+        run_hooks(world, &info, handle);
+
+        self.instances.insert(id, info);
+        self.track(handle, id);
+
+        Ok(id)
+    }
+
+    /// Whether some instances of the prefab are orphaned, see [`PrefabRemovalPolicy::Orphan`].
+    fn is_orphaned(&self, handle: &Handle<Prefab>) -> bool {
+        let mut ids = self.prefabs.get(handle).into_iter().flatten();
+        ids.any(|id| self.orphaned.contains(id))
+    }
+
+    fn policy(&self, id: &Id) -> PrefabUpdatePolicy {
+        self.policies.get(id).copied().unwrap_or(self.update_policy)
+    }
+
+    fn generate_id(&mut self) -> Id {
+        match self.id_seed {
+            Some(seed) => {
+                let id = Id::from_u64_pair(seed, self.next_id);
+                self.next_id += 1;
+                id
             }
-        });
+            None => Id::new_v4(),
+        }
+    }
+
+    /// Evict instances of a prefab until there is room for a new one within its limit.
+    ///
+    /// Nothing is evicted while the prefab isn't loaded, as the new instance can't be spawned yet.
+    fn make_room(&mut self, world: &mut World, handle: &Handle<Prefab>) -> Result<(), PrefabError> {
+        let Some(&(max, eviction)) = self.limits.get(&handle.id()) else {
+            return Ok(());
+        };
+        if !world.resource::<Assets<Prefab>>().contains(handle) {
+            return Ok(());
+        }
+
+        loop {
+            let ids = self
+                .prefabs
+                .get(handle)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            if ids.len() < max {
+                return Ok(());
+            }
+            let Some(&oldest) = ids.first() else {
+                return Err(PrefabError::TooManyInstances { max });
+            };
+
+            let evicted = match eviction {
+                PrefabEviction::Reject => return Err(PrefabError::TooManyInstances { max }),
+                PrefabEviction::Oldest => oldest,
+                PrefabEviction::Farthest(origin) => {
+                    let origin = world
+                        .get::<GlobalTransform>(origin)
+                        .map(|t| t.translation());
+                    let farthest = ids.iter().filter_map(|id| {
+                        let position = self.instances.get(id)?.position(world)?;
+                        Some((origin?.distance_squared(position), *id))
+                    });
+                    let farthest = farthest.max_by(|(a, _), (b, _)| a.total_cmp(b));
+                    farthest.map_or(oldest, |(_, id)| id)
+                }
+            };
+            self.despawn(world, &evicted);
+        }
+    }
+
+    /// Prefix of the entity names of the next instance of a prefab, like `goblin#3`.
+    fn instance_name(&self, world: &World, handle: &Handle<Prefab>) -> Option<String> {
+        if !self.name_entities {
+            return None;
+        }
+
+        let server = world.get_resource::<AssetServer>();
+        let path = server.and_then(|server| server.get_handle_path(handle));
+        let file_name = path.as_ref().and_then(|path| path.path().file_name());
+        // Strip all extensions, prefabs are usually `*.prefab.ron`.
+        let name = file_name.and_then(|name| name.to_str()?.split('.').next());
+        let number = self.numbers.get(&handle.id()).copied().unwrap_or_default();
+        Some(format!("{}#{}", name.unwrap_or("prefab"), number))
+    }
+
+    /// Track an instance once all its entities are written and send its [`PrefabInstanceReady`].
+    fn ready(
+        &mut self,
+        world: &mut World,
+        handle: &Handle<Prefab>,
+        id: Id,
+        info: PrefabInstanceInfo,
+    ) {
+        let ready = PrefabInstanceReady {
+            instance: PrefabInstance(id),
+            root: info.root(world),
+            entities: info.entity_map.len(),
+        };
+        run_hooks(world, &info, handle);
+        send_event(world, ready);
+        self.stats.spawned += 1;
+        self.stats.spawned_entities += info.entity_map.len();
+        self.instances.insert(id, info);
+        self.track(handle, id);
+    }
+
+    /// Add a spawned instance to the instances of its prefab.
+    fn track(&mut self, handle: &Handle<Prefab>, id: Id) {
+        if self.name_entities {
+            *self.numbers.entry(handle.id()).or_default() += 1;
+        }
+
+        let handle = if self.despawn_unused && !self.owned.contains(&handle.id()) {
+            handle.clone_weak()
+        } else {
+            handle.clone()
+        };
+        self.prefabs.entry(handle).or_default().push(id);
+    }
+
+    fn update(&mut self, world: &mut World, handle: &Handle<Prefab>) {
+        let ids = self.prefabs.get(handle).cloned().unwrap_or_default();
+        for id in &ids {
+            self.respawn(world, id);
+        }
+    }
+
+    /// Spawn the instances using a patch asset again, after it was modified.
+    fn update_patch(&mut self, world: &mut World, handle: &Handle<Patch>) {
+        let ids: Vec<Id> = (self.instances.iter())
+            .filter(|(_, info)| info.patch_asset.as_ref() == Some(handle))
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &ids {
+            self.respawn(world, id);
+        }
+    }
+
+    /// Write the prefab and patch of a spawned instance to its existing entities again.
+    fn respawn(&mut self, world: &mut World, id: &Id) {
+        let policy = self.policy(id);
+        let Some(info) = self.instances.get_mut(id) else {
+            return;
+        };
+        let span = info_span!("prefab_update", path = Empty, instance = %id, entities = Empty);
+        record_path(&span, world, &info.handle);
+        let _span = span.enter();
+        match info.spawn(world, policy) {
+            Ok(()) => {
+                span.record("entities", info.entity_map.len());
+                info.attach_roots(world);
+                run_hooks(world, info, &info.handle);
+                self.failed.remove(id);
+                self.orphaned.remove(id);
+            }
+            Err(err) => {
+                let err = report_error(world, *id, &info.handle, err);
+                self.failed.insert(*id, err);
+            }
+        }
+    }
+
+    fn patch_all(&mut self, world: &mut World, handle: &Handle<Prefab>, patch: &Patch) {
+        let prefabs = world.resource::<Assets<Prefab>>();
+        let resolved = match prefabs.get(handle).map(|prefab| patch.resolve(prefab)) {
+            Some(Cow::Owned(resolved)) => Some(resolved),
+            _ => None,
+        };
+        let resolved = resolved.as_ref().unwrap_or(patch);
+
+        if let Some(spawned_instances) = self.prefabs.get(handle) {
+            for id in spawned_instances {
+                if let Some(info) = self.instances.get_mut(id) {
+                    let span = info_span!("prefab_patch", path = Empty, instance = %id);
+                    record_path(&span, world, handle);
+                    let _span = span.entered();
+                    match info.apply_patch(world, patch, resolved) {
+                        Ok(()) => info.attach_roots(world),
+                        Err(err) => {
+                            report_error(world, *id, handle, err);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (&Handle<Prefab>, &PrefabInstanceInfo)> {
+        self.prefabs.iter().flat_map(move |(handle, ids)| {
+            let infos = ids.iter().filter_map(|id| self.instances.get(id));
+            infos.map(move |info| (handle, info))
+        })
+    }
+
+    fn despawn(&mut self, world: &mut World, id: &Id) {
+        if let Some(mut info) = self.forget(id) {
+            let span = info_span!("prefab_despawn", path = Empty, instance = %id);
+            record_path(&span, world, &info.handle);
+            let _span = span.entered();
+            info.despawn(world, self.despawn_recursive);
+        }
+
+        // Nested instances go with their parent.
+        for child in self.children.remove(id).unwrap_or_default() {
+            self.parents.remove(&child);
+            self.despawn(world, &child);
+        }
+    }
+
+    /// Stop tracking an instance, without despawning its entities.
+    fn forget(&mut self, id: &Id) -> Option<PrefabInstanceInfo> {
+        let info = self.instances.remove(id);
+        if let Some(info) = &info {
+            // Release the prefab once its last instance is gone.
+            if let Some(ids) = self.prefabs.get_mut(&info.handle) {
+                ids.retain(|spawned| spawned != id);
+                if ids.is_empty() {
+                    self.prefabs.remove(&info.handle);
+                    self.owned.remove(&info.handle.id());
+                }
+            }
+        }
+        self.failed.remove(id);
+        self.orphaned.remove(id);
+        self.policies.remove(id);
+        self.unlink(id);
+        info
+    }
+
+    /// Forget the instances whose entities were all despawned without the spawner,
+    /// see [`PrefabSpawner::gc`].
+    fn gc(&mut self, world: &World) -> usize {
+        let dead: Vec<Id> = (self.instances.iter())
+            .filter(|(_, info)| {
+                let mut entities = info.entities().peekable();
+                entities.peek().is_some() && entities.all(|e| world.get_entity(e).is_none())
+            })
+            .map(|(id, _)| *id)
+            .collect();
+        for id in &dead {
+            self.forget(id);
+            // Nested instances may still be alive, they are not nested anymore.
+            for child in self.children.remove(id).unwrap_or_default() {
+                self.parents.remove(&child);
+            }
+        }
+
+        self.failed.retain(|id, _| self.instances.contains_key(id));
+        self.prefabs.retain(|handle, ids| {
+            let empty = ids.is_empty();
+            if empty {
+                self.owned.remove(&handle.id());
+            }
+            !empty
+        });
+        dead.len()
+    }
+
+    fn unlink(&mut self, id: &Id) {
+        if let Some(parent) = self.parents.remove(id) {
+            if let Some(siblings) = self.children.get_mut(&parent) {
+                siblings.retain(|sibling| sibling != id);
+            }
+        }
+    }
+
+    /// Track `id` as nested in the instance owning the `parent` entity, if there is one.
+    fn link(&mut self, id: Id, parent: Entity) {
+        let owner = self.instances.iter().find(|(&owner, info)| {
+            owner != id && info.entity_map.values().any(|entity| entity == parent)
+        });
+        if let Some((&owner, _)) = owner {
+            self.parents.insert(id, owner);
+            self.children.entry(owner).or_default().push(id);
+        }
+    }
+}
+
+#[derive(Default, Resource)]
+pub struct PrefabSpawner {
+    asset_event_reader: ManualEventReader<AssetEvent<Prefab>>,
+    patch_event_reader: ManualEventReader<AssetEvent<Patch>>,
+
+    spawned: Spawned,
+
+    to_spawn: Vec<(Handle<Prefab>, Id, Patch)>,
+    /// Patch assets of queued instances, see [`PrefabSpawner::spawn_with_patch_asset`].
+    patch_assets: HashMap<Id, Handle<Patch>>,
+    /// Transforms of the roots of queued instances, see [`PrefabSpawner::spawn_at`].
+    root_transforms: HashMap<Id, Transform>,
+    /// Existing entities of queued instances, see [`PrefabSpawner::spawn_into`].
+    seeded: HashMap<Id, EntityMap>,
+    to_despawn: Vec<Id>,
+    to_despawn_parts: Vec<(Id, Part)>,
+    /// Queued instances whose prefab wasn't loaded yet on the last maintenance.
+    waiting: HashMap<Id, Waiting>,
+    /// See [`PrefabSpawner::set_pending_limit`].
+    pending_limit: PendingLimit,
+    /// See [`PrefabSpawner::set_spawn_budget`].
+    spawn_budget: Option<SpawnBudget>,
+    /// See [`PrefabSpawner::set_strict`].
+    strict: bool,
+    /// Queued instances partially written, oldest first.
+    hydrating: Vec<Hydrating>,
+
+    with_parent: Vec<(Id, Option<Entity>)>,
+    updates: Vec<Handle<Prefab>>,
+    patch_updates: Vec<Handle<Patch>>,
+    patches: Vec<(Handle<Prefab>, Patch)>,
+}
+
+impl PrefabSpawner {
+    /// Queue a prefab to be spawned, see [`PrefabSpawner::spawn_with_patch`] to override parts of it.
+    pub fn spawn(&mut self, handle: Handle<Prefab>, parent: Option<Entity>) -> PrefabInstance {
+        self.spawn_with_patch(handle, parent, Patch::default())
+    }
+
+    /// Queue a prefab received at runtime, like over the network, to be spawned.
+    ///
+    /// The prefab is read from rust object notation (ron) like a loaded prefab:
+    /// it is migrated by the [`PrefabMigrations`](super::PrefabMigrations),
+    /// goes through the [`PrefabLoaderHooks`](super::PrefabLoaderHooks)
+    /// and is checked if the spawner is strict, see [`PrefabSpawner::set_strict`].
+    /// It is then added to the [`Assets<Prefab>`] and stays there as long as the instance lives.
+    pub fn spawn_from_ron(
+        &mut self,
+        world: &mut World,
+        ron: &[u8],
+        parent: Option<Entity>,
+    ) -> Result<PrefabInstance, Error> {
+        let loader = PrefabLoader::from_world(world).with_strict(self.strict);
+        let prefab = loader.read_unloaded(ron)?;
+
+        let handle = world.resource_mut::<Assets<Prefab>>().add(prefab);
+        self.spawned.owned.insert(handle.id());
+        Ok(self.spawn(handle, parent))
+    }
+
+    /// Queue a prefab to be spawned with a patch applied on top of it.
+    ///
+    /// The patch is kept with the instance and applied again when the prefab is updated.
+    pub fn spawn_with_patch(
+        &mut self,
+        handle: Handle<Prefab>,
+        parent: Option<Entity>,
+        patch: Patch,
+    ) -> PrefabInstance {
+        let id = PrefabInstance(self.spawned.generate_id());
+        self.spawn_with_id(id, handle, parent, patch)
+    }
+
+    /// Queue a prefab to be spawned with its roots moved to `transform`.
+    ///
+    /// The [`Transform`] of the roots is replaced, relative to `parent` if there is one,
+    /// and replaced again when the instance is updated.
+    pub fn spawn_at(
+        &mut self,
+        handle: Handle<Prefab>,
+        transform: Transform,
+        parent: Option<Entity>,
+    ) -> PrefabInstance {
+        let id = self.spawn(handle, parent);
+        self.root_transforms.insert(id.0, transform);
+        id
+    }
+
+    /// Queue a prefab to be spawned with a patch asset applied on top of it.
+    ///
+    /// The instance waits for both assets to be loaded and keeps the patch asset alive.
+    /// When the patch asset is modified, like when its file changes on disk,
+    /// the instance is spawned again the same way as when its prefab is modified.
+    /// Patches applied to the instance at runtime win over the patch asset.
+    pub fn spawn_with_patch_asset(
+        &mut self,
+        handle: Handle<Prefab>,
+        parent: Option<Entity>,
+        patch: Handle<Patch>,
+    ) -> PrefabInstance {
+        let id = self.spawn_with_patch(handle, parent, Patch::default());
+        self.patch_assets.insert(id.0, patch);
+        id
+    }
+
+    /// Queue a prefab to be spawned into existing entities, by their prefab entity ids.
+    ///
+    /// The prefab is written to these entities instead of new ones,
+    /// like placeholders spawned while waiting for a server or when restoring a saved game.
+    /// Prefab entities missing from `entities` are spawned as usual.
+    /// The given entities belong to the instance, they are despawned with it,
+    /// and must still exist when the instance is spawned,
+    /// otherwise spawning fails with [`PrefabError::MissingEntity`] and writes nothing.
+    pub fn spawn_into(
+        &mut self,
+        handle: Handle<Prefab>,
+        parent: Option<Entity>,
+        entities: impl IntoIterator<Item = (u32, Entity)>,
+    ) -> PrefabInstance {
+        let id = self.spawn(handle, parent);
+        let mut entity_map = EntityMap::default();
+        for (prefab_entity, entity) in entities {
+            entity_map.insert(Entity::from_raw(prefab_entity), entity);
+        }
+        self.seeded.insert(id.0, entity_map);
+        id
+    }
+
+    /// Queue a prefab to be spawned as an instance with a given id.
+    ///
+    /// Lets every peer of a networked game refer to the same instance by the same id,
+    /// for example with an id chosen by the server, see [`PrefabInstance::from_uuid`].
+    /// The id must not be used by another instance of the spawner.
+    pub fn spawn_with_id(
+        &mut self,
+        id: PrefabInstance,
+        handle: Handle<Prefab>,
+        parent: Option<Entity>,
+        patch: Patch,
+    ) -> PrefabInstance {
+        self.to_spawn.push((handle, id.0, patch));
+        if let Some(parent) = parent {
+            self.with_parent.push((id.0, Some(parent)));
+        }
+        id
+    }
+
+    /// Generate instance ids from a seeded sequence instead of at random, `None` goes back to random.
+    ///
+    /// The sequence restarts on every call, so peers that set the same seed
+    /// and spawn the same prefabs in the same order get the same instance ids.
+    pub fn set_id_seed(&mut self, seed: Option<u64>) {
+        self.spawned.id_seed = seed;
+        self.spawned.next_id = 0;
+    }
+
+    /// Queue an instance to be despawned.
+    ///
+    /// An instance that is still waiting to be spawned is dropped from the queue right away.
+    pub fn despawn(&mut self, id: &PrefabInstance) {
+        let id = id.0;
+        let queued = self.to_spawn.len();
+        self.to_spawn.retain(|(_, pending, _)| *pending != id);
+        if self.to_spawn.len() < queued {
+            self.waiting.remove(&id);
+            self.patch_assets.remove(&id);
+            self.root_transforms.remove(&id);
+            self.seeded.remove(&id);
+            self.spawned.policies.remove(&id);
+            self.with_parent.retain(|(pending, _)| *pending != id);
+            self.to_despawn_parts.retain(|(pending, _)| *pending != id);
+        } else {
+            self.to_despawn.push(id);
+        }
+    }
+
+    /// Queue some entities of an instance to be despawned, by their prefab entity ids.
+    ///
+    /// The rest of the instance stays tracked and is still updated on hot reload,
+    /// the despawned entities are not spawned again.
+    /// Children of the despawned entities are detached and kept.
+    pub fn despawn_entities(
+        &mut self,
+        id: &PrefabInstance,
+        entities: impl IntoIterator<Item = u32>,
+    ) {
+        let entities = entities.into_iter().collect();
+        let part = Part {
+            entities,
+            recursive: false,
+        };
+        self.to_despawn_parts.push((id.0, part));
+    }
+
+    /// Queue an entity of an instance to be despawned with all its descendants.
+    ///
+    /// See [`PrefabSpawner::despawn_entities`].
+    pub fn despawn_subtree(&mut self, id: &PrefabInstance, root: u32) {
+        let part = Part {
+            entities: vec![root],
+            recursive: true,
+        };
+        self.to_despawn_parts.push((id.0, part));
+    }
+
+    /// Queue all root entities of an instance to be attached to `parent`, or detached for `None`.
+    ///
+    /// Roots added to the instance later, by a hot reload, are attached to the same parent.
+    pub fn set_parent(&mut self, id: &PrefabInstance, parent: Option<Entity>) {
+        self.with_parent.push((id.0, parent));
+    }
+
+    /// Apply a patch to every live instance of a prefab, without reloading it.
+    ///
+    /// Modified components are patched starting from their current values,
+    /// appended components and entities are added and ignored entities are despawned.
+    /// The patch is kept by the instances, so it survives hot reloads of the prefab.
+    /// Instances that are not spawned yet are not patched.
+    /// An instance is left untouched if the patch fails to apply to it.
+    pub fn patch_all(&mut self, handle: &Handle<Prefab>, patch: &Patch) {
+        self.patches.push((handle.clone_weak(), patch.clone()));
+    }
+
+    /// Despawn the instances of a prefab once no strong handle to it is left outside the spawner.
+    ///
+    /// The spawner then only keeps weak handles to the prefabs of spawned instances,
+    /// so dropping the last handle, like the one of a [`PrefabBundle`] or of a streamed chunk,
+    /// frees the asset and its instances are despawned on the next maintenance.
+    /// Instances still waiting to be spawned keep their prefab alive,
+    /// and so do instances spawned with [`PrefabSpawner::spawn_from_ron`].
+    /// Only instances spawned after the call are affected.
+    pub fn set_despawn_unused(&mut self, enabled: bool) {
+        self.spawned.despawn_unused = enabled;
+    }
+
+    /// Choose what updates of instances do with the changes made to them at runtime.
+    ///
+    /// Applies to all instances without a policy of their own,
+    /// see [`PrefabSpawner::set_instance_update_policy`].
+    pub fn set_update_policy(&mut self, policy: PrefabUpdatePolicy) {
+        self.spawned.update_policy = policy;
+    }
+
+    /// Choose the update policy of a single instance, `None` goes back to the one of the spawner.
+    ///
+    /// The instance may still be waiting to be spawned.
+    /// [`PrefabUpdatePolicy::Merge`] only merges the fields changed after the next update.
+    pub fn set_instance_update_policy(
+        &mut self,
+        id: &PrefabInstance,
+        policy: Option<PrefabUpdatePolicy>,
+    ) {
+        match policy {
+            Some(policy) => self.spawned.policies.insert(id.0, policy),
+            None => self.spawned.policies.remove(&id.0),
+        };
+    }
+
+    /// Give the entities of spawned instances a [`Name`](bevy::core::Name), like `goblin#3/weapon`.
+    ///
+    /// Names start with the file name of the prefab and the number of the instance,
+    /// followed by the name of the prefab entity, or by its id if it has none.
+    /// Only instances spawned after the call are affected.
+    pub fn set_name_entities(&mut self, enabled: bool) {
+        self.spawned.name_entities = enabled;
+    }
+
+    /// Also despawn the descendants of the entities of despawned instances,
+    /// like children added at runtime, instead of leaving them orphaned.
+    ///
+    /// Descendants with [`KeepOnPrefabDespawn`] are detached and kept.
+    pub fn set_despawn_recursive(&mut self, enabled: bool) {
+        self.spawned.despawn_recursive = enabled;
+    }
+
+    /// Choose what happens to the instances of a prefab asset that is removed.
+    ///
+    /// With [`PrefabSpawner::set_despawn_unused`], the instances are despawned in any case.
+    pub fn set_removal_policy(&mut self, policy: PrefabRemovalPolicy) {
+        self.spawned.removal_policy = policy;
+    }
+
+    /// Give up on queued spawns whose prefab or patch asset takes too long to load.
+    ///
+    /// By default spawns wait for their assets forever.
+    /// A spawn that gives up is dropped from the queue,
+    /// its state becomes [`PrefabInstanceState::Failed`] and a [`PrefabSpawnError`] is sent.
+    pub fn set_pending_limit(&mut self, limit: PendingLimit) {
+        self.pending_limit = limit;
+    }
+
+    /// Number of queued spawns, including the ones waiting for their assets
+    /// and the ones partially written.
+    pub fn pending_spawns(&self) -> usize {
+        self.to_spawn.len() + self.hydrating.len()
+    }
+
+    /// What the last maintenance did, see [`PrefabDiagnosticsPlugin`](super::PrefabDiagnosticsPlugin).
+    pub fn stats(&self) -> &PrefabSpawnerStats {
+        &self.spawned.stats
+    }
+
+    /// Number of queued spawns whose assets were missing on the last maintenance.
+    pub fn waiting_spawns(&self) -> usize {
+        self.waiting.len()
+    }
+
+    /// Spread the spawns of large prefabs over several maintenances, instead of writing
+    /// every queued instance at once.
+    ///
+    /// The entities of an instance are all reserved by the first maintenance,
+    /// empty until they are written.
+    /// Instances stay [`PrefabInstanceState::Spawning`] until all their entities are written,
+    /// then their roots are attached to their parent and a [`PrefabInstanceReady`] is sent.
+    /// See [`PrefabSpawner::spawn_progress`].
+    ///
+    /// Hot reloads and [`PrefabSpawner::spawn_sync`] are not budgeted.
+    /// Instances already partially written are finished by the next maintenance
+    /// once the budget is removed.
+    pub fn set_spawn_budget(&mut self, budget: Option<SpawnBudget>) {
+        self.spawn_budget = budget;
+    }
+
+    /// Fail [`PrefabSpawner::spawn_from_ron`] on prefabs with components that aren't registered
+    /// components, like the loader does with [`PrefabLoader::with_strict`].
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// How much of an instance is written, from `0.0` while it is queued to `1.0` once it's ready.
+    ///
+    /// Only instances spawned with a [`SpawnBudget`] are partially written for a while.
+    /// `None` if the instance was despawned, never existed or failed to spawn.
+    pub fn spawn_progress(&self, id: &PrefabInstance) -> Option<f32> {
+        let id = &id.0;
+        if self.spawned.instances.contains_key(id) {
+            Some(1.0)
+        } else if let Some(hydrating) = self.hydrating.iter().find(|h| h.id == *id) {
+            Some(hydrating.written as f32 / hydrating.entities.len().max(1) as f32)
+        } else if self.to_spawn.iter().any(|(_, queued, _)| queued == id) {
+            Some(0.0)
+        } else {
+            None
+        }
+    }
+
+    /// Limit the number of live instances of a prefab.
+    ///
+    /// When a new instance is about to be spawned and the limit is reached,
+    /// the `eviction` policy decides if an older instance is despawned to make room for it.
+    /// Instances waiting for the prefab to load don't count towards the limit.
+    pub fn set_max_instances(
+        &mut self,
+        handle: &Handle<Prefab>,
+        max: usize,
+        eviction: PrefabEviction,
+    ) {
+        self.spawned.limits.insert(handle.id(), (max, eviction));
+    }
+
+    /// Remove the instance limit of a prefab, see [`PrefabSpawner::set_max_instances`].
+    pub fn remove_max_instances(&mut self, handle: &Handle<Prefab>) {
+        self.spawned.limits.remove(&handle.id());
+    }
+
+    /// Get the lifecycle state of an instance, `None` if it was despawned or never existed.
+    pub fn state(&self, id: &PrefabInstance) -> Option<PrefabInstanceState> {
+        let id = &id.0;
+        if let Some(err) = self.spawned.failed.get(id) {
+            Some(PrefabInstanceState::Failed(err.clone()))
+        } else if self.spawned.orphaned.contains(id) {
+            Some(PrefabInstanceState::Orphaned)
+        } else if self.spawned.instances.contains_key(id) {
+            Some(PrefabInstanceState::Ready)
+        } else if self.waiting.contains_key(id) {
+            Some(PrefabInstanceState::PendingAsset)
+        } else if self.to_spawn.iter().any(|(_, queued, _)| queued == id)
+            || self.hydrating.iter().any(|hydrating| hydrating.id == *id)
+        {
+            Some(PrefabInstanceState::Spawning)
+        } else {
+            None
+        }
+    }
+
+    pub fn info(&self, id: &PrefabInstance) -> Option<&PrefabInstanceInfo> {
+        self.spawned.instances.get(&id.0)
+    }
+
+    /// Read back the current state of a spawned instance as a standalone prefab.
+    ///
+    /// Entities get their prefab ids back, so the result can be saved as its own prefab asset.
+    /// References to entities outside the instance are dropped,
+    /// like the [`Parent`] of its roots or roots of nested instances in [`Children`].
+    /// Returns `None` if the instance isn't spawned.
+    pub fn extract_instance(&self, world: &World, id: &PrefabInstance) -> Option<Prefab> {
+        self.info(id).map(|info| info.extract(world))
+    }
+
+    /// Record the changes made to a spawned instance as a patch of its prefab.
+    ///
+    /// The instance is read back like with [`PrefabSpawner::extract_instance`]
+    /// and compared to its prefab with [`Patch::diff`].
+    /// Changes of the hierarchy are recorded as [`PatchEntity::parent`] overrides.
+    /// Components added at runtime, like the [`Name`](bevy::core::Name) from
+    /// [`PrefabSpawner::set_name_entities`], are recorded as appended components.
+    pub fn capture_overrides(
+        &self,
+        world: &World,
+        id: &PrefabInstance,
+    ) -> Result<Patch, PrefabError> {
+        let info = self.info(id);
+        let info = info.ok_or(PrefabError::NotSpawned { instance: *id })?;
+        info.overrides(world)
+    }
+
+    /// Queue a copy of a spawned instance, with the changes made to it at runtime.
+    ///
+    /// The copy is an instance of the same prefab, under the same parent,
+    /// with the changes found by [`PrefabSpawner::capture_overrides`] as its patch,
+    /// so it keeps being updated when the prefab is.
+    /// Names given by [`PrefabSpawner::set_name_entities`] are given anew.
+    pub fn clone_instance(
+        &mut self,
+        world: &World,
+        id: &PrefabInstance,
+    ) -> Result<PrefabInstance, PrefabError> {
+        let info = self.info(id);
+        let info = info.ok_or(PrefabError::NotSpawned { instance: *id })?;
+        let mut patch = info.overrides(world)?;
+        if let Some(prefix) = &info.name {
+            let prefix = format!("{}/", prefix);
+            for entity in &mut patch.modify {
+                (entity.append).retain(|component| !has_name_prefix(component.as_ref(), &prefix));
+            }
+        }
+
+        // Keep the prefab alive like the instance does, see `Spawned::track`.
+        let handle = self.spawned.prefabs.get_key_value(&info.handle);
+        let handle = handle.map_or_else(|| info.handle.clone(), |(handle, _)| handle.clone());
+        let parent = info.parent;
+        Ok(self.spawn_with_patch(handle, parent, patch))
+    }
+
+    /// The instance this instance was spawned under, if it is nested in another instance.
+    ///
+    /// An instance is nested when its parent entity belongs to another instance,
+    /// for example when spawned from a [`PrefabBundle`] that is part of a prefab.
+    /// Despawning an instance also despawns all instances nested in it.
+    pub fn parent_of(&self, id: &PrefabInstance) -> Option<PrefabInstance> {
+        self.spawned.parents.get(&id.0).copied().map(PrefabInstance)
+    }
+
+    /// The spawned instance an entity belongs to, see [`PrefabInstanceInfo::prefab_id_of`]
+    /// for the prefab entity it was spawned for.
+    ///
+    /// Descendants added to the entities of an instance at runtime don't belong to it.
+    pub fn instance_of(&self, entity: Entity) -> Option<PrefabInstance> {
+        let mut instances = self.spawned.instances.iter();
+        let (id, _) = instances.find(|(_, info)| info.prefab_id_of(entity).is_some())?;
+        Some(PrefabInstance(*id))
+    }
+
+    /// Instances nested directly in this instance, see [`PrefabSpawner::parent_of`].
+    pub fn children_of(&self, id: &PrefabInstance) -> impl Iterator<Item = PrefabInstance> + '_ {
+        let children = self.spawned.children.get(&id.0).into_iter().flatten();
+        children.copied().map(PrefabInstance)
+    }
+
+    /// The spawned instances of a prefab, oldest first.
+    pub fn iter_instances(
+        &self,
+        handle: &Handle<Prefab>,
+    ) -> impl Iterator<Item = (PrefabInstance, &PrefabInstanceInfo)> + '_ {
+        let ids = self.spawned.prefabs.get(handle).into_iter().flatten();
+        ids.filter_map(|id| Some((PrefabInstance(*id), self.spawned.instances.get(id)?)))
+    }
+
+    /// Iterate over all spawned instances together with their prefab handle.
+    pub(crate) fn instances(&self) -> impl Iterator<Item = (&Handle<Prefab>, &PrefabInstanceInfo)> {
+        self.spawned.iter()
+    }
+
+    pub fn spawn_sync(
+        &mut self,
+        world: &mut World,
+        handle: &Handle<Prefab>,
+    ) -> Result<PrefabInstance, PrefabError> {
+        self.spawned.spawn(world, handle).map(PrefabInstance)
+    }
+
+    pub fn update_sync(&mut self, world: &mut World, handle: &Handle<Prefab>) {
+        self.spawned.update(world, handle);
+    }
+
+    pub fn despawn_sync(&mut self, world: &mut World, id: &PrefabInstance) {
+        self.spawned.despawn(world, &id.0);
+    }
+
+    /// Forget the instances whose entities were all despawned without the spawner,
+    /// like by despawning their parent recursively, and return how many there were.
+    ///
+    /// Their prefabs are released once they have no instances left,
+    /// and the errors of instances that failed to spawn are dropped,
+    /// their [`PrefabSpawner::state`] becomes `None`.
+    /// Instances that are still queued are kept.
+    pub fn gc(&mut self, world: &World) -> usize {
+        self.spawned.gc(world)
+    }
+
+    /// Resolve queued spawns, despawns and hot reloads.
+    ///
+    /// This is run by [`prefab_spawner_maintain_system`] unless
+    /// [`PrefabPlugin::manual_maintenance`](super::PrefabPlugin::manual_maintenance) is set.
+    pub fn maintain(&mut self, world: &mut World) {
+        let _span = info_span!(
+            "prefab_maintain",
+            queued = self.to_spawn.len(),
+            hydrating = self.hydrating.len(),
+            instances = self.spawned.instances.len(),
+        )
+        .entered();
+        let start = Instant::now();
+        self.spawned.stats = PrefabSpawnerStats::default();
+
+        let asset_events = world.resource::<Events<AssetEvent<Prefab>>>();
+        for event in self.asset_event_reader.iter(asset_events) {
+            if let AssetEvent::Modified { handle } = event {
+                if self.spawned.prefabs.contains_key(handle) {
+                    self.updates.push(handle.clone_weak());
+                }
+                // Written again once they are ready, parts may be written already.
+                let hydrating = self.hydrating.iter_mut();
+                for hydrating in hydrating.filter(|h| h.handle == *handle) {
+                    hydrating.modified = true;
+                }
+            }
+        }
+
+        // Asset events are only sent at the end of the frame,
+        // removed prefabs and prefabs added back are looked up instead.
+        let prefabs = world.resource::<Assets<Prefab>>();
+        let mut removed = Vec::new();
+        for handle in self.spawned.prefabs.keys() {
+            if !prefabs.contains(handle) {
+                removed.push(handle.clone_weak());
+            } else if self.spawned.is_orphaned(handle) && !self.updates.contains(handle) {
+                self.updates.push(handle.clone_weak());
+            }
+        }
+
+        for handle in removed {
+            let ids = self
+                .spawned
+                .prefabs
+                .get(&handle)
+                .cloned()
+                .unwrap_or_default();
+            if self.spawned.despawn_unused
+                || self.spawned.removal_policy == PrefabRemovalPolicy::Despawn
+            {
+                self.to_despawn.extend(ids);
+                continue;
+            }
+            for id in ids {
+                if self.spawned.orphaned.insert(id) {
+                    let instance = PrefabInstance(id);
+                    let handle = handle.clone_weak();
+                    send_event(world, PrefabOrphaned { instance, handle });
+                }
+            }
+        }
+
+        if let Some(patch_events) = world.get_resource::<Events<AssetEvent<Patch>>>() {
+            for event in self.patch_event_reader.iter(patch_events) {
+                if let AssetEvent::Modified { handle } = event {
+                    self.patch_updates.push(handle.clone_weak());
+                }
+            }
+        }
+
+        for id in self.to_despawn.drain(..) {
+            if let Some(index) = self.hydrating.iter().position(|h| h.id == id) {
+                let mut hydrating = self.hydrating.remove(index);
+                (hydrating.info).despawn(world, self.spawned.despawn_recursive);
+            }
+            self.spawned.despawn(world, &id);
+        }
+
+        self.to_spawn.retain_mut(|(handle, id, patch)| {
+            let span = info_span!("prefab_spawn", path = Empty, instance = %id, entities = Empty);
+            record_path(&span, world, handle);
+            let _span = span.enter();
+
+            let mut info = PrefabInstanceInfo::new(*id, handle, std::mem::take(patch));
+            info.name = self.spawned.instance_name(world, handle);
+            info.patch_asset = self.patch_assets.get(id).cloned();
+            info.root_transform = self.root_transforms.remove(id);
+            if let Some(entity_map) = self.seeded.remove(id) {
+                info.entity_map = entity_map;
+            }
+            let policy = self.spawned.policy(id);
+            let spawned = match self.spawned.make_room(world, handle) {
+                Ok(()) if self.spawn_budget.is_some() => match info.hydrate(world, *id, handle) {
+                    Ok(hydrating) => {
+                        self.waiting.remove(id);
+                        self.patch_assets.remove(id);
+                        self.hydrating.push(hydrating);
+                        return false;
+                    }
+                    Err(failed) => {
+                        let (returned, err) = *failed;
+                        info = returned;
+                        Err(err)
+                    }
+                },
+                Ok(()) => info.spawn(world, policy),
+                Err(err) => Err(err),
+            };
+            match spawned {
+                Ok(()) => {
+                    span.record("entities", info.entity_map.len());
+                    self.waiting.remove(id);
+                    self.patch_assets.remove(id);
+                    self.spawned.ready(world, handle, *id, info);
+                    false
+                }
+                Err(
+                    PrefabError::NonExistentPrefab { .. } | PrefabError::NonExistentPatch { .. },
+                ) => {
+                    let waiting = self.waiting.entry(*id).or_insert_with(|| Waiting {
+                        attempts: 0,
+                        since: Instant::now(),
+                    });
+                    waiting.attempts += 1;
+                    let waited = waiting.since.elapsed();
+                    let limit = self.pending_limit;
+                    if limit.attempts.is_some_and(|max| waiting.attempts >= max)
+                        || limit.timeout.is_some_and(|timeout| waited >= timeout)
+                    {
+                        let err = PrefabError::PendingTimeout {
+                            attempts: waiting.attempts,
+                            waited,
+                        };
+                        let err = report_error(world, *id, handle, err);
+                        self.waiting.remove(id);
+                        self.patch_assets.remove(id);
+                        self.spawned.failed.insert(*id, err);
+                        return false;
+                    }
+                    *patch = info.patch;
+                    if !info.entity_map.is_empty() {
+                        self.seeded.insert(*id, info.entity_map);
+                    }
+                    if let Some(transform) = info.root_transform {
+                        self.root_transforms.insert(*id, transform);
+                    }
+                    true
+                }
+                Err(err) => {
+                    let err = report_error(world, *id, handle, err);
+                    self.waiting.remove(id);
+                    self.patch_assets.remove(id);
+                    self.spawned.failed.insert(*id, err);
+                    false
+                }
+            }
+        });
+
+        if !self.hydrating.is_empty() {
+            self.hydrate(world);
+        }
+
+        for handle in self.updates.drain(..) {
+            self.spawned.update(world, &handle);
+        }
+
+        for handle in self.patch_updates.drain(..) {
+            self.spawned.update_patch(world, &handle);
+        }
+
+        for (handle, patch) in self.patches.drain(..) {
+            self.spawned.patch_all(world, &handle, &patch);
+        }
+
+        self.to_despawn_parts.retain(|(id, part)| {
+            if let Some(info) = self.spawned.instances.get_mut(id) {
+                info.despawn_part(world, part);
+                false
+            } else {
+                // Wait for instances that are not spawned yet.
+                self.to_spawn.iter().any(|(_, pending, _)| pending == id)
+                    || self.hydrating.iter().any(|hydrating| hydrating.id == *id)
+            }
+        });
+
+        self.with_parent.retain(|&(id, parent)| {
+            if let Some(info) = self.spawned.instances.get_mut(&id) {
+                info.set_parent(world, parent);
+                self.spawned.unlink(&id);
+                if let Some(parent) = parent {
+                    self.spawned.link(id, parent);
+                }
+                false
+            } else {
+                // Wait for instances that are not spawned yet.
+                self.to_spawn.iter().any(|(_, queued, _)| *queued == id)
+                    || self.hydrating.iter().any(|hydrating| hydrating.id == id)
+            }
+        });
+
+        let stats = &mut self.spawned.stats;
+        stats.instances = self.spawned.instances.len();
+        stats.pending = self.to_spawn.len() + self.hydrating.len();
+        stats.maintain_time = start.elapsed();
+    }
+}
+
+/// Whether `component` is a [`Name`] starting with `prefix`.
+fn has_name_prefix(component: &dyn Reflect, prefix: &str) -> bool {
+    if component.type_name() != std::any::type_name::<Name>() {
+        return false;
+    }
+    let name = component.reflect_path("name").ok();
+    let name = name.and_then(|name| name.downcast_ref::<Cow<'static, str>>());
+    name.is_some_and(|name| name.starts_with(prefix))
+}
+
+/// Log the error of an instance and send it as a [`PrefabSpawnError`].
+///
+/// The path of the prefab is added to the context of the error.
+fn report_error(
+    world: &mut World,
+    id: Id,
+    handle: &Handle<Prefab>,
+    err: PrefabError,
+) -> Arc<PrefabError> {
+    let err = err.in_file(asset_path(world, handle));
+    bevy::log::error!("{}", err);
+    let error = Arc::new(err);
+    let event = PrefabSpawnError {
+        instance: PrefabInstance(id),
+        handle: handle.clone(),
+        error: error.clone(),
+    };
+    send_event(world, event);
+    error
+}
+
+/// Send an event, if the app added it.
+fn send_event<E: Event>(world: &mut World, event: E) {
+    if let Some(mut events) = world.get_resource_mut::<Events<E>>() {
+        events.send(event);
+    }
+}
+
+/// The patch asset of an instance with its `patch` applied on top of it, or only `patch`.
+fn merge_patch_asset<'a>(
+    patch: &'a Patch,
+    patch_asset: Option<&Handle<Patch>>,
+    world: &World,
+) -> Result<Cow<'a, Patch>, PrefabError> {
+    // Changes made to the instance at runtime win over the patch asset.
+    let Some(handle) = patch_asset else {
+        return Ok(Cow::Borrowed(patch));
+    };
+    let patches = world.resource::<Assets<Patch>>();
+    let mut merged = patches
+        .get(handle)
+        .ok_or_else(|| PrefabError::NonExistentPatch {
+            handle: handle.clone_weak(),
+        })?
+        .clone();
+    merged.merge(patch);
+    Ok(Cow::Owned(merged))
+}
+
+/// Despawn the placeholders reserved for the `ignore`d entities of a written instance.
+///
+/// References to ignored entities, like `Children` of their parent,
+/// make the entity mapping reserve placeholders for them.
+fn remove_placeholders(world: &mut World, entity_map: &mut EntityMap, ignore: &HashSet<u32>) {
+    let placeholders: Vec<Entity> = (ignore.iter())
+        .filter_map(|&entity| entity_map.remove(Entity::from_raw(entity)))
+        .collect();
+    if placeholders.is_empty() {
+        return;
+    }
+
+    let entities: Vec<Entity> = entity_map.values().collect();
+    for entity in entities {
+        let Some(children) = world.get::<Children>(entity) else {
+            continue;
+        };
+        if children.iter().any(|child| placeholders.contains(child)) {
+            let children = children.iter().copied();
+            let children: Vec<Entity> = children
+                .filter(|child| !placeholders.contains(child))
+                .collect();
+            let mut entity = world.entity_mut(entity);
+            entity.remove::<Children>();
+            entity.push_children(&children);
+        }
+    }
+    for entity in placeholders {
+        if let Some(entity) = world.get_entity_mut(entity) {
+            entity.despawn();
+        }
+    }
+}
+
+/// `patch` removing the kept components, see [`PrefabUpdatePolicy::KeepRuntimeChanges`].
+fn keep_components(patch: &Patch, prefab: &Prefab, kept: &HashSet<(u32, &'static str)>) -> Patch {
+    let mut patch = patch.resolve(prefab).into_owned();
+    for &(prefab_entity, type_name) in kept {
+        let index = patch.modify.iter().position(|e| e.entity == prefab_entity);
+        let index = index.unwrap_or_else(|| {
+            patch.modify.push(PatchEntity::new(prefab_entity));
+            patch.modify.len() - 1
+        });
+        patch.modify[index].remove.insert(type_name.to_string());
+    }
+    patch
+}
+
+/// The types of the components a prefab with a patch applied writes, by prefab entity id.
+///
+/// Names are the ones of the registry, unregistered components are never written.
+fn owned_components(
+    prefab: &Prefab,
+    patch: &Patch,
+    registry: &TypeRegistryInternal,
+) -> HashMap<u32, Vec<&'static str>> {
+    // Names of the registry, `'static` and shared by all instances.
+    let by_name = |type_name: &str| {
+        let registration = registry.get_with_name(type_name);
+        registration.map(|registration| registration.type_name())
+    };
+    // Reflected components are looked up by `TypeId`, without hashing their name.
+    let by_type = |component: &dyn Reflect| match component.get_represented_type_info() {
+        Some(info) => registry.get(info.type_id()).map(|r| r.type_name()),
+        None => by_name(component.type_name()),
+    };
+    let patch = patch.resolve(prefab);
+    let mut owned: HashMap<u32, Vec<&'static str>> = HashMap::default();
+    for entity in &prefab.entities {
+        if patch.ignore.contains(&entity.entity) {
+            continue;
+        }
+        let components = entity.components.iter().map(Box::as_ref);
+        let serialized = entity.serialized.iter().map(|c| c.type_name());
+        let types = owned.entry(entity.entity).or_default();
+        types.extend(components.filter_map(by_type));
+        types.extend(serialized.filter_map(by_name));
+    }
+    for entity in &patch.modify {
+        let types = owned.entry(entity.entity).or_default();
+        types.retain(|type_name| !entity.remove.contains(*type_name));
+        types.extend(entity.append.iter().map(Box::as_ref).filter_map(by_type));
+    }
+    owned
+}
+
+/// Record the path of a prefab on a span, only looked up when the span is enabled.
+fn record_path(span: &Span, world: &World, handle: &Handle<Prefab>) {
+    if !span.is_disabled() {
+        span.record("path", asset_path(world, handle).as_str());
+    }
+}
+
+/// Path of a prefab for tracing, or its handle id if it wasn't loaded from a file.
+fn asset_path(world: &World, handle: &Handle<Prefab>) -> String {
+    let path = world.get_resource::<AssetServer>();
+    let path = path.and_then(|server| server.get_handle_path(handle));
+    match path {
+        Some(path) => path.path().display().to_string(),
+        None => format!("{:?}", handle.id()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prefab::{
+        test_utils, KeepOnPrefabDespawn, Patch, PatchEntity, PatchParent, PatchedPrefabBundle,
+        PendingLimit, Prefab, PrefabBundle, PrefabComponent, PrefabError, PrefabEviction,
+        PrefabHooks, PrefabInstance, PrefabInstanceState, PrefabLabel, PrefabLoaderHooks,
+        PrefabMigrations, PrefabOrphaned, PrefabRemovalPolicy, PrefabRoot, PrefabSpawnError,
+        PrefabSpawner, PrefabUpdatePolicy, ReflectPrefabComponent, SpawnBudget,
+    };
+    use bevy::{
+        app::App,
+        asset::{Assets, Handle, HandleId},
+        core::Name,
+        ecs::event::Events,
+        ecs::world::{EntityMut, Mut, World},
+        ecs::{component::Component, entity::Entity},
+        ecs::{reflect::AppTypeRegistry, reflect::ReflectComponent},
+        hierarchy::{BuildWorldChildren, Children, Parent},
+        math::Vec3,
+        reflect::{GetPath, Reflect, TypeRegistryInternal},
+        transform::components::{GlobalTransform, Transform},
+        utils::{HashMap, HashSet, Uuid},
+    };
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Part(u32);
+
+    #[derive(Reflect, Default)]
+    struct NotComponent;
+
+    /// Inserts a [`Part`] in its place.
+    #[derive(Reflect, Default)]
+    #[reflect(PrefabComponent)]
+    struct PartProxy(u32);
+
+    impl PrefabComponent for PartProxy {
+        fn insert(self, entity: &mut EntityMut) {
+            entity.insert(Part(self.0));
+        }
+
+        fn remove(entity: &mut EntityMut) {
+            entity.remove::<Part>();
+        }
+    }
+
+    #[test]
+    fn instance_state() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let handle = Handle::<Prefab>::weak(HandleId::random::<Prefab>());
+        let owner = app.world.spawn(PrefabBundle {
+            prefab: handle.clone(),
+            ..Default::default()
+        });
+        let owner = owner.id();
+        app.update();
+
+        let instance = *app.world.get::<PrefabInstance>(owner).unwrap();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let state = Some(PrefabInstanceState::PendingAsset);
+        assert_eq!(spawner.state(&instance), state);
+        assert_eq!(app.world.get::<PrefabInstanceState>(owner).cloned(), state);
+
+        let ron = format!("{{ 0: {{ \"{}\": (0) }} }}", std::any::type_name::<Part>());
+        let prefab = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        let prefab = prefabs.remove(prefab).unwrap();
+        prefabs.set_untracked(handle.clone(), prefab);
+        app.update();
+
+        let state = app.world.get::<PrefabInstanceState>(owner);
+        assert!(state.unwrap().is_ready());
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let mut patch = Patch::default();
+        patch.modify.push(PatchEntity {
+            entity: 0,
+            uuid: None,
+            name: None,
+            append: vec![Box::new(NotComponent)],
+            modify: Default::default(),
+            remove: Default::default(),
+            parent: None,
+        });
+        let failed = spawner.spawn_with_patch(handle, None, patch);
+        assert_eq!(spawner.state(&failed), Some(PrefabInstanceState::Spawning));
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(matches!(
+            spawner.state(&failed),
+            Some(PrefabInstanceState::Failed(_))
+        ));
+        let events = app.world.resource::<Events<PrefabSpawnError>>();
+        let errors: Vec<_> = events.get_reader().iter(events).cloned().collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].instance, failed);
+        assert!(matches!(
+            errors[0].error.kind(),
+            PrefabError::UnregisteredType { .. }
+        ));
+        assert_eq!(errors[0].error.context().unwrap().entity, Some(0));
+    }
+
+    #[test]
+    fn pending_limit() {
+        let mut app = test_utils::test_app();
+        let handle = Handle::<Prefab>::weak(HandleId::random::<Prefab>());
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_pending_limit(PendingLimit {
+            attempts: Some(2),
+            timeout: None,
+        });
+        let instance = spawner.spawn(handle, None);
+        assert_eq!(spawner.pending_spawns(), 1);
+        assert_eq!(spawner.waiting_spawns(), 0);
+
+        app.update();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.waiting_spawns(), 1);
+        assert_eq!(
+            spawner.state(&instance),
+            Some(PrefabInstanceState::PendingAsset)
+        );
+
+        app.update();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.pending_spawns(), 0);
+        let Some(PrefabInstanceState::Failed(err)) = spawner.state(&instance) else {
+            panic!("expected the spawn to give up");
+        };
+        assert!(matches!(
+            err.kind(),
+            PrefabError::PendingTimeout { attempts: 2, .. }
+        ));
+        let events = app.world.resource::<Events<PrefabSpawnError>>();
+        assert_eq!(events.get_reader().iter(events).count(), 1);
+    }
+
+    #[test]
+    fn replace_pending_instance() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let first = Handle::<Prefab>::weak(HandleId::random::<Prefab>());
+        let second = Handle::<Prefab>::weak(HandleId::random::<Prefab>());
+        let owner = app.world.spawn(PrefabBundle {
+            prefab: first.clone(),
+            ..Default::default()
+        });
+        let owner = owner.id();
+        app.update();
+        let replaced = *app.world.get::<PrefabInstance>(owner).unwrap();
+
+        *app.world.get_mut::<Handle<Prefab>>(owner).unwrap() = second.clone();
+        app.update();
+        let instance = *app.world.get::<PrefabInstance>(owner).unwrap();
+        assert_ne!(instance, replaced);
+
+        for (handle, index) in [(first, 1), (second, 2)] {
+            let ron = format!(
+                "{{ 0: {{ \"{}\": ({}) }} }}",
+                std::any::type_name::<Part>(),
+                index
+            );
+            let prefab = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+            let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+            let prefab = prefabs.remove(prefab).unwrap();
+            prefabs.set_untracked(handle, prefab);
+        }
+        app.update();
+
+        let parts: Vec<&Part> = app.world.query::<&Part>().iter(&app.world).collect();
+        assert_eq!(parts, [&Part(2)]);
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&replaced).is_none());
+        assert!(spawner.state(&instance).unwrap().is_ready());
+    }
+
+    #[test]
+    fn remove_prefab_handle() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (0) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let bundle = || PrefabBundle {
+            prefab: handle.clone(),
+            ..Default::default()
+        };
+        let removed = app.world.spawn(bundle()).id();
+        let despawned = app.world.spawn(bundle()).id();
+        app.update();
+        let first = *app.world.get::<PrefabInstance>(removed).unwrap();
+        let second = *app.world.get::<PrefabInstance>(despawned).unwrap();
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 2);
+
+        app.world.entity_mut(removed).remove::<Handle<Prefab>>();
+        app.world.despawn(despawned);
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&first).is_none());
+        assert!(spawner.state(&second).is_none());
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 0);
+        assert!(app.world.get::<PrefabInstance>(removed).is_none());
+    }
+
+    #[test]
+    fn removed_prefab() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (0) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        app.update();
+
+        let prefab = app.world.resource_mut::<Assets<Prefab>>().remove(&handle);
+        assert!(prefab.is_some());
+        app.update();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(
+            spawner.state(&instance),
+            Some(PrefabInstanceState::Orphaned)
+        );
+        let events = app.world.resource::<Events<PrefabOrphaned>>();
+        assert_eq!(events.get_reader().iter(events).count(), 1);
+        test_utils::assert_component(&app, &instance, 0, &Part(0));
+
+        // Adding the prefab back updates the instance again.
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let registry = app.world.resource::<AppTypeRegistry>();
+        let prefab = Prefab::deserialize_ron(ron.as_bytes(), registry).unwrap();
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        prefabs.set_untracked(handle.clone(), prefab);
+        app.update();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&instance).unwrap().is_ready());
+        test_utils::assert_component(&app, &instance, 0, &Part(1));
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_removal_policy(PrefabRemovalPolicy::Despawn);
+        app.world.resource_mut::<Assets<Prefab>>().remove(&handle);
+        app.update();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&instance).is_none());
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 0);
+    }
+
+    #[test]
+    fn despawn_recursive() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (0) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let root = test_utils::instance_entity(&app, &instance, 0);
+        let added = app.world.spawn(Part(1)).id();
+        let grandchild = app.world.spawn(Part(2)).id();
+        let kept = app.world.spawn((Part(3), KeepOnPrefabDespawn)).id();
+        let kept_child = app.world.spawn(Part(4)).id();
+        app.world.entity_mut(root).push_children(&[added, kept]);
+        app.world.entity_mut(added).push_children(&[grandchild]);
+        app.world.entity_mut(kept).push_children(&[kept_child]);
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_despawn_recursive(true);
+        spawner.despawn(&instance);
+        app.update();
+
+        for entity in [root, added, grandchild] {
+            assert!(app.world.get_entity(entity).is_none());
+        }
+        assert!(app.world.get::<Parent>(kept).is_none());
+        assert_eq!(app.world.get::<Parent>(kept_child).unwrap().get(), kept);
+    }
+
+    #[test]
+    fn spawn_budget() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let mut source = World::new();
+        let registry = app.world.resource::<AppTypeRegistry>().clone();
+        source.insert_resource(registry.clone());
+        source.spawn(Part(0)).with_children(|root| {
+            root.spawn(Part(1));
+            root.spawn(Part(2));
+            root.spawn(Part(3));
+        });
+        let prefab = Prefab::from_world(&source, &registry);
+        let handle = app.world.resource_mut::<Assets<Prefab>>().add(prefab);
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_spawn_budget(Some(SpawnBudget::Entities(3)));
+        let instance = spawner.spawn(handle.clone(), None);
+        assert_eq!(spawner.spawn_progress(&instance), Some(0.0));
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(
+            spawner.state(&instance),
+            Some(PrefabInstanceState::Spawning)
+        );
+        assert_eq!(spawner.spawn_progress(&instance), Some(0.75));
+        assert_eq!(spawner.pending_spawns(), 1);
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 3);
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&instance).unwrap().is_ready());
+        assert_eq!(spawner.spawn_progress(&instance), Some(1.0));
+        let root = test_utils::instance_entity(&app, &instance, 0);
+        let children: Vec<Entity> = (1..4)
+            .map(|id| test_utils::instance_entity(&app, &instance, id))
+            .collect();
+        assert_eq!(&**app.world.get::<Children>(root).unwrap(), children);
+
+        // a despawned instance stops being written
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn(handle, None);
+        app.update();
+        app.world.resource_mut::<PrefabSpawner>().despawn(&instance);
+        app.update();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.spawn_progress(&instance).is_none());
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 4);
+    }
+
+    #[test]
+    fn spawn_from_ron() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (5) }} }}", std::any::type_name::<Part>());
+        let instance = app
+            .world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                spawner.spawn_from_ron(world, ron.as_bytes(), None)
+            })
+            .unwrap();
+        app.update();
+        test_utils::assert_component(&app, &instance, 0, &Part(5));
+
+        app.world.resource_mut::<PrefabSpawner>().despawn(&instance);
+        // Unused assets are freed a few frames after the last handle is dropped.
+        for _ in 0..4 {
+            app.update();
+        }
+        assert!(app.world.resource::<Assets<Prefab>>().is_empty());
+
+        let invalid = app
+            .world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                spawner.spawn_from_ron(world, b"{ 0: ", None)
+            });
+        assert!(invalid.is_err());
+    }
+
+    #[test]
+    fn spawn_from_ron_like_loaded() {
+        #[derive(Reflect, Default)]
+        struct NotComponent;
+
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        app.register_type::<NotComponent>();
+        let spawn = |app: &mut App, ron: &str| {
+            app.world
+                .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                    spawner.spawn_from_ron(world, ron.as_bytes(), None)
+                })
+        };
+
+        // Versioned prefabs are migrated.
+        let ron = format!(
+            "(version: 1, entities: {{ 0: {{ \"{}\": (5) }} }})",
+            std::any::type_name::<Part>()
+        );
+        assert!(spawn(&mut app, &ron).is_err());
+        app.world.resource::<PrefabMigrations>().add(|_, _| Ok(()));
+        let instance = spawn(&mut app, &ron).unwrap();
+        app.update();
+        test_utils::assert_component(&app, &instance, 0, &Part(5));
+
+        // Types that aren't components are reported when strict, before spawning.
+        let ron = format!(
+            "{{ 0: {{ \"{}\": () }} }}",
+            std::any::type_name::<NotComponent>()
+        );
+        assert!(spawn(&mut app, &ron).is_ok());
+        app.world.resource_mut::<PrefabSpawner>().set_strict(true);
+        let err = spawn(&mut app, &ron).unwrap_err();
+        let err = err.downcast::<PrefabError>().unwrap();
+        assert!(matches!(err, PrefabError::UnregisteredComponents { .. }));
+    }
+
+    /// Loader hook doubling every [`Part`].
+    fn double_parts(prefab: &mut Prefab, _: &TypeRegistryInternal) {
+        for entity in &mut prefab.entities {
+            for component in &mut entity.components {
+                if component.type_name() == std::any::type_name::<Part>() {
+                    let value = component.reflect_path_mut(".0").unwrap();
+                    *value.downcast_mut::<u32>().unwrap() *= 2;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn loader_hooks() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        app.world.resource::<PrefabLoaderHooks>().add(double_parts);
+
+        let ron = format!(
+            "{{ 0: {{ \"{0}\": (5) }}, 1: {{ \"{0}\": (7) }} }}",
+            std::any::type_name::<Part>()
+        );
+        let instance = app
+            .world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                spawner.spawn_from_ron(world, ron.as_bytes(), None)
+            })
+            .unwrap();
+        app.update();
+        test_utils::assert_component(&app, &instance, 0, &Part(10));
+        test_utils::assert_component(&app, &instance, 1, &Part(14));
+    }
+
+    #[test]
+    fn set_parent() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!(
+            "{{ 0: {{ \"{0}\": (0) }}, 1: {{ \"{0}\": (1) }} }}",
+            std::any::type_name::<Part>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let first = app.world.spawn_empty().id();
+        let second = app.world.spawn_empty().id();
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn(handle.clone(), Some(first));
+        spawner.set_parent(&instance, Some(second));
+        app.update();
+
+        assert!(app.world.get::<Children>(first).is_none());
+        assert_eq!(app.world.get::<Children>(second).unwrap().len(), 2);
+
+        app.world
+            .resource_mut::<PrefabSpawner>()
+            .set_parent(&instance, None);
+        app.update();
+        assert!(app.world.get::<Children>(second).is_none());
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.info(&instance).unwrap().parent(), None);
+    }
+
+    #[test]
+    fn extract_instance() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!(
+            "{{ 3: {{ \"{0}\": (3) }}, 5: {{ \"{0}\": (5) }} }}",
+            std::any::type_name::<Part>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let owner = app.world.spawn_empty().id();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn(handle, Some(owner));
+        app.update();
+
+        let root = test_utils::instance_entity(&app, &instance, 3);
+        let child = test_utils::instance_entity(&app, &instance, 5);
+        app.world.entity_mut(root).push_children(&[child]);
+        app.world.get_mut::<Part>(child).unwrap().0 = 7;
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let prefab = spawner.extract_instance(&app.world, &instance).unwrap();
+        let ids: Vec<_> = prefab.entities.iter().map(|entity| entity.entity).collect();
+        assert_eq!(ids, [3, 5]);
+
+        let handle = app.world.resource_mut::<Assets<Prefab>>().add(prefab);
+        let copy = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let root = test_utils::instance_entity(&app, &copy, 3);
+        let child = test_utils::instance_entity(&app, &copy, 5);
+        assert!(app.world.get::<Parent>(root).is_none());
+        assert_eq!(app.world.get::<Parent>(child).unwrap().get(), root);
+        test_utils::assert_component(&app, &copy, 5, &Part(7));
+    }
+
+    #[test]
+    fn capture_overrides() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!(
+            "{{ 0: {{ \"{0}\": (0) }}, 1: {{ \"{0}\": (1) }} }}",
+            std::any::type_name::<Part>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let patch = spawner.capture_overrides(&app.world, &instance).unwrap();
+        assert!(patch.modify.is_empty());
+
+        let root = test_utils::instance_entity(&app, &instance, 0);
+        let child = test_utils::instance_entity(&app, &instance, 1);
+        app.world.get_mut::<Part>(root).unwrap().0 = 5;
+        app.world.entity_mut(root).push_children(&[child]);
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let patch = spawner.capture_overrides(&app.world, &instance).unwrap();
+        let overrides = patch
+            .modify
+            .iter()
+            .find(|entity| entity.entity == 1)
+            .unwrap();
+        assert_eq!(overrides.parent, Some(PatchParent::Entity(0)));
+        assert!(overrides.modify.is_empty());
+
+        // Spawning the prefab with the patch gives the same entities.
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let copy = spawner.spawn_with_patch(handle.clone(), None, patch);
+        app.update();
+        test_utils::assert_component(&app, &copy, 0, &Part(5));
+        test_utils::assert_component(&app, &copy, 1, &Part(1));
+        let root = test_utils::instance_entity(&app, &copy, 0);
+        let child = test_utils::instance_entity(&app, &copy, 1);
+        assert_eq!(app.world.get::<Parent>(child).unwrap().get(), root);
+
+        // Instances that aren't spawned yet have nothing to capture.
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let queued = spawner.spawn(handle, None);
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let err = spawner.capture_overrides(&app.world, &queued);
+        assert!(matches!(err, Err(PrefabError::NotSpawned { .. })));
+    }
+
+    #[test]
+    fn clone_instance() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        let owner = app.world.spawn_empty().id();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_name_entities(true);
+
+        let ron = format!("{{ 0: {{ \"{}\": (0) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn(handle.clone(), Some(owner));
+        app.update();
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+        app.world.get_mut::<Part>(entity).unwrap().0 = 7;
+
+        let copy = app
+            .world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                spawner.clone_instance(world, &instance)
+            })
+            .unwrap();
+        app.update();
+
+        test_utils::assert_component(&app, &copy, 0, &Part(7));
+        let entity = test_utils::instance_entity(&app, &copy, 0);
+        assert_eq!(app.world.get::<Parent>(entity).unwrap().get(), owner);
+        let name = app.world.get::<Name>(entity).map(Name::as_str);
+        assert_eq!(name, Some("prefab#1/0"));
+
+        // The copy is an instance of the same prefab.
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.info(&copy).unwrap().handle(), &handle);
+
+        // Despawned instances can't be cloned.
+        app.world.resource_mut::<PrefabSpawner>().despawn(&instance);
+        app.update();
+        let err = app
+            .world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                spawner.clone_instance(world, &instance)
+            });
+        assert!(matches!(err, Err(PrefabError::NotSpawned { .. })));
+    }
+
+    #[test]
+    fn spawn_at() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let mut source = World::new();
+        let registry = app.world.resource::<AppTypeRegistry>().clone();
+        source.insert_resource(registry.clone());
+        source
+            .spawn((Part(0), Transform::from_xyz(5.0, 0.0, 0.0)))
+            .with_children(|root| {
+                root.spawn((Part(1), Transform::from_xyz(0.0, 1.0, 0.0)));
+            });
+        let prefab = Prefab::from_world(&source, &registry);
+        let handle = app.world.resource_mut::<Assets<Prefab>>().add(prefab);
+
+        let transform = Transform::from_xyz(1.0, 2.0, 3.0);
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn_at(handle.clone(), transform, None);
+        app.update();
+
+        test_utils::assert_component(&app, &instance, 0, &transform);
+        test_utils::assert_component(&app, &instance, 1, &Transform::from_xyz(0.0, 1.0, 0.0));
+
+        // Updates keep the roots in place.
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        prefabs.get_mut(&handle).unwrap();
+        app.update();
+        test_utils::assert_component(&app, &instance, 0, &transform);
+    }
+
+    #[test]
+    fn prefab_root() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let mut source = World::new();
+        let registry = app.world.resource::<AppTypeRegistry>().clone();
+        source.insert_resource(registry.clone());
+        source.spawn(Part(0)).with_children(|root| {
+            root.spawn(Part(1)).with_children(|child| {
+                child.spawn(Part(2));
+            });
+        });
+        source.spawn(Part(3));
+        let prefab = Prefab::from_world(&source, &registry);
+        let handle = app.world.resource_mut::<Assets<Prefab>>().add(prefab);
+
+        let parent = app.world.spawn_empty().id();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn(handle, Some(parent));
+        app.update();
+
+        let root = PrefabRoot { instance };
+        let roots = |app: &App| -> Vec<bool> {
+            (0..4)
+                .map(|id| test_utils::instance_entity(app, &instance, id))
+                .map(|entity| app.world.get::<PrefabRoot>(entity) == Some(&root))
+                .collect()
+        };
+        assert_eq!(roots(&app), [true, false, false, true]);
+
+        // The children of a despawned entity become roots.
+        let child = test_utils::instance_entity(&app, &instance, 2);
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.despawn_entities(&instance, [1]);
+        app.update();
+        assert_eq!(app.world.get::<PrefabRoot>(child), Some(&root));
+    }
+
+    #[test]
+    fn spawn_hooks() {
+        #[derive(Component)]
+        struct Hooked(u32);
+
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        app.world
+            .resource_mut::<PrefabHooks>()
+            .add(|world, info, _| {
+                for entity in info.entities() {
+                    let mut entity = world.entity_mut(entity);
+                    let count = entity.get::<Hooked>().map_or(0, |hooked| hooked.0);
+                    entity.insert(Hooked(count + 1));
+                }
+            });
+
+        let ron = format!("{{ 0: {{ {:?}: (0) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn(handle.clone(), None);
+        app.update();
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+        assert_eq!(app.world.get::<Hooked>(entity).unwrap().0, 1);
+
+        // Hot reloads run the hooks again, once the modification is seen by the next frame.
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        prefabs.get_mut(&handle).unwrap();
+        app.update();
+        app.update();
+        assert_eq!(app.world.get::<Hooked>(entity).unwrap().0, 2);
+
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+        assert_eq!(app.world.get::<Hooked>(entity).unwrap().0, 1);
+    }
+
+    #[test]
+    fn spawn_into() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!(
+            "{{ 0: {{ \"{0}\": (0) }}, 1: {{ \"{0}\": (1) }} }}",
+            std::any::type_name::<Part>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let placeholder = app.world.spawn(Name::new("placeholder")).id();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn_into(handle, None, [(1, placeholder)]);
+        app.update();
+
+        assert_eq!(test_utils::instance_entity(&app, &instance, 1), placeholder);
+        test_utils::assert_component(&app, &instance, 1, &Part(1));
+        assert_eq!(
+            app.world.get::<Name>(placeholder).unwrap().as_str(),
+            "placeholder"
+        );
+        test_utils::assert_component(&app, &instance, 0, &Part(0));
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 2);
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.despawn(&instance);
+        app.update();
+        assert!(app.world.get_entity(placeholder).is_none());
+    }
+
+    #[test]
+    fn remove_stale_components() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let part = std::any::type_name::<Part>();
+        let transform = "(
+            translation: (x: 0.0, y: 0.0, z: 0.0),
+            rotation: (0.0, 0.0, 0.0, 1.0),
+            scale: (x: 1.0, y: 1.0, z: 1.0),
+        )";
+        let ron = format!(
+            "{{ 0: {{ \"{}\": (1), \"{}\": {} }} }}",
+            part,
+            std::any::type_name::<Transform>(),
+            transform,
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+        app.world.entity_mut(entity).insert(Name::new("runtime"));
+        assert!(app.world.get::<Transform>(entity).is_some());
+
+        let registry = app.world.resource::<AppTypeRegistry>().0.clone();
+        let ron = format!("{{ 0: {{ \"{}\": (2) }} }}", part);
+        let prefab = Prefab::deserialize_ron(ron.as_bytes(), &registry).unwrap();
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        *prefabs.get_mut(&handle).unwrap() = prefab;
+        // The modification is sent at the end of the frame and seen by the next one.
+        app.update();
+        app.update();
+
+        // Components added at runtime are kept.
+        test_utils::assert_component(&app, &instance, 0, &Part(2));
+        assert!(app.world.get::<Transform>(entity).is_none());
+        assert!(app.world.get::<Name>(entity).is_some());
+    }
+
+    #[test]
+    fn remove_proxies() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        app.register_type::<PartProxy>();
+
+        let proxy = std::any::type_name::<PartProxy>();
+        let ron = format!("{{ 0: {{ {0:?}: (1) }}, 1: {{ {0:?}: (2) }} }}", proxy);
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        test_utils::assert_component(&app, &instance, 0, &Part(1));
+        test_utils::assert_component(&app, &instance, 1, &Part(2));
+
+        let mut patch = Patch::default();
+        patch.modify.push(PatchEntity {
+            entity: 0,
+            uuid: None,
+            name: None,
+            append: Vec::new(),
+            modify: HashMap::default(),
+            remove: HashSet::from_iter([proxy.to_string()]),
+            parent: None,
+        });
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.patch_all(&handle, &patch);
+        app.update();
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+        assert!(app.world.get::<Part>(entity).is_none());
+
+        // The proxy is gone from the updated prefab.
+        let registry = app.world.resource::<AppTypeRegistry>().0.clone();
+        let prefab = Prefab::deserialize_ron(b"{ 0: {}, 1: {} }", &registry).unwrap();
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        *prefabs.get_mut(&handle).unwrap() = prefab;
+        // The modification is sent at the end of the frame and seen by the next one.
+        app.update();
+        app.update();
+        let entity = test_utils::instance_entity(&app, &instance, 1);
+        assert!(app.world.get::<Part>(entity).is_none());
+    }
+
+    #[test]
+    fn update_policies() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = |part: u32, scale: f32| {
+            format!(
+                "{{ 0: {{ \"{}\": ({}), \"{}\": (
+                    translation: (x: 0.0, y: 0.0, z: 0.0),
+                    rotation: (0.0, 0.0, 0.0, 1.0),
+                    scale: (x: {:?}, y: 1.0, z: 1.0),
+                ) }} }}",
+                std::any::type_name::<Part>(),
+                part,
+                std::any::type_name::<Transform>(),
+                scale,
+            )
+        };
+        let handle = test_utils::load_prefab_str(&mut app, &ron(1, 1.0)).unwrap();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_update_policy(PrefabUpdatePolicy::KeepRuntimeChanges);
+        let kept = spawner.spawn(handle.clone(), None);
+        let merged = spawner.spawn(handle.clone(), None);
+        spawner.set_instance_update_policy(&merged, Some(PrefabUpdatePolicy::Merge));
+        app.update();
+
+        for instance in [kept, merged] {
+            let entity = test_utils::instance_entity(&app, &instance, 0);
+            app.world.get_mut::<Part>(entity).unwrap().0 = 7;
+            let mut transform = app.world.get_mut::<Transform>(entity).unwrap();
+            transform.translation.x = 5.0;
+        }
+        let registry = app.world.resource::<AppTypeRegistry>().0.clone();
+        let prefab = Prefab::deserialize_ron(ron(2, 2.0).as_bytes(), &registry).unwrap();
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        *prefabs.get_mut(&handle).unwrap() = prefab;
+        // The modification is sent at the end of the frame and seen by the next one.
+        app.update();
+        app.update();
+
+        // Changed components are kept whole, or only their changed fields are.
+        let mut transform = Transform::from_xyz(5.0, 0.0, 0.0);
+        test_utils::assert_component(&app, &kept, 0, &Part(7));
+        test_utils::assert_component(&app, &kept, 0, &transform);
+        transform.scale.x = 2.0;
+        test_utils::assert_component(&app, &merged, 0, &Part(7));
+        test_utils::assert_component(&app, &merged, 0, &transform);
+
+        // The changes survive later updates.
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        prefabs.get_mut(&handle).unwrap();
+        app.update();
+        app.update();
+        test_utils::assert_component(&app, &kept, 0, &Part(7));
+        test_utils::assert_component(&app, &merged, 0, &Part(7));
+        test_utils::assert_component(&app, &merged, 0, &transform);
+    }
+
+    #[test]
+    fn patch_all() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let transform = "(
+            translation: (x: 0.0, y: 0.0, z: 0.0),
+            rotation: (0.0, 0.0, 0.0, 1.0),
+            scale: (x: 1.0, y: 1.0, z: 1.0),
+        )";
+        let ron = format!(
+            "{{ 0: {{ \"{}\": {} }}, 1: {{ \"{}\": (1) }} }}",
+            std::any::type_name::<Transform>(),
+            transform,
+            std::any::type_name::<Part>(),
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let first = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let second = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let entity = test_utils::instance_entity(&app, &first, 0);
+        let mut transform = app.world.get_mut::<Transform>(entity).unwrap();
+        transform.translation.y = 2.0;
+
+        let mut patch = Patch::default();
+        patch.ignore.insert(1);
+        patch.modify.push(PatchEntity {
+            entity: 0,
+            uuid: None,
+            name: None,
+            append: vec![Box::new(Part(0))],
+            modify: HashMap::from_iter([(
+                std::any::type_name::<Transform>().to_string(),
+                HashMap::from_iter([(
+                    String::from(".translation.x"),
+                    Box::new(5.0f32) as Box<dyn Reflect>,
+                )]),
+            )]),
+            remove: Default::default(),
+            parent: None,
+        });
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.patch_all(&handle, &patch);
+        app.update();
+
+        for (instance, y) in [(first, 2.0), (second, 0.0)] {
+            let translation = Vec3::new(5.0, y, 0.0);
+            test_utils::assert_component(
+                &app,
+                &instance,
+                0,
+                &Transform::from_translation(translation),
+            );
+            test_utils::assert_component(&app, &instance, 0, &Part(0));
+            let spawner = app.world.resource::<PrefabSpawner>();
+            assert_eq!(spawner.info(&instance).unwrap().entities().count(), 1);
+        }
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 2);
+
+        // The patch survives a hot reload.
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        prefabs.get_mut(&handle).unwrap();
+        app.update();
+        test_utils::assert_component(&app, &second, 0, &Part(0));
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 2);
+    }
+
+    #[test]
+    fn patch_survives_reload() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = |value: u32| {
+            let type_name = std::any::type_name::<Part>();
+            format!(
+                "{{ 0: {{ \"{0}\": ({1}) }}, 1: {{ \"{0}\": ({1}) }} }}",
+                type_name, value
+            )
+        };
+        let handle = test_utils::load_prefab_str(&mut app, &ron(1)).unwrap();
+        let mut patch = Patch::default();
+        patch.ignore.insert(1);
+        patch.modify.push(PatchEntity::new(0));
+        patch.modify[0].set_field::<Part>(".0", &4u32);
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn_with_patch(handle.clone(), None, patch);
+        app.update();
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+
+        let registry = app.world.resource::<AppTypeRegistry>().0.clone();
+        let prefab = Prefab::deserialize_ron(ron(2).as_bytes(), &registry).unwrap();
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        *prefabs.get_mut(&handle).unwrap() = prefab;
+        app.update();
+
+        test_utils::assert_component(&app, &instance, 0, &Part(4));
+        assert_eq!(test_utils::instance_entity(&app, &instance, 0), entity);
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn patch_asset_reload() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let patch_handle = Handle::<Patch>::weak(HandleId::random::<Patch>());
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn_with_patch_asset(handle, None, patch_handle.clone());
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let state = spawner.state(&instance);
+        assert_eq!(state, Some(PrefabInstanceState::PendingAsset));
+
+        let part = |value: u32| {
+            let mut patch = Patch::default();
+            patch.modify.push(PatchEntity {
+                entity: 0,
+                uuid: None,
+                name: None,
+                append: Vec::new(),
+                modify: HashMap::from_iter([(
+                    std::any::type_name::<Part>().to_string(),
+                    HashMap::from_iter([(String::from(".0"), Box::new(value) as Box<dyn Reflect>)]),
+                )]),
+                remove: Default::default(),
+                parent: None,
+            });
+            patch
+        };
+        let mut patches = app.world.resource_mut::<Assets<Patch>>();
+        patches.set_untracked(patch_handle.clone(), part(2));
+        app.update();
+        test_utils::assert_component(&app, &instance, 0, &Part(2));
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+
+        // Modifying the patch asset applies it to the live instance.
+        let mut patches = app.world.resource_mut::<Assets<Patch>>();
+        *patches.get_mut(&patch_handle).unwrap() = part(3);
+        // The modification is sent at the end of the frame and seen by the next one.
+        app.update();
+        app.update();
+        test_utils::assert_component(&app, &instance, 0, &Part(3));
+        assert_eq!(test_utils::instance_entity(&app, &instance, 0), entity);
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn patched_bundle() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let prefab = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let mut patch = Patch::default();
+        patch.modify.push(PatchEntity::new(0));
+        patch.modify[0].set_field::<Part>(".0", &4u32);
+        let patch = app.world.resource_mut::<Assets<Patch>>().add(patch);
+        let owner = app.world.spawn(PatchedPrefabBundle {
+            prefab,
+            patch,
+            ..Default::default()
+        });
+        let owner = owner.id();
+        app.update();
+
+        let instance = *app.world.get::<PrefabInstance>(owner).unwrap();
+        test_utils::assert_component(&app, &instance, 0, &Part(4));
+        let entity = test_utils::instance_entity(&app, &instance, 0);
+        assert_eq!(app.world.get::<Parent>(entity).unwrap().get(), owner);
+    }
+
+    #[test]
+    fn despawn_unused() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_despawn_unused(true);
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let ron = ron.as_bytes();
+        let owned = app
+            .world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                spawner.spawn_from_ron(world, ron, None)
+            })
+            .unwrap();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let instance = spawner.spawn(handle.clone(), None);
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.state(&instance), Some(PrefabInstanceState::Ready));
+
+        // The asset is freed and its removal seen over the next updates.
+        drop(handle);
+        for _ in 0..3 {
+            app.update();
+        }
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.state(&instance), None);
+        assert_eq!(spawner.state(&owned), Some(PrefabInstanceState::Ready));
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn gc() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let dead = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let alive = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let entity = test_utils::instance_entity(&app, &dead, 0);
+        app.world.despawn(entity);
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let failed = spawner.spawn(Handle::<Prefab>::weak(HandleId::random::<Prefab>()), None);
+        spawner.set_pending_limit(PendingLimit {
+            attempts: Some(1),
+            timeout: None,
+        });
+        app.update();
+
+        app.world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                assert_eq!(spawner.gc(world), 1);
+                assert_eq!(spawner.gc(world), 0);
+            });
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.state(&dead), None);
+        assert_eq!(spawner.state(&failed), None);
+        assert!(spawner.state(&alive).unwrap().is_ready());
+
+        // The prefab is released once its last instance is forgotten.
+        let entity = test_utils::instance_entity(&app, &alive, 0);
+        app.world.despawn(entity);
+        app.world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                assert_eq!(spawner.gc(world), 1);
+            });
+        let weak = handle.clone_weak();
+        drop(handle);
+        for _ in 0..3 {
+            app.update();
+        }
+        assert!(!app.world.resource::<Assets<Prefab>>().contains(&weak));
+    }
+
+    #[test]
+    fn name_entities() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_name_entities(true);
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let first = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let second = test_utils::spawn_sync(&mut app, &handle).unwrap();
+
+        for (instance, expected) in [(first, "prefab#0/0"), (second, "prefab#1/0")] {
+            let entity = test_utils::instance_entity(&app, &instance, 0);
+            assert_eq!(
+                app.world.get::<Name>(entity).map(Name::as_str),
+                Some(expected)
+            );
+        }
+    }
+
+    #[test]
+    fn entity_lookup() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_name_entities(true);
+
+        let ron = format!(
+            "{{ 0: {{ {0:?}: (0) }}, 1: {{ {0:?}: (1) }} }}",
+            std::any::type_name::<Part>()
+        );
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let mut prefabs = app.world.resource_mut::<Assets<Prefab>>();
+        let prefab = prefabs.get_mut(&handle).unwrap();
+        let muzzle = Box::new(Name::new("muzzle"));
+        prefab.entities[1].components.push(muzzle);
+        let label = Box::new(PrefabLabel(String::from("Muzzle")));
+        prefab.entities[1].components.push(label);
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let other = app.world.spawn_empty().id();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let info = spawner.info(&instance).unwrap();
+        let muzzle = info.entity(1).unwrap();
+        assert_eq!(app.world.get::<Part>(muzzle), Some(&Part(1)));
+        assert_eq!(info.entity(2), None);
+
+        assert_eq!(info.prefab_id_of(muzzle), Some(1));
+        assert_eq!(spawner.instance_of(muzzle), Some(instance));
+        assert_eq!(info.prefab_id_of(other), None);
+        assert_eq!(spawner.instance_of(other), None);
+
+        let prefabs = app.world.resource::<Assets<Prefab>>();
+        assert_eq!(info.entity_by_name(prefabs, "muzzle"), Some(muzzle));
+        assert_eq!(info.entity_by_name(prefabs, "prefab#0/muzzle"), None);
+        assert_eq!(info.find_by_label(prefabs, "Muzzle"), Some(muzzle));
+        assert_eq!(info.find_by_label(prefabs, "Grip"), None);
+        let name = app.world.get::<Name>(muzzle).map(Name::as_str);
+        assert_eq!(name, Some("prefab#0/muzzle"));
+    }
+
+    #[test]
+    fn iter_instances() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (0) }} }}", std::any::type_name::<Part>());
+        let campfire = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let other = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let first = test_utils::spawn_sync(&mut app, &campfire).unwrap();
+        test_utils::spawn_sync(&mut app, &other).unwrap();
+        let second = test_utils::spawn_sync(&mut app, &campfire).unwrap();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let instances = spawner.iter_instances(&campfire);
+        let ids: Vec<PrefabInstance> = instances.map(|(id, _)| id).collect();
+        assert_eq!(ids, [first, second]);
+        let mut instances = spawner.iter_instances(&campfire.clone_weak());
+        assert!(instances.all(|(_, info)| info.handle() == &campfire));
+    }
+
+    #[test]
+    fn max_instances() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_max_instances(&handle, 2, PrefabEviction::Reject);
+        let first = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let second = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let rejected = test_utils::spawn_sync(&mut app, &handle);
+        assert!(matches!(
+            rejected,
+            Err(PrefabError::TooManyInstances { max: 2 })
+        ));
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_max_instances(&handle, 2, PrefabEviction::Oldest);
+        let third = spawner.spawn(handle.clone(), None);
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.state(&first), None);
+        assert_eq!(spawner.state(&second), Some(PrefabInstanceState::Ready));
+        assert_eq!(spawner.state(&third), Some(PrefabInstanceState::Ready));
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 2);
+    }
+
+    #[test]
+    fn evict_farthest() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let origin = app.world.spawn(GlobalTransform::IDENTITY).id();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_max_instances(&handle, 2, PrefabEviction::Farthest(origin));
+
+        let mut instances = Vec::new();
+        for x in [1.0, 5.0] {
+            let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+            let entity = test_utils::instance_entity(&app, &instance, 0);
+            let transform = GlobalTransform::from_translation(Vec3::new(x, 0.0, 0.0));
+            app.world.entity_mut(entity).insert(transform);
+            instances.push(instance);
+        }
+        test_utils::spawn_sync(&mut app, &handle).unwrap();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.info(&instances[0]).is_some());
+        assert!(spawner.info(&instances[1]).is_none());
+    }
+
+    #[test]
+    fn deterministic_ids() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (1) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.set_id_seed(Some(7));
+        let first = spawner.spawn(handle.clone(), None);
+        spawner.despawn(&first);
+        spawner.set_id_seed(Some(7));
+        let again = spawner.spawn(handle.clone(), None);
+        assert_eq!(first, again);
+
+        let given = PrefabInstance::from_uuid(Uuid::from_u128(42));
+        let given = spawner.spawn_with_id(given, handle.clone(), None, Patch::default());
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert_eq!(spawner.state(&again), Some(PrefabInstanceState::Ready));
+        assert_eq!(spawner.state(&given), Some(PrefabInstanceState::Ready));
+    }
+
+    #[test]
+    fn despawn_nested() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        let ron = format!("{{ 0: {{ \"{}\": (0) }} }}", std::any::type_name::<Part>());
+        let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+
+        let outer = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let root = test_utils::instance_entity(&app, &outer, 0);
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let inner = spawner.spawn(handle.clone(), Some(root));
+        app.update();
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        let unrelated = spawner.spawn(handle.clone(), None);
+        assert_eq!(spawner.parent_of(&inner), Some(outer));
+        assert_eq!(spawner.children_of(&outer).collect::<Vec<_>>(), [inner]);
+        assert_eq!(spawner.parent_of(&outer), None);
+        app.update();
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 3);
+
+        let mut spawner = app.world.resource_mut::<PrefabSpawner>();
+        spawner.despawn(&outer);
+        app.update();
+
+        let spawner = app.world.resource::<PrefabSpawner>();
+        assert!(spawner.state(&inner).is_none());
+        assert!(spawner.state(&unrelated).unwrap().is_ready());
+        assert_eq!(spawner.children_of(&outer).count(), 0);
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 1);
+    }
+
+    #[test]
+    fn despawn_subtree() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Part>();
+
+        // vehicle (0) -> turret (1) -> barrel (2)
+        //             -> wheel (3)
+        let mut source = World::new();
+        let registry = app.world.resource::<AppTypeRegistry>().clone();
+        source.insert_resource(registry.clone());
+        source.spawn(Part(0)).with_children(|vehicle| {
+            vehicle.spawn(Part(1)).with_children(|turret| {
+                turret.spawn(Part(2));
+            });
+            vehicle.spawn(Part(3));
+        });
+        let prefab = Prefab::from_world(&source, &registry);
+        let handle = app.world.resource_mut::<Assets<Prefab>>().add(prefab);
+
+        let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+        let vehicle = test_utils::instance_entity(&app, &instance, 0);
+
+        app.world
+            .resource_mut::<PrefabSpawner>()
+            .despawn_subtree(&instance, 1);
+        app.update();
+
+        let mut parts: Vec<u32> = app
+            .world
+            .query::<&Part>()
+            .iter(&app.world)
+            .map(|p| p.0)
+            .collect();
+        parts.sort();
+        assert_eq!(parts, [0, 3]);
+        assert_eq!(app.world.get::<Children>(vehicle).unwrap().len(), 1);
+
+        // a hot reload keeps the part despawned
+        app.world
+            .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| {
+                spawner.update_sync(world, &handle)
+            });
+        assert_eq!(app.world.query::<&Part>().iter(&app.world).count(), 2);
+        assert_eq!(app.world.get::<Children>(vehicle).unwrap().len(), 1);
+        let spawner = app.world.resource::<PrefabSpawner>();
+        let info = spawner.info(&instance).unwrap();
+        assert_eq!(info.entities().count(), 2);
+        assert!(info.patch().ignore.contains(&1) && info.patch().ignore.contains(&2));
     }
 }
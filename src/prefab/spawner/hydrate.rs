@@ -0,0 +1,238 @@
+use super::{
+    merge_patch_asset, owned_components, record_path, remove_placeholders, report_error, Id,
+    PrefabInstanceInfo, PrefabSpawner, PrefabUpdatePolicy,
+};
+use crate::prefab::{
+    write::{write_part, Hierarchy},
+    Patch, Prefab, PrefabError,
+};
+use bevy::{
+    asset::{Assets, Handle},
+    ecs::{
+        entity::Entity,
+        reflect::AppTypeRegistry,
+        world::{Mut, World},
+    },
+    log::info_span,
+    utils::{tracing::field::Empty, Duration, HashSet, Instant},
+};
+
+/// How much of the queued spawns each maintenance writes, see [`PrefabSpawner::set_spawn_budget`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpawnBudget {
+    /// Write at most this many entities per maintenance.
+    Entities(usize),
+    /// Stop writing entities once the maintenance spent this long on them.
+    ///
+    /// The number of entities that fit is guessed from the entities already written,
+    /// so the budget may be exceeded by a few entities.
+    Time(Duration),
+}
+
+/// Entities written first by a maintenance with a [`SpawnBudget::Time`],
+/// before it knows how long writing an entity takes.
+const FIRST_TIMED_PART: usize = 16;
+
+/// Lower bound of the time guessed to write an entity,
+/// as a coarse clock may see no time pass while writing the first entities.
+const MIN_ENTITY_TIME: Duration = Duration::from_micros(1);
+
+/// Guess how many more entities fit in a [`SpawnBudget::Time`],
+/// from the `written` entities that took `elapsed`.
+fn timed_count(budget: Duration, elapsed: Duration, written: usize) -> usize {
+    let per_entity = elapsed.as_secs_f64() / written as f64;
+    let per_entity = per_entity.max(MIN_ENTITY_TIME.as_secs_f64());
+    let count = (budget - elapsed).as_secs_f64() / per_entity;
+    (count as usize).max(1)
+}
+
+impl PrefabInstanceInfo {
+    /// Prepare to write the instance over several maintenances, see [`PrefabSpawner::set_spawn_budget`].
+    ///
+    /// All the entities of the instance are reserved right away,
+    /// so that references between entities written by different maintenances are mapped.
+    /// On failure the instance is given back, boxed with the error to keep the result small.
+    pub(super) fn hydrate(
+        mut self,
+        world: &mut World,
+        id: Id,
+        handle: &Handle<Prefab>,
+    ) -> Result<Hydrating, Box<(PrefabInstanceInfo, PrefabError)>> {
+        let patch = merge_patch_asset(&self.patch, self.patch_asset.as_ref(), world);
+        let patch = match patch {
+            Ok(patch) => patch.into_owned(),
+            Err(err) => return Err(Box::new((self, err))),
+        };
+        let prefabs = world.resource::<Assets<Prefab>>();
+        let Some(prefab) = prefabs.get(&self.handle) else {
+            let handle = self.handle.clone_weak();
+            return Err(Box::new((self, PrefabError::NonExistentPrefab { handle })));
+        };
+
+        let patch = patch.resolve(prefab).into_owned();
+        let mut seen = HashSet::default();
+        let ids = (prefab.entities.iter())
+            .map(|entity| entity.entity)
+            .filter(|id| !patch.ignore.contains(id))
+            .chain(patch.modify.iter().map(|entity| entity.entity));
+        let entities: Vec<u32> = ids.filter(|&id| seen.insert(id)).collect();
+
+        for &id in &entities {
+            let prefab_entity = Entity::from_raw(id);
+            if self.entity_map.get(prefab_entity).is_none() {
+                let entity = world.spawn_empty().id();
+                self.entity_map.insert(prefab_entity, entity);
+            }
+        }
+
+        Ok(Hydrating {
+            id,
+            handle: handle.clone(),
+            info: self,
+            patch,
+            entities,
+            written: 0,
+            hierarchy: Hierarchy::default(),
+            modified: false,
+        })
+    }
+}
+
+/// A queued spawn written over several maintenances, see [`PrefabSpawner::set_spawn_budget`].
+pub(super) struct Hydrating {
+    pub(super) id: Id,
+    pub(super) handle: Handle<Prefab>,
+    pub(super) info: PrefabInstanceInfo,
+    /// The patch of the instance with its patch asset, resolved against the prefab.
+    patch: Patch,
+    /// Prefab entities of the instance, in the order they are written.
+    pub(super) entities: Vec<u32>,
+    /// Number of `entities` written so far.
+    pub(super) written: usize,
+    /// Hierarchy of the written entities, attached once all of them are written.
+    hierarchy: Hierarchy,
+    /// The prefab was modified while the instance was written.
+    pub(super) modified: bool,
+}
+
+impl Hydrating {
+    fn is_done(&self) -> bool {
+        self.written == self.entities.len()
+    }
+
+    /// Write the next `count` entities of the instance.
+    fn write(&mut self, world: &mut World, count: usize) -> Result<(), PrefabError> {
+        let end = self.written.saturating_add(count).min(self.entities.len());
+        let part: HashSet<u32> = self.entities[self.written..end].iter().copied().collect();
+        let options = self.info.write_options();
+        world.resource_scope(|world, prefabs: Mut<Assets<Prefab>>| {
+            let prefab = prefabs.get(&self.handle);
+            let prefab = prefab.ok_or_else(|| PrefabError::NonExistentPrefab {
+                handle: self.handle.clone_weak(),
+            })?;
+            let entity_map = &mut self.info.entity_map;
+            let hierarchy = &mut self.hierarchy;
+            write_part(
+                &self.patch,
+                prefab,
+                world,
+                entity_map,
+                &options,
+                Some(&part),
+                hierarchy,
+            )
+        })?;
+        self.written = end;
+        Ok(())
+    }
+
+    /// Attach the written entities, once all of them are written.
+    fn finish(&mut self, world: &mut World, policy: PrefabUpdatePolicy) -> Result<(), PrefabError> {
+        let prefabs = world.resource::<Assets<Prefab>>();
+        let prefab = prefabs.get(&self.handle);
+        let prefab = prefab.ok_or_else(|| PrefabError::NonExistentPrefab {
+            handle: self.handle.clone_weak(),
+        })?;
+        let registry = world.resource::<AppTypeRegistry>().clone();
+        let registry = registry.read();
+        let owned = owned_components(prefab, &self.patch, &registry);
+
+        let info = &mut self.info;
+        std::mem::take(&mut self.hierarchy).write(world, &info.entity_map);
+        remove_placeholders(world, &mut info.entity_map, &self.patch.ignore);
+        info.written(world, owned, policy, Vec::new(), &registry);
+        Ok(())
+    }
+}
+
+impl PrefabSpawner {
+    /// Write the entities of partially written instances within the spawn budget,
+    /// oldest instance first.
+    pub(super) fn hydrate(&mut self, world: &mut World) {
+        let start = Instant::now();
+        // Entities written by this maintenance.
+        let mut written = 0;
+        while let Some(hydrating) = self.hydrating.first_mut() {
+            let count = match self.spawn_budget {
+                None => usize::MAX,
+                Some(SpawnBudget::Entities(max)) if written >= max.max(1) => break,
+                Some(SpawnBudget::Entities(max)) => max.max(1) - written,
+                Some(SpawnBudget::Time(budget)) => {
+                    let elapsed = start.elapsed();
+                    if written == 0 {
+                        FIRST_TIMED_PART
+                    } else if elapsed >= budget {
+                        break;
+                    } else {
+                        timed_count(budget, elapsed, written)
+                    }
+                }
+            };
+
+            let id = hydrating.id;
+            let span = info_span!("prefab_hydrate", path = Empty, instance = %id, entities = Empty);
+            record_path(&span, world, &hydrating.handle);
+            let _span = span.enter();
+            let before = hydrating.written;
+            let result = hydrating.write(world, count);
+            written += hydrating.written - before;
+            span.record("entities", hydrating.written - before);
+            if result.is_ok() && !hydrating.is_done() {
+                continue;
+            }
+
+            let mut hydrating = self.hydrating.remove(0);
+            let policy = self.spawned.policy(&id);
+            match result.and_then(|()| hydrating.finish(world, policy)) {
+                Ok(()) => {
+                    let handle = hydrating.handle;
+                    self.spawned.ready(world, &handle, id, hydrating.info);
+                    if hydrating.modified {
+                        self.spawned.respawn(world, &id);
+                    }
+                }
+                Err(err) => {
+                    hydrating.info.despawn(world, false);
+                    let err = report_error(world, id, &hydrating.handle, err);
+                    self.spawned.failed.insert(id, err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::timed_count;
+    use bevy::utils::Duration;
+
+    #[test]
+    fn guess_timed_count() {
+        let budget = Duration::from_secs(4);
+        assert_eq!(timed_count(budget, Duration::from_secs(1), 4), 12);
+        assert_eq!(timed_count(budget, Duration::from_millis(3500), 1), 1);
+        // A clock that saw no time pass doesn't make the count unbounded.
+        let count = timed_count(Duration::from_millis(2), Duration::ZERO, 16);
+        assert!((1..=2000).contains(&count));
+    }
+}
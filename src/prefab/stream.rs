@@ -0,0 +1,345 @@
+use super::{serde::PrefabDeserializer, Prefab, PrefabEntity};
+use bevy::reflect::{TypeRegistryArc, TypeRegistryInternal};
+use futures_lite::{AsyncRead, AsyncReadExt};
+use serde::de::DeserializeSeed;
+use std::io::Read;
+
+const CHUNK_SIZE: usize = 8 * 1024;
+
+impl Prefab {
+    /// Deserialize prefab from rust object notation (ron) read from a stream.
+    ///
+    /// Only one entity is kept in memory in its serialized form at a time.
+    /// Use a [`PrefabEntityReader`] to handle entities one by one instead of collecting them.
+    pub fn read_ron(reader: impl Read, registry: &TypeRegistryArc) -> Result<Self, ron::Error> {
+        let registry = &registry.read();
+        let entities = PrefabEntityReader::new(reader, registry).collect::<Result<_, _>>()?;
+        Ok(Self { entities })
+    }
+
+    /// Deserialize prefab from rust object notation (ron) read from an async stream.
+    ///
+    /// See [`Prefab::read_ron`].
+    pub async fn read_ron_async(
+        mut reader: impl AsyncRead + Unpin,
+        registry: &TypeRegistryArc,
+    ) -> Result<Self, ron::Error> {
+        let mut splitter = EntitySplitter::default();
+        let mut entities = Vec::new();
+        let mut chunk = vec![0; CHUNK_SIZE];
+
+        loop {
+            let len = reader.read(&mut chunk).await?;
+            if len == 0 {
+                splitter.finish()?;
+                break;
+            }
+
+            let registry = &registry.read();
+            for entry in splitter.push(&chunk[..len])? {
+                entities.push(parse_entity(&entry, registry)?);
+            }
+        }
+
+        Ok(Self { entities })
+    }
+}
+
+/// Reads a ron prefab from a stream, entity by entity.
+///
+/// ```
+/// # use bevy::reflect::TypeRegistryInternal;
+/// # use bevy_nursery::prefab::PrefabEntityReader;
+/// let registry = TypeRegistryInternal::default();
+/// let input = "{ 0: {}, 1: {} }";
+///
+/// let reader = PrefabEntityReader::new(input.as_bytes(), &registry);
+/// let ids: Vec<u32> = reader.map(|entity| entity.unwrap().entity).collect();
+/// assert_eq!(ids, [0, 1]);
+/// ```
+pub struct PrefabEntityReader<'a, R> {
+    reader: R,
+    registry: &'a TypeRegistryInternal,
+    splitter: EntitySplitter,
+    pending: std::vec::IntoIter<Vec<u8>>,
+    done: bool,
+}
+
+impl<'a, R: Read> PrefabEntityReader<'a, R> {
+    pub fn new(reader: R, registry: &'a TypeRegistryInternal) -> Self {
+        Self {
+            reader,
+            registry,
+            splitter: EntitySplitter::default(),
+            pending: Vec::new().into_iter(),
+            done: false,
+        }
+    }
+}
+
+impl<'a, R: Read> Iterator for PrefabEntityReader<'a, R> {
+    type Item = Result<PrefabEntity, ron::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut chunk = [0; CHUNK_SIZE];
+        loop {
+            if let Some(entry) = self.pending.next() {
+                return Some(parse_entity(&entry, self.registry));
+            }
+            if self.done {
+                return None;
+            }
+
+            let result = match self.reader.read(&mut chunk) {
+                Ok(0) => {
+                    self.done = true;
+                    self.splitter.finish().map(|()| Vec::new())
+                }
+                Ok(len) => self.splitter.push(&chunk[..len]),
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => Err(err.into()),
+            };
+
+            match result {
+                Ok(entries) => self.pending = entries.into_iter(),
+                Err(err) => {
+                    self.done = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+fn parse_entity(entry: &[u8], registry: &TypeRegistryInternal) -> Result<PrefabEntity, ron::Error> {
+    // An entry is parsed as a map with a single entity, so that comments and
+    // whitespace around it are handled by ron itself.
+    let mut input = Vec::with_capacity(entry.len() + 2);
+    input.push(b'{');
+    input.extend_from_slice(entry);
+    input.push(b'}');
+
+    let mut deserializer = ron::de::Deserializer::from_bytes(&input)?;
+    let prefab = PrefabDeserializer::new(registry).deserialize(&mut deserializer)?;
+    deserializer.end()?;
+
+    prefab
+        .entities
+        .into_iter()
+        .next()
+        .ok_or_else(|| ron::Error::Message(String::from("expected an entity")))
+}
+
+/// Split a complete ron map into its `key: value` entries.
+pub(super) fn split_map(input: &[u8]) -> Result<Vec<Vec<u8>>, ron::Error> {
+    let mut splitter = EntitySplitter::default();
+    let entries = splitter.push(input)?;
+    splitter.finish()?;
+    Ok(entries)
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum Scan {
+    #[default]
+    Code,
+    Slash,
+    String,
+    StringEscape,
+    Char,
+    CharEscape,
+    LineComment,
+    BlockComment,
+    BlockCommentStar,
+}
+
+/// Splits the top-level map of a ron prefab into `id: { components }` entries
+/// without parsing the entries themselves.
+#[derive(Default)]
+struct EntitySplitter {
+    buffer: Vec<u8>,
+    scan: Scan,
+    depth: usize,
+    /// Start of the current entry in `buffer`.
+    start: usize,
+    started: bool,
+    finished: bool,
+}
+
+impl EntitySplitter {
+    fn push(&mut self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, ron::Error> {
+        let mut entries = Vec::new();
+        let offset = self.buffer.len();
+        self.buffer.extend_from_slice(bytes);
+
+        for index in offset..self.buffer.len() {
+            let c = self.buffer[index];
+            self.scan = match (self.scan, c) {
+                (Scan::Slash, b'/') => Scan::LineComment,
+                (Scan::Slash, b'*') => Scan::BlockComment,
+                (Scan::LineComment, b'\n') => Scan::Code,
+                (Scan::LineComment, _) => Scan::LineComment,
+                (Scan::BlockComment, b'*') => Scan::BlockCommentStar,
+                (Scan::BlockComment, _) => Scan::BlockComment,
+                (Scan::BlockCommentStar, b'/') => Scan::Code,
+                (Scan::BlockCommentStar, b'*') => Scan::BlockCommentStar,
+                (Scan::BlockCommentStar, _) => Scan::BlockComment,
+                (Scan::String, b'\\') => Scan::StringEscape,
+                (Scan::String, b'"') => Scan::Code,
+                (Scan::String, _) | (Scan::StringEscape, _) => Scan::String,
+                (Scan::Char, b'\\') => Scan::CharEscape,
+                (Scan::Char, b'\'') => Scan::Code,
+                (Scan::Char, _) | (Scan::CharEscape, _) => Scan::Char,
+                (Scan::Code | Scan::Slash, _) => self.code(index, c, &mut entries)?,
+            };
+        }
+
+        // Drop everything that was already handed out.
+        let start = self.start.min(self.buffer.len());
+        self.buffer.drain(..start);
+        self.start -= start;
+
+        Ok(entries)
+    }
+
+    fn code(
+        &mut self,
+        index: usize,
+        c: u8,
+        entries: &mut Vec<Vec<u8>>,
+    ) -> Result<Scan, ron::Error> {
+        if self.finished && !c.is_ascii_whitespace() && c != b'/' {
+            return Err(ron::Error::TrailingCharacters);
+        }
+
+        match c {
+            b'/' => return Ok(Scan::Slash),
+            b'"' => return Ok(Scan::String),
+            b'\'' => return Ok(Scan::Char),
+            b'{' | b'(' | b'[' => {
+                if !self.started {
+                    if c != b'{' {
+                        return Err(ron::Error::ExpectedMap);
+                    }
+                    self.started = true;
+                    self.start = index + 1;
+                }
+                self.depth += 1;
+            }
+            b'}' | b')' | b']' => {
+                self.depth = self
+                    .depth
+                    .checked_sub(1)
+                    .ok_or(ron::Error::TrailingCharacters)?;
+                if self.depth == 0 {
+                    self.emit(index, entries);
+                    self.finished = true;
+                    self.start = index + 1;
+                }
+            }
+            b',' if self.depth == 1 => {
+                self.emit(index, entries);
+                self.start = index + 1;
+            }
+            c if !self.started && !c.is_ascii_whitespace() => return Err(ron::Error::ExpectedMap),
+            _ => {}
+        }
+
+        Ok(Scan::Code)
+    }
+
+    fn emit(&mut self, end: usize, entries: &mut Vec<Vec<u8>>) {
+        let entry = &self.buffer[self.start..end];
+        if entry.iter().any(|c| !c.is_ascii_whitespace()) {
+            entries.push(entry.to_vec());
+        }
+    }
+
+    fn finish(&self) -> Result<(), ron::Error> {
+        if self.finished {
+            Ok(())
+        } else {
+            Err(ron::Error::Eof)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefabEntityReader;
+    use crate::prefab::Prefab;
+    use bevy::reflect::{Reflect, TypeRegistryArc};
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Label {
+        text: String,
+        value: char,
+    }
+
+    const INPUT: &str = r#"
+        // a prefab with tricky strings
+        {
+            0: {
+                "bevy_nursery::prefab::stream::tests::Label": (text: "}, 1: {", value: '}'),
+            },
+            /* comment with } */
+            7: {},
+        }
+    "#;
+
+    struct Trickle<'a>(&'a [u8]);
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let Some((first, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = *first;
+            self.0 = rest;
+            Ok(1)
+        }
+    }
+
+    fn registry() -> TypeRegistryArc {
+        let registry = TypeRegistryArc::default();
+        registry.write().register::<Label>();
+        registry
+    }
+
+    #[test]
+    fn stream_entities() {
+        let registry = registry();
+        let registry = registry.read();
+
+        // Feed the input one byte at a time to cross every possible chunk boundary.
+        let reader = Trickle(INPUT.as_bytes());
+        let entities: Vec<_> = PrefabEntityReader::new(reader, &registry)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities[0].entity, 0);
+        assert_eq!(entities[1].entity, 7);
+        assert!(entities[0].components[0]
+            .reflect_partial_eq(&Label {
+                text: String::from("}, 1: {"),
+                value: '}',
+            })
+            .unwrap());
+    }
+
+    #[test]
+    fn read_same_as_deserialize() {
+        let registry = registry();
+
+        let streamed = Prefab::read_ron(INPUT.as_bytes(), &registry).unwrap();
+        let parsed = Prefab::deserialize_ron(INPUT.as_bytes(), &registry).unwrap();
+        assert_eq!(streamed.entities.len(), parsed.entities.len());
+
+        let input =
+            futures_lite::future::block_on(Prefab::read_ron_async(INPUT.as_bytes(), &registry));
+        assert_eq!(input.unwrap().entities.len(), parsed.entities.len());
+
+        assert!(Prefab::read_ron("{ 0: {}".as_bytes(), &registry).is_err());
+        assert!(Prefab::read_ron("{ 0: {} } x".as_bytes(), &registry).is_err());
+    }
+}
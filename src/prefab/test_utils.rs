@@ -0,0 +1,85 @@
+//! Helpers for testing prefabs without a window or a running app loop.
+//!
+//! ```
+//! # use bevy::prelude::*;
+//! # use bevy_nursery::prefab::test_utils;
+//! #[derive(Component, Reflect, Default, PartialEq, Debug)]
+//! #[reflect(Component)]
+//! struct Health(u32);
+//!
+//! let mut app = test_utils::test_app();
+//! app.register_type::<Health>();
+//!
+//! let ron = format!("{{ 0: {{ \"{}\": (10) }} }}", std::any::type_name::<Health>());
+//! let handle = test_utils::load_prefab_str(&mut app, &ron).unwrap();
+//! let instance = test_utils::spawn_sync(&mut app, &handle).unwrap();
+//!
+//! test_utils::assert_component(&app, &instance, 0, &Health(10));
+//! ```
+
+use super::{Prefab, PrefabError, PrefabInstance, PrefabPlugin, PrefabSpawner};
+use bevy::{
+    app::App,
+    asset::{AssetPlugin, Assets, Handle},
+    ecs::{component::Component, entity::Entity, reflect::AppTypeRegistry, world::Mut},
+    hierarchy::HierarchyPlugin,
+    transform::TransformPlugin,
+    MinimalPlugins,
+};
+
+/// Build a headless [`App`] with the [`PrefabPlugin`](struct@PrefabPlugin) and everything it depends on.
+pub fn test_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin::default(),
+        TransformPlugin,
+        HierarchyPlugin,
+        PrefabPlugin,
+    ));
+    app
+}
+
+/// Deserialize a prefab from a RON string and add it to the [`Assets<Prefab>`].
+pub fn load_prefab_str(app: &mut App, ron: &str) -> Result<Handle<Prefab>, ron::Error> {
+    let registry = app.world.resource::<AppTypeRegistry>().0.clone();
+    let prefab = Prefab::deserialize_ron(ron.as_bytes(), &registry)?;
+    Ok(app.world.resource_mut::<Assets<Prefab>>().add(prefab))
+}
+
+/// Spawn a prefab right away, without waiting for the next update.
+pub fn spawn_sync(app: &mut App, handle: &Handle<Prefab>) -> Result<PrefabInstance, PrefabError> {
+    app.world
+        .resource_scope(|world, mut spawner: Mut<PrefabSpawner>| spawner.spawn_sync(world, handle))
+}
+
+/// Get the world entity spawned for the prefab entity `prefab_entity` of an instance.
+///
+/// # Panics
+///
+/// Panics if the instance or the entity doesn't exist.
+pub fn instance_entity(app: &App, instance: &PrefabInstance, prefab_entity: u32) -> Entity {
+    let spawner = app.world.resource::<PrefabSpawner>();
+    let info = spawner.info(instance).expect("the instance is not spawned");
+    info.entity(prefab_entity)
+        .unwrap_or_else(|| panic!("the instance has no entity {}", prefab_entity))
+}
+
+/// Assert that the given prefab entity of an instance has a component equal to `expected`.
+#[track_caller]
+pub fn assert_component<T: Component + PartialEq + std::fmt::Debug>(
+    app: &App,
+    instance: &PrefabInstance,
+    prefab_entity: u32,
+    expected: &T,
+) {
+    let entity = instance_entity(app, instance, prefab_entity);
+    let component = app.world.get::<T>(entity);
+    assert_eq!(
+        component,
+        Some(expected),
+        "unexpected `{}` on prefab entity {}",
+        std::any::type_name::<T>(),
+        prefab_entity,
+    );
+}
@@ -0,0 +1,117 @@
+use super::{Patch, PatchEntity};
+
+/// Overrides of a prefab entity written with plain Rust types, turned into a [`Patch`].
+///
+/// Derive it for a struct of `Option<T>` fields, the fields that are `Some` are patched.
+/// A field is a component replacing the one of the entity,
+/// or a field of a component with `#[prefab_patch(component = T, path = "...")]`.
+///
+/// ```
+/// # use bevy::prelude::*;
+/// # use bevy_nursery::prefab::PrefabPatch;
+/// #[derive(Component, Reflect, Default)]
+/// #[reflect(Component)]
+/// struct Health {
+///     max: u32,
+/// }
+///
+/// #[derive(PrefabPatch, Default)]
+/// struct Boss {
+///     #[prefab_patch(component = Health, path = "max")]
+///     max_health: Option<u32>,
+///     transform: Option<Transform>,
+/// }
+///
+/// let boss = Boss {
+///     max_health: Some(500),
+///     ..default()
+/// };
+/// let patch = boss.to_patch(0);
+/// assert_eq!(patch.modify[0].modify.len(), 1);
+/// assert!(patch.modify[0].append.is_empty());
+/// ```
+pub trait PrefabPatch {
+    /// Write the overrides that are set into the patch of an entity.
+    fn patch_entity(&self, entity: &mut PatchEntity);
+
+    /// A patch of the prefab entity with this id.
+    fn to_patch(&self, entity: u32) -> Patch {
+        let mut patch = PatchEntity::new(entity);
+        self.patch_entity(&mut patch);
+        Patch {
+            modify: vec![patch],
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prefab::{write_to_world, PrefabBuilder, PrefabPatch};
+    use bevy::ecs::{
+        component::Component,
+        entity::EntityMap,
+        reflect::{AppTypeRegistry, ReflectComponent},
+        world::World,
+    };
+    use bevy::reflect::Reflect;
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Health {
+        max: u32,
+        current: u32,
+    }
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Loot(u32);
+
+    #[derive(PrefabPatch, Default)]
+    struct Overrides {
+        #[prefab_patch(component = Health, path = "max")]
+        max_health: Option<u32>,
+        loot: Option<Loot>,
+    }
+
+    #[test]
+    fn derive_patch() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Health>();
+        registry.write().register::<Loot>();
+        let mut source = World::new();
+        source.insert_resource(registry.clone());
+        let entity = source
+            .spawn((
+                Health {
+                    max: 10,
+                    current: 10,
+                },
+                Loot(1),
+            ))
+            .id();
+        let mut builder = PrefabBuilder::from_world(&source);
+        builder.extract_entities([entity].into_iter());
+        let prefab = builder.build();
+
+        let overrides = Overrides {
+            max_health: Some(50),
+            loot: Some(Loot(2)),
+        };
+        let mut world = World::new();
+        world.insert_resource(registry);
+        let mut entity_map = EntityMap::default();
+        let patch = overrides.to_patch(entity.index());
+        write_to_world(&patch, &prefab, &mut world, &mut entity_map).unwrap();
+
+        let entity = entity_map.get(entity).unwrap();
+        let health = Health {
+            max: 50,
+            current: 10,
+        };
+        assert_eq!(world.get::<Health>(entity), Some(&health));
+        assert_eq!(world.get::<Loot>(entity), Some(&Loot(2)));
+
+        assert!(Overrides::default().to_patch(0).modify[0].append.is_empty());
+    }
+}
@@ -0,0 +1,78 @@
+use super::{intern::shared_type_name, Prefab, PrefabError, ReflectPrefabComponent};
+use bevy::ecs::reflect::ReflectComponent;
+use bevy::reflect::TypeRegistryInternal;
+use std::sync::Arc;
+
+impl Prefab {
+    /// Check that every component of the prefab is a registered component,
+    /// reporting all the types that aren't in a single error.
+    ///
+    /// Serialized components are checked by type name, without deserializing them.
+    /// Spawning would otherwise fail on the first such component,
+    /// see [`PrefabLoader::with_strict`](super::PrefabLoader::with_strict).
+    pub fn validate(&self, registry: &TypeRegistryInternal) -> Result<(), PrefabError> {
+        let components = self.entities.iter().flat_map(|entity| {
+            let components = entity.components.iter().map(|c| c.type_name());
+            components.chain(entity.serialized.iter().map(|c| c.type_name()))
+        });
+
+        let mut type_names: Vec<Arc<str>> = Vec::new();
+        for type_name in components {
+            let registration = registry.get_with_name(type_name);
+            let is_component = registration.is_some_and(|registration| {
+                registration.data::<ReflectComponent>().is_some()
+                    || registration.data::<ReflectPrefabComponent>().is_some()
+            });
+            if !is_component && !type_names.iter().any(|t| &**t == type_name) {
+                type_names.push(shared_type_name(registry, type_name));
+            }
+        }
+
+        if type_names.is_empty() {
+            Ok(())
+        } else {
+            Err(PrefabError::UnregisteredComponents { type_names })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prefab::{Prefab, PrefabError};
+    use bevy::ecs::{component::Component, reflect::AppTypeRegistry, reflect::ReflectComponent};
+    use bevy::reflect::Reflect;
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component)]
+    struct Health(u32);
+
+    #[derive(Reflect, Default)]
+    struct NotComponent;
+
+    #[test]
+    fn report_all_unregistered() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Health>();
+        registry.write().register::<NotComponent>();
+
+        let input = format!(
+            "{{ 0: {{ {0:?}: (1), {1:?}: () }}, 1: {{ {1:?}: (), \"game::Missing\": () }} }}",
+            std::any::type_name::<Health>(),
+            std::any::type_name::<NotComponent>(),
+        );
+        let prefab = Prefab::deserialize_ron_lazy(input.as_bytes()).unwrap();
+        let err = prefab.validate(&registry.read()).unwrap_err();
+        let PrefabError::UnregisteredComponents { type_names } = err else {
+            panic!("expected unregistered components");
+        };
+        let type_names: Vec<&str> = type_names.iter().map(AsRef::as_ref).collect();
+        assert_eq!(
+            type_names,
+            [std::any::type_name::<NotComponent>(), "game::Missing"]
+        );
+
+        let input = format!("{{ 0: {{ {:?}: (1) }} }}", std::any::type_name::<Health>());
+        let prefab = Prefab::deserialize_ron(input.as_bytes(), &registry.0).unwrap();
+        assert!(prefab.validate(&registry.read()).is_ok());
+    }
+}
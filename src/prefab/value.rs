@@ -0,0 +1,336 @@
+use serde::Deserialize;
+use std::fmt;
+
+/// A value in rust object notation (ron), as seen by [`PrefabMigration`](super::PrefabMigration)s.
+///
+/// Unlike [`ron::Value`], it keeps the names of structs and enum variants,
+/// so `Visible` or `Rect(x: 1)` are written back as they were read.
+/// Numbers are kept as written.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrefabValue {
+    Bool(bool),
+    /// A number as written, like `1`, `-0.5` or `0xff`.
+    Number(String),
+    Char(char),
+    String(String),
+    /// `()`.
+    Unit,
+    /// A unit struct or a unit enum variant, like `Visible` or `None`.
+    Ident(String),
+    /// A tuple, a tuple struct or a tuple enum variant, like `(1, 2)` or `Some(1)`.
+    Tuple(Option<String>, Vec<PrefabValue>),
+    /// A struct or a struct enum variant, like `(x: 1)` or `Rect(x: 1)`.
+    Struct(Option<String>, Vec<(String, PrefabValue)>),
+    Seq(Vec<PrefabValue>),
+    /// A map, with its entries in the order they were written.
+    Map(Vec<(PrefabValue, PrefabValue)>),
+}
+
+impl PrefabValue {
+    /// Parse a value from rust object notation (ron).
+    pub fn from_ron(input: &[u8]) -> Result<Self, ron::Error> {
+        let mut parser = Parser::new(std::str::from_utf8(input)?);
+        let value = parser.value()?;
+        parser.skip_ws()?;
+        if !parser.rest().is_empty() {
+            return Err(ron::Error::TrailingCharacters);
+        }
+        Ok(value)
+    }
+
+    /// The field `name` of a struct, or the entry of a map keyed by the string `name`,
+    /// like a component of an entity.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut PrefabValue> {
+        match self {
+            Self::Struct(_, fields) => fields
+                .iter_mut()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value),
+            Self::Map(entries) => entries
+                .iter_mut()
+                .find(|(key, _)| matches!(key, Self::String(key) if key == name))
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PrefabValue {
+    /// Write the value back as rust object notation (ron).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn list<T>(
+            f: &mut fmt::Formatter<'_>,
+            items: &[T],
+            item: impl Fn(&mut fmt::Formatter<'_>, &T) -> fmt::Result,
+        ) -> fmt::Result {
+            for (index, value) in items.iter().enumerate() {
+                if index > 0 {
+                    f.write_str(", ")?;
+                }
+                item(f, value)?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Number(value) | Self::Ident(value) => f.write_str(value),
+            Self::Char(value) => write!(f, "'{}'", value.escape_debug()),
+            Self::String(value) => write!(f, "\"{}\"", value.escape_debug()),
+            Self::Unit => f.write_str("()"),
+            Self::Tuple(name, items) => {
+                f.write_str(name.as_deref().unwrap_or_default())?;
+                f.write_str("(")?;
+                list(f, items, |f, value| write!(f, "{}", value))?;
+                f.write_str(")")
+            }
+            Self::Struct(name, fields) => {
+                f.write_str(name.as_deref().unwrap_or_default())?;
+                f.write_str("(")?;
+                list(f, fields, |f, (field, value)| {
+                    write!(f, "{}: {}", field, value)
+                })?;
+                f.write_str(")")
+            }
+            Self::Seq(items) => {
+                f.write_str("[")?;
+                list(f, items, |f, value| write!(f, "{}", value))?;
+                f.write_str("]")
+            }
+            Self::Map(entries) => {
+                f.write_str("{")?;
+                list(f, entries, |f, (key, value)| {
+                    write!(f, "{}: {}", key, value)
+                })?;
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// A small ron parser that doesn't need to know the types of the values.
+///
+/// Strings, chars and numbers are left to ron itself.
+pub(super) struct Parser<'a> {
+    input: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    pub(super) fn new(input: &'a str) -> Self {
+        Self { input }
+    }
+
+    /// The input that isn't parsed yet.
+    pub(super) fn rest(&self) -> &'a str {
+        self.input
+    }
+
+    /// Skip whitespace and comments.
+    pub(super) fn skip_ws(&mut self) -> Result<(), ron::Error> {
+        loop {
+            self.input = self.input.trim_start();
+            if let Some(rest) = self.input.strip_prefix("//") {
+                self.input = rest.find('\n').map_or("", |end| &rest[end..]);
+            } else if self.input.starts_with("/*") {
+                // Block comments nest.
+                let bytes = self.input.as_bytes();
+                let (mut index, mut level) = (0, 0);
+                loop {
+                    if index >= bytes.len() {
+                        return Err(ron::Error::UnclosedBlockComment);
+                    } else if bytes[index..].starts_with(b"/*") {
+                        level += 1;
+                        index += 2;
+                    } else if bytes[index..].starts_with(b"*/") {
+                        level -= 1;
+                        index += 2;
+                        if level == 0 {
+                            break;
+                        }
+                    } else {
+                        index += 1;
+                    }
+                }
+                self.input = &self.input[index..];
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Consume `token` if it comes next.
+    pub(super) fn consume(&mut self, token: char) -> Result<bool, ron::Error> {
+        self.skip_ws()?;
+        let rest = self.input.strip_prefix(token);
+        self.input = rest.unwrap_or(self.input);
+        Ok(rest.is_some())
+    }
+
+    pub(super) fn expect(&mut self, token: char, err: ron::Error) -> Result<(), ron::Error> {
+        if self.consume(token)? {
+            Ok(())
+        } else {
+            Err(err)
+        }
+    }
+
+    /// Consume an identifier, like `Visible` or `r#type`, if it comes next.
+    pub(super) fn ident(&mut self) -> Result<Option<&'a str>, ron::Error> {
+        self.skip_ws()?;
+        let raw = self.input.starts_with("r#");
+        let start = if raw { 2 } else { 0 };
+        let len = self.input[start..]
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || raw && "+-.".contains(c)))
+            .unwrap_or(self.input.len() - start);
+        if len == 0 || self.input[start..].starts_with(|c: char| c.is_ascii_digit()) {
+            return Ok(None);
+        }
+        let (ident, rest) = self.input.split_at(start + len);
+        self.input = rest;
+        Ok(Some(ident))
+    }
+
+    /// Deserialize a value of type `T` with ron.
+    pub(super) fn parse<T: Deserialize<'a>>(&mut self) -> Result<T, ron::Error> {
+        self.skip_ws()?;
+        let mut deserializer = ron::de::Deserializer::from_str(self.input)?;
+        let value = T::deserialize(&mut deserializer)?;
+        let parsed = self.input.len() - deserializer.remainder().len();
+        self.input = &self.input[parsed..];
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<PrefabValue, ron::Error> {
+        self.skip_ws()?;
+        let raw_string = self.input.strip_prefix('r');
+        let raw_string =
+            raw_string.is_some_and(|rest| rest.trim_start_matches('#').starts_with('"'));
+        match self.input.chars().next() {
+            None => Err(ron::Error::Eof),
+            Some('"') => self.parse().map(PrefabValue::String),
+            Some(_) if raw_string => self.parse().map(PrefabValue::String),
+            Some('\'') => self.parse().map(PrefabValue::Char),
+            Some('0'..='9' | '+' | '-' | '.') => {
+                let input = self.input;
+                self.parse::<serde::de::IgnoredAny>()?;
+                let number = &input[..input.len() - self.input.len()];
+                Ok(PrefabValue::Number(number.trim_end().to_string()))
+            }
+            Some('[') => {
+                self.input = &self.input[1..];
+                let items = self.list(']', Self::value)?;
+                Ok(PrefabValue::Seq(items))
+            }
+            Some('{') => {
+                self.input = &self.input[1..];
+                let entries = self.list('}', |parser| {
+                    let key = parser.value()?;
+                    parser.expect(':', ron::Error::ExpectedMapColon)?;
+                    Ok((key, parser.value()?))
+                })?;
+                Ok(PrefabValue::Map(entries))
+            }
+            Some('(') => self.struct_like(None),
+            Some(c) => match self.ident()?.ok_or(ron::Error::UnexpectedByte(c))? {
+                "true" => Ok(PrefabValue::Bool(true)),
+                "false" => Ok(PrefabValue::Bool(false)),
+                name => {
+                    self.skip_ws()?;
+                    if self.input.starts_with('(') {
+                        self.struct_like(Some(name.to_string()))
+                    } else {
+                        Ok(PrefabValue::Ident(name.to_string()))
+                    }
+                }
+            },
+        }
+    }
+
+    /// Parse a tuple or a struct, after its name if it has one.
+    fn struct_like(&mut self, name: Option<String>) -> Result<PrefabValue, ron::Error> {
+        self.expect('(', ron::Error::ExpectedStructLike)?;
+        if name.is_none() && self.consume(')')? {
+            return Ok(PrefabValue::Unit);
+        }
+
+        // Structs start with a field name followed by a colon.
+        let start = self.input;
+        let is_struct = self.ident()?.is_some() && {
+            self.skip_ws()?;
+            self.input.starts_with(':') && !self.input.starts_with("::")
+        };
+        self.input = start;
+
+        if is_struct {
+            let fields = self.list(')', |parser| {
+                let field = parser.ident()?.ok_or(ron::Error::ExpectedIdentifier)?;
+                parser.expect(':', ron::Error::ExpectedMapColon)?;
+                Ok((field.to_string(), parser.value()?))
+            })?;
+            Ok(PrefabValue::Struct(name, fields))
+        } else {
+            let items = self.list(')', Self::value)?;
+            Ok(PrefabValue::Tuple(name, items))
+        }
+    }
+
+    /// Parse comma separated items up to `end`, the opening token is already consumed.
+    fn list<T>(
+        &mut self,
+        end: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, ron::Error>,
+    ) -> Result<Vec<T>, ron::Error> {
+        let mut items = Vec::new();
+        while !self.consume(end)? {
+            items.push(item(self)?);
+            if !self.consume(',')? {
+                self.expect(end, ron::Error::ExpectedComma)?;
+                break;
+            }
+        }
+        Ok(items)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefabValue;
+
+    #[test]
+    fn parse_and_write() {
+        let input = r##"{
+            // Comments /* are */ skipped.
+            0: { "a::Mood": Angry, /* /* nested */ */ "a::Size": (1.5, -2), },
+            1: { "a::Label": (text: "say \"hi\"\n", raw: r#"r"aw"#, c: '\''), "a::Opt": Some(Rect(x: 31)) },
+            2: { "a::List": [true, false, ()], "a::Empty": {} },
+        }"##;
+        let value = PrefabValue::from_ron(input.as_bytes()).unwrap();
+
+        let PrefabValue::Map(entities) = &value else {
+            panic!("expected a map");
+        };
+        assert_eq!(entities.len(), 3);
+        let mood = &entities[0].1;
+        let PrefabValue::Map(components) = mood else {
+            panic!("expected a map");
+        };
+        assert_eq!(components[0].1, PrefabValue::Ident(String::from("Angry")));
+
+        let written = value.to_string();
+        assert_eq!(
+            written,
+            "{0: {\"a::Mood\": Angry, \"a::Size\": (1.5, -2)}, \
+            1: {\"a::Label\": (text: \"say \\\"hi\\\"\\n\", raw: \"r\\\"aw\", c: '\\''), \
+            \"a::Opt\": Some(Rect(x: 31))}, \
+            2: {\"a::List\": [true, false, ()], \"a::Empty\": {}}}"
+        );
+        assert_eq!(PrefabValue::from_ron(written.as_bytes()).unwrap(), value);
+
+        let mut value = PrefabValue::from_ron(b"{ \"a::Label\": (text: \"a\") }").unwrap();
+        let text = value.get_mut("a::Label").unwrap().get_mut("text").unwrap();
+        *text = PrefabValue::String(String::from("b"));
+        assert_eq!(value.to_string(), "{\"a::Label\": (text: \"b\")}");
+        assert!(PrefabValue::from_ron(b"{ 0: (x: 1 }").is_err());
+        assert!(PrefabValue::from_ron(b"(1) 2").is_err());
+    }
+}
@@ -0,0 +1,321 @@
+use super::{
+    asset::load_error, clone_compact, path::wrong_path, serde::PrefabVariantDeserializer, Patch,
+    PatchEntity, PatchParent, Prefab, PrefabEntity, PrefabError, PrefabLoader,
+};
+use bevy::{
+    asset::{AssetLoader, AssetPath, BoxedFuture, Error, LoadContext, LoadedAsset},
+    ecs::entity::Entity,
+    ecs::world::{FromWorld, World},
+    hierarchy::Parent,
+    log::info_span,
+    reflect::{
+        DynamicTupleStruct, FromReflect, GetPath, Reflect, TypePath, TypeRegistryArc,
+        TypeRegistryInternal, TypeUuid,
+    },
+    utils::{HashMap, Instant},
+};
+use std::path::Path;
+
+/// A prefab made of a base prefab with a patch applied on top of it.
+///
+/// Variant files, like `goblin_boss.variant.ron`, name their base by its asset path
+/// and embed a patch, see [`PatchDeserializer`](super::PatchDeserializer):
+///
+/// ```ron
+/// (
+///     base: "goblin.prefab",
+///     patch: (
+///         entities: { 0: (modify: { "game::Health": { "max": 500 } }) },
+///     ),
+/// )
+/// ```
+///
+/// The [`PrefabVariantLoader`] resolves the file into a standalone [`Prefab`],
+/// spawned like any other prefab. The variant itself is the `Variant` labeled asset of the file.
+#[derive(Clone, TypeUuid, TypePath)]
+#[uuid = "3f6d1c2a-94b7-4e05-8a1d-c7e2b5f08a36"]
+pub struct PrefabVariant {
+    /// Asset path of the base prefab, which may be a variant itself.
+    pub base: String,
+    pub patch: Patch,
+}
+
+impl PrefabVariant {
+    /// Deserialize a variant from rust object notation (ron).
+    pub fn deserialize_ron(input: &[u8], registry: &TypeRegistryArc) -> Result<Self, ron::Error> {
+        let registry = &registry.read();
+        serde::de::DeserializeSeed::deserialize(
+            PrefabVariantDeserializer::new(registry),
+            &mut ron::de::Deserializer::from_bytes(input)?,
+        )
+    }
+}
+
+impl Prefab {
+    /// This prefab with `patch` applied, the same entities spawning it with the patch would give.
+    ///
+    /// Serialized components are deserialized.
+    /// Reparented entities get a new [`Parent`], the [`Children`](bevy::hierarchy::Children)
+    /// of their previous parent are left as they are, as spawning only reads them for the order.
+    pub fn patched(
+        &self,
+        patch: &Patch,
+        registry: &TypeRegistryInternal,
+    ) -> Result<Prefab, PrefabError> {
+        let patch = &*patch.resolve(self);
+        let mut patch_map: HashMap<u32, &PatchEntity> =
+            patch.modify.iter().map(|e| (e.entity, e)).collect();
+
+        let mut entities = Vec::with_capacity(self.entities.len());
+        for prefab_entity in &self.entities {
+            if patch.ignore.contains(&prefab_entity.entity) {
+                continue;
+            }
+            let patch = patch_map.remove(&prefab_entity.entity);
+            let serialized = prefab_entity.serialized.iter();
+            let serialized = serialized.map(|component| component.materialize(registry));
+            let serialized = serialized.collect::<Result<Vec<_>, _>>()?;
+            let components = prefab_entity.components.iter().map(AsRef::as_ref);
+            let components = components.chain(serialized).collect();
+            let entity = patch_entity(prefab_entity.entity, components, patch, registry);
+            let entity = entity.map_err(|err| err.in_entity(prefab_entity.entity, None))?;
+            entities.push(entity);
+        }
+
+        // Patches of entities that aren't in the prefab add them, in the order of the patch.
+        for patch in &patch.modify {
+            if patch_map.remove(&patch.entity).is_some() {
+                let entity = patch_entity(patch.entity, Vec::new(), Some(patch), registry);
+                let entity = entity.map_err(|err| err.in_entity(patch.entity, None))?;
+                entities.push(entity);
+            }
+        }
+
+        Ok(Prefab { entities })
+    }
+}
+
+/// Apply a patch to the components of an entity, like [`write_to_world`](super::write_to_world).
+fn patch_entity(
+    entity: u32,
+    components: Vec<&dyn Reflect>,
+    patch: Option<&PatchEntity>,
+    registry: &TypeRegistryInternal,
+) -> Result<PrefabEntity, PrefabError> {
+    let append = patch.map(|p| p.append.iter()).into_iter().flatten();
+    let components = components.into_iter().chain(append.map(AsRef::as_ref));
+
+    let mut patched = Vec::new();
+    for component in components {
+        let type_name = component.type_name();
+        let mut component = clone_compact(component, registry);
+        let info = component.get_represented_type_info();
+        if let Some(patch) = patch {
+            if patch.remove.contains(type_name) {
+                continue;
+            }
+            for (path, value) in patch.modify.get(type_name).into_iter().flatten() {
+                let field = component.reflect_path_mut(path);
+                let field = field.map_err(|err| wrong_path(info, path, err, registry))?;
+                field.apply(value.as_ref());
+            }
+        }
+        patched.push(component);
+    }
+
+    if let Some(parent) = patch.and_then(|patch| patch.parent) {
+        let type_name = std::any::type_name::<Parent>();
+        patched.retain(|component| component.type_name() != type_name);
+        if let PatchParent::Entity(parent) = parent {
+            let mut value = DynamicTupleStruct::default();
+            value.insert(Entity::from_raw(parent));
+            // `Parent` can't be built directly, its field is private.
+            patched.push(Box::new(Parent::from_reflect(&value).unwrap()));
+        }
+    }
+
+    Ok(PrefabEntity::new(entity, patched))
+}
+
+/// Loads [`PrefabVariant`] files as the [`Prefab`] they resolve to.
+///
+/// The base prefab is read, not loaded as an asset of its own,
+/// and goes through the [`PrefabLoader`] before the patch is applied.
+/// The resolved prefab then goes through the [`PrefabLoaderHooks`](super::PrefabLoaderHooks).
+/// A modified base is seen as a modification of the variant when hot reloading.
+pub struct PrefabVariantLoader {
+    prefabs: PrefabLoader,
+}
+
+impl FromWorld for PrefabVariantLoader {
+    fn from_world(world: &mut World) -> Self {
+        Self {
+            prefabs: PrefabLoader::from_world(world),
+        }
+    }
+}
+
+impl PrefabVariantLoader {
+    /// Fail loading variants that resolve to prefabs with unregistered components,
+    /// see [`PrefabLoader::with_strict`].
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.prefabs = self.prefabs.with_strict(strict);
+        self
+    }
+}
+
+/// File extensions loaded by the [`PrefabVariantLoader`].
+pub(super) const VARIANT_EXTENSIONS: &[&str] = &["variant", "variant.ron"];
+
+fn is_variant(path: &Path) -> bool {
+    let name = path.file_name().and_then(|name| name.to_str());
+    name.is_some_and(|name| has_extension(name, VARIANT_EXTENSIONS))
+}
+
+/// Does the file `name` end with one of the (possibly compound) `extensions`?
+pub(super) fn has_extension(name: &str, extensions: &[&str]) -> bool {
+    extensions.iter().any(|extension| {
+        name.strip_suffix(extension)
+            .is_some_and(|name| name.ends_with('.'))
+    })
+}
+
+impl AssetLoader for PrefabVariantLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let start = Instant::now();
+            let registry = self.prefabs.registry();
+            let variant = PrefabVariant::deserialize_ron(bytes, registry);
+            let variant = variant.map_err(|err| load_error(load_context.path(), err))?;
+            let mut variants = vec![variant];
+            let mut visited = vec![load_context.path().to_path_buf()];
+            let mut read = bytes.len();
+
+            // Follow the chain of variants down to a prefab.
+            let prefab = loop {
+                let base = variants.last().unwrap().base.as_str();
+                let base = AssetPath::from(base).to_owned();
+                let path = base.path().to_path_buf();
+                if visited.contains(&path) {
+                    let message = format!("prefab variant `{}` is its own base", path.display());
+                    return Err(Error::msg(message));
+                }
+
+                let bytes = load_context.read_asset_bytes(&path).await?;
+                read += bytes.len();
+                if is_variant(&path) {
+                    let variant = PrefabVariant::deserialize_ron(&bytes, registry);
+                    variants.push(variant.map_err(|err| load_error(&path, err))?);
+                    visited.push(path);
+                    continue;
+                }
+
+                break match base.label() {
+                    Some(label) => {
+                        let prefabs = self.prefabs.read_labeled(&bytes);
+                        let prefabs = prefabs.map_err(|err| load_error(&path, err))?;
+                        let prefab = prefabs.into_iter().find(|(l, _)| l == label);
+                        let message = || format!("`{}` has no prefab `{}`", path.display(), label);
+                        prefab.ok_or_else(|| Error::msg(message()))?.1
+                    }
+                    None => {
+                        let prefab = self.prefabs.read(&bytes, &path);
+                        prefab.map_err(|err| load_error(&path, err))?
+                    }
+                };
+            };
+
+            let path = load_context.path().display().to_string();
+            let _span = info_span!("prefab_variant_load", path).entered();
+            let mut prefab = prefab;
+            {
+                let registry = registry.read();
+                for variant in variants.iter().rev() {
+                    let patched = prefab.patched(&variant.patch, &registry);
+                    prefab = patched.map_err(|err| load_error(load_context.path(), err))?;
+                }
+            }
+
+            let asset = self
+                .prefabs
+                .finish(prefab, load_context, None, start, read)?;
+            load_context.set_default_asset(asset);
+            let variant = variants.swap_remove(0);
+            load_context.set_labeled_asset("Variant", LoadedAsset::new(variant));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        VARIANT_EXTENSIONS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefabVariant;
+    use crate::prefab::Prefab;
+    use bevy::ecs::reflect::AppTypeRegistry;
+    use bevy::reflect::Reflect;
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Health {
+        max: u32,
+        current: u32,
+    }
+
+    #[derive(Reflect, Default, PartialEq, Debug)]
+    struct Loot(u32);
+
+    #[test]
+    fn resolve_variant() {
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Health>();
+        registry.write().register::<Loot>();
+
+        let health = std::any::type_name::<Health>();
+        let loot = std::any::type_name::<Loot>();
+        let base = format!(
+            "{{ 0: {{ {0:?}: (max: 10, current: 10), {1:?}: (1) }}, 1: {{ {1:?}: (2) }} }}",
+            health, loot
+        );
+        let base = Prefab::deserialize_ron(base.as_bytes(), &registry.0).unwrap();
+
+        let variant = format!(
+            "(
+                base: \"goblin.prefab\",
+                patch: (
+                    ignore: [1],
+                    entities: {{
+                        0: (modify: {{ {0:?}: {{ \"max\": 50 }} }}, remove: [{1:?}]),
+                        2: (append: {{ {1:?}: (3) }}),
+                    }},
+                ),
+            )",
+            health, loot
+        );
+        let variant = PrefabVariant::deserialize_ron(variant.as_bytes(), &registry.0).unwrap();
+        assert_eq!(variant.base, "goblin.prefab");
+
+        let prefab = base.patched(&variant.patch, &registry.read()).unwrap();
+        let ids: Vec<u32> = prefab.entities.iter().map(|e| e.entity).collect();
+        assert_eq!(ids, [0, 2]);
+        let components = &prefab.entities[0].components;
+        assert_eq!(components.len(), 1);
+        let expected = Health {
+            max: 50,
+            current: 10,
+        };
+        assert!(components[0].reflect_partial_eq(&expected).unwrap());
+        let components = &prefab.entities[1].components;
+        assert!(components[0].reflect_partial_eq(&Loot(3)).unwrap());
+
+        let unknown =
+            "(base: \"goblin.prefab\", patch: (entities: { 0: (modify: { \"Nope\": {} }) }))";
+        assert!(PrefabVariant::deserialize_ron(unknown.as_bytes(), &registry.0).is_err());
+    }
+}
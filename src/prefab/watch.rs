@@ -0,0 +1,219 @@
+use super::{
+    asset::PREFAB_EXTENSIONS,
+    variant::{has_extension, VARIANT_EXTENSIONS},
+    Prefab, PrefabInstance, PrefabSpawner,
+};
+use bevy::{
+    app::{App, Plugin, Update},
+    asset::{AssetServer, FileAssetIo, Handle},
+    ecs::system::{Res, ResMut, Resource},
+    time::{Time, Timer, TimerMode},
+    utils::{tracing::warn, HashMap, HashSet},
+};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Development helper that spawns every prefab file dropped into a directory.
+///
+/// The directory is polled every `interval`. A new prefab or variant file is loaded and spawned,
+/// a deleted one has its instance despawned.
+/// Changes to existing files are picked up by the regular prefab hot reload,
+/// as long as asset watching is enabled on the `AssetPlugin`.
+///
+/// Only works when assets are read from files.
+pub struct PrefabWatchPlugin {
+    /// The directory to watch, relative to the asset folder of the `AssetPlugin`.
+    pub directory: String,
+    pub interval: Duration,
+}
+
+impl Default for PrefabWatchPlugin {
+    fn default() -> Self {
+        Self {
+            directory: String::from("prefabs"),
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl Plugin for PrefabWatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PrefabWatcher {
+            directory: PathBuf::from(&self.directory),
+            timer: Timer::new(self.interval, TimerMode::Repeating),
+            spawned: HashMap::default(),
+            warned: false,
+        })
+        .add_systems(Update, prefab_watch_system);
+    }
+}
+
+/// State of the [`PrefabWatchPlugin`].
+#[derive(Resource)]
+pub struct PrefabWatcher {
+    directory: PathBuf,
+    timer: Timer,
+    spawned: HashMap<PathBuf, (Handle<Prefab>, PrefabInstance)>,
+    /// Set once a failed scan was reported, until a scan succeeds again.
+    warned: bool,
+}
+
+impl PrefabWatcher {
+    /// Get the instance spawned for a file, by its path relative to the asset folder.
+    pub fn instance(&self, path: impl AsRef<Path>) -> Option<&PrefabInstance> {
+        self.spawned
+            .get(path.as_ref())
+            .map(|(_, instance)| instance)
+    }
+
+    /// Spawn the prefabs of new files and despawn the instances of deleted ones.
+    fn poll(&mut self, asset_server: &AssetServer, spawner: &mut PrefabSpawner) {
+        let root = asset_server
+            .asset_io()
+            .downcast_ref::<FileAssetIo>()
+            .map(FileAssetIo::root_path);
+        let files = match root {
+            Some(root) => self.scan(root),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "assets are not read from files",
+            )),
+        };
+        let files = match files {
+            Ok(files) => {
+                self.warned = false;
+                files
+            }
+            Err(err) => {
+                // A missing directory is reported once, not on every poll.
+                if !self.warned {
+                    let path = self.directory.display();
+                    warn!("can't watch prefab directory {}: {}", path, err);
+                    self.warned = true;
+                }
+                return;
+            }
+        };
+
+        self.spawned.retain(|path, (_, instance)| {
+            let keep = files.contains(path);
+            if !keep {
+                spawner.despawn(instance);
+            }
+            keep
+        });
+
+        for path in files {
+            if !self.spawned.contains_key(&path) {
+                let handle: Handle<Prefab> = asset_server.load(path.as_path());
+                let instance = spawner.spawn(handle.clone(), None);
+                self.spawned.insert(path, (handle, instance));
+            }
+        }
+    }
+
+    fn scan(&self, root: &Path) -> io::Result<HashSet<PathBuf>> {
+        let mut files = HashSet::default();
+        for entry in std::fs::read_dir(root.join(&self.directory))? {
+            let entry = entry?;
+            let name = entry.file_name();
+            let is_prefab = name.to_str().is_some_and(|name| {
+                has_extension(name, PREFAB_EXTENSIONS) || has_extension(name, VARIANT_EXTENSIONS)
+            });
+            if is_prefab && entry.file_type()?.is_file() {
+                files.insert(self.directory.join(name));
+            }
+        }
+        Ok(files)
+    }
+}
+
+fn prefab_watch_system(
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut watcher: ResMut<PrefabWatcher>,
+    mut spawner: ResMut<PrefabSpawner>,
+) {
+    if watcher.timer.tick(time.delta()).just_finished() {
+        watcher.poll(&asset_server, &mut spawner);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PrefabWatchPlugin, PrefabWatcher};
+    use crate::prefab::{PrefabPlugin, PrefabSpawner};
+    use bevy::{
+        asset::{AssetPlugin, AssetServer},
+        ecs::world::Mut,
+        hierarchy::HierarchyPlugin,
+        prelude::{App, MinimalPlugins},
+        transform::TransformPlugin,
+    };
+    use std::path::Path;
+
+    fn poll(app: &mut App) {
+        let asset_server = app.world.resource::<AssetServer>().clone();
+        app.world
+            .resource_scope(|world, mut watcher: Mut<PrefabWatcher>| {
+                let mut spawner = world.resource_mut::<PrefabSpawner>();
+                watcher.poll(&asset_server, &mut spawner);
+            });
+    }
+
+    #[test]
+    fn watch_directory() {
+        let dir = std::env::temp_dir().join(format!("prefab_watch_{}", std::process::id()));
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin {
+                asset_folder: dir.to_string_lossy().into_owned(),
+                ..Default::default()
+            },
+            TransformPlugin,
+            HierarchyPlugin,
+            PrefabPlugin::default(),
+            PrefabWatchPlugin::default(),
+        ));
+
+        // The directory doesn't exist yet.
+        poll(&mut app);
+        assert!(app.world.resource::<PrefabWatcher>().warned);
+
+        let prefabs = dir.join("prefabs");
+        std::fs::create_dir_all(prefabs.join("nested.prefab")).unwrap();
+        let files = [
+            "a.prefab",
+            "b.prefab.ron",
+            "c.prefab.json",
+            "d.prefab.yaml",
+            "e.variant.ron",
+            "f.ron",
+            "notaprefab",
+        ];
+        for file in files {
+            std::fs::write(prefabs.join(file), "{}").unwrap();
+        }
+        poll(&mut app);
+        let watcher = app.world.resource::<PrefabWatcher>();
+        assert!(!watcher.warned);
+        let path = |file: &str| Path::new("prefabs").join(file);
+        for &file in &files[..5] {
+            assert!(watcher.instance(path(file)).is_some(), "{}", file);
+        }
+        assert!(watcher.instance(path("f.ron")).is_none());
+        assert!(watcher.instance(path("notaprefab")).is_none());
+        assert!(watcher.instance(path("nested.prefab")).is_none());
+
+        std::fs::remove_file(prefabs.join("c.prefab.json")).unwrap();
+        poll(&mut app);
+        let watcher = app.world.resource::<PrefabWatcher>();
+        assert!(watcher.instance(path("c.prefab.json")).is_none());
+        assert!(watcher.instance(path("b.prefab.ron")).is_some());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,1004 @@
+use super::{
+    clone_compact, intern_type_name, path::wrong_path, Patch, PatchEntity, PatchParent, Prefab,
+    PrefabEntity, PrefabError, ReflectPrefabComponent,
+};
+use bevy::{
+    asset::{Assets, Handle},
+    core::Name,
+    ecs::component::ComponentId,
+    ecs::entity::{Entity, EntityMap},
+    ecs::reflect::{AppTypeRegistry, ReflectComponent, ReflectMapEntities},
+    ecs::system::{Command, Commands},
+    ecs::world::{EntityMut, Mut, World},
+    hierarchy::{BuildWorldChildren, Children, Parent},
+    log::info_span,
+    ptr::OwningPtr,
+    reflect::{
+        GetPath, Reflect, ReflectFromReflect, ReflectRef, TypeRegistration, TypeRegistryInternal,
+    },
+    tasks::{ComputeTaskPool, TaskPool},
+    utils::{HashMap, HashSet},
+};
+use std::{alloc::Layout, any::TypeId, ptr::NonNull, sync::Arc};
+
+/// Prefabs with fewer entities are prepared on the calling thread.
+const PARALLEL_THRESHOLD: usize = 64;
+
+/// Options for [`write_to_world_with`].
+#[derive(Debug, Clone)]
+pub struct WriteOptions {
+    /// Give the written entities a [`Name`] starting with this prefix, like `goblin#3`.
+    ///
+    /// The prefix is followed by the name of the prefab entity, or by its id if it has none:
+    /// `goblin#3/weapon` or `goblin#3/1`.
+    /// Entities that already have a name keep it, unless the prefab entity has one.
+    pub name_prefix: Option<String>,
+    /// Insert the components missing from an entity together, moving it to its archetype once.
+    ///
+    /// Otherwise components are inserted one at a time.
+    /// Components of a type the world doesn't know yet and
+    /// components without [`ReflectFromReflect`] are always inserted one at a time.
+    pub batch_inserts: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            name_prefix: None,
+            batch_inserts: true,
+        }
+    }
+}
+
+/// Write a prefab with a patch applied to the world.
+///
+/// Component values are prepared in parallel on the [`ComputeTaskPool`]:
+/// lazy components are deserialized, patches are applied and registrations are resolved.
+/// Only inserting the prepared values into the world is done serially.
+/// Nothing is written to the world if preparing any of the components fails,
+/// or if an entity of `entity_map` doesn't exist anymore.
+///
+/// [`Parent`] and [`Children`] are not written as is, the hierarchy is rebuilt from them
+/// once all entities exist: children come first in their parent's [`Children`],
+/// in the order of the prefab, before children added to the parent at runtime.
+///
+/// Patched entities with a [`PatchEntity::uuid`] or a [`PatchEntity::name`]
+/// target the prefab entity with that uuid or [`Name`].
+pub fn write_to_world(
+    patch: &Patch,
+    prefab: &Prefab,
+    world: &mut World,
+    entity_map: &mut EntityMap,
+) -> Result<(), PrefabError> {
+    write_to_world_with(patch, prefab, world, entity_map, &WriteOptions::default())
+}
+
+/// Write a prefab with a patch applied to the world, see [`write_to_world`].
+pub fn write_to_world_with(
+    patch: &Patch,
+    prefab: &Prefab,
+    world: &mut World,
+    entity_map: &mut EntityMap,
+    options: &WriteOptions,
+) -> Result<(), PrefabError> {
+    let _span = info_span!(
+        "write_to_world",
+        entities = prefab.entities.len(),
+        patched = patch.modify.len(),
+        ignored = patch.ignore.len(),
+    )
+    .entered();
+
+    let mut hierarchy = Hierarchy::default();
+    write_part(
+        patch,
+        prefab,
+        world,
+        entity_map,
+        options,
+        None,
+        &mut hierarchy,
+    )?;
+    info_span!("hierarchy").in_scope(|| hierarchy.write(world, entity_map));
+    Ok(())
+}
+
+/// Write the entities of a prefab in `part`, or all of them, without attaching them.
+///
+/// The hierarchy read from the written entities is added to `hierarchy`,
+/// to be written once all the parts are, so that children keep the order of the prefab.
+/// Entities written by other parts have to be in `entity_map` already,
+/// for references to them to be mapped.
+pub(crate) fn write_part(
+    patch: &Patch,
+    prefab: &Prefab,
+    world: &mut World,
+    entity_map: &mut EntityMap,
+    options: &WriteOptions,
+    part: Option<&HashSet<u32>>,
+    hierarchy: &mut Hierarchy,
+) -> Result<(), PrefabError> {
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let registry = registry.read();
+
+    let patch = &*patch.resolve(prefab);
+    let mut patch_map: HashMap<_, _> = patch.modify.iter().map(|e| (e.entity, e)).collect();
+
+    let mut work = Vec::with_capacity(prefab.entities.len());
+    for prefab_entity in &prefab.entities {
+        // ignore despawned entities
+        if !patch.ignore.contains(&prefab_entity.entity) {
+            let patch = patch_map.remove(&prefab_entity.entity);
+            work.push(Work::Entity(prefab_entity, patch));
+        }
+    }
+    work.extend(patch_map.into_values().map(Work::Append));
+    if let Some(part) = part {
+        work.retain(|work| part.contains(&work.entity()));
+    }
+
+    for work in &work {
+        let entity = entity_map.get(Entity::from_raw(work.entity()));
+        if entity.is_some_and(|entity| world.get_entity(entity).is_none()) {
+            return Err(PrefabError::MissingEntity {
+                entity: work.entity(),
+            });
+        }
+    }
+    let prepared = info_span!("prepare").in_scope(|| prepare(&work, &registry))?;
+
+    // For each component types that reference other entities, we keep track
+    // of which entities in the scene use that component.
+    // This is so we can update the scene-internal references to references
+    // of the actual entities in the world.
+    let mut scene_mappings: HashMap<TypeId, Vec<Entity>> = HashMap::default();
+
+    let insert_span = info_span!("insert", entities = work.len()).entered();
+    for (work, prepared) in work.iter().zip(prepared) {
+        let (prefab_entity, components, patch) = work.components();
+        for &component in &components {
+            let removed = patch.is_some_and(|p| p.remove.contains(component.type_name()));
+            if !removed {
+                hierarchy.read(prefab_entity, component);
+            }
+        }
+        if let Some(parent) = work.patch().and_then(|patch| patch.parent) {
+            hierarchy.reparent(prefab_entity, parent);
+        }
+
+        // Fetch the entity with the given entity id from the `entity_map`
+        let entity = entity_map.entry(Entity::from_raw(prefab_entity));
+        // or spawn a new entity with a transiently unique id if there is no corresponding entry.
+        let entity = *entity.or_insert_with(|| world.spawn_empty().id());
+        let mut entity = world.entity_mut(entity);
+        let mut named = false;
+        let mut batch = InsertBatch::default();
+
+        // Apply/ add each component to the given entity.
+        for PreparedComponent {
+            index,
+            mut patched,
+            type_id,
+        } in prepared
+        {
+            let registration = registry.get(type_id).unwrap();
+            named |= type_id == TypeId::of::<Name>();
+
+            if let Some(proxy) = registration.data::<ReflectPrefabComponent>() {
+                // Proxies may read the components written before them.
+                batch.insert(&mut entity);
+                let component = patched.as_deref().unwrap_or(components[index]);
+                proxy.apply_insert(&mut entity, component);
+                continue;
+            }
+
+            // Checked when preparing the component.
+            let reflect = registration.data::<ReflectComponent>().unwrap();
+
+            // If this component references entities in the scene, track it
+            // so we can update it to the entity in the world.
+            if registration.data::<ReflectMapEntities>().is_some() {
+                scene_mappings
+                    .entry(registration.type_id())
+                    .or_default()
+                    .push(entity.id());
+            }
+
+            // If the entity already has the given component attached,
+            // just apply the (possibly) new value.
+            if entity.contains_type_id(type_id) {
+                let component = patched.as_deref().unwrap_or(components[index]);
+                reflect.apply(&mut entity, component);
+                continue;
+            }
+
+            // Otherwise add the component to the entity, with the other missing ones if possible.
+            let id = entity.world().components().get_id(type_id);
+            let id = id.filter(|_| options.batch_inserts);
+            let value = id.and_then(|id| {
+                let value = concrete_value(&mut patched, components[index], registration)?;
+                Some((id, value))
+            });
+            match value {
+                Some((id, value)) => batch.push(id, value),
+                None => {
+                    let component = patched.as_deref().unwrap_or(components[index]);
+                    reflect.insert(&mut entity, component);
+                }
+            }
+        }
+        batch.insert(&mut entity);
+
+        if let Some(prefix) = &options.name_prefix {
+            let name = if named {
+                entity.get::<Name>().map(|name| name.as_str().to_owned())
+            } else if !entity.contains::<Name>() {
+                Some(prefab_entity.to_string())
+            } else {
+                None
+            };
+            if let Some(name) = name {
+                entity.insert(Name::new(format!("{}/{}", prefix, name)));
+            }
+        }
+    }
+
+    drop(insert_span);
+
+    // Updates references to entities in the scene to entities in the world
+    let _span = info_span!("map_entities").entered();
+    for (type_id, entities) in scene_mappings.into_iter() {
+        let registration = registry
+            .get(type_id)
+            .expect("we should be getting TypeId from this TypeRegistration in the first place");
+        if let Some(map_entities_reflect) = registration.data::<ReflectMapEntities>() {
+            map_entities_reflect.map_entities(world, entity_map, &entities);
+        }
+    }
+
+    Ok(())
+}
+
+/// Write a prefab with a patch applied to the world from [`Commands`], see [`write_to_world`].
+///
+/// Lets normal systems write prefabs, instead of only exclusive systems.
+/// The entities of the prefab are reserved right away and returned by prefab entity id,
+/// so other commands can refer to them.
+/// The prefab is written when the commands are applied, errors are logged then
+/// and the reserved entities are despawned.
+pub fn write_to_commands(
+    commands: &mut Commands,
+    prefabs: &Assets<Prefab>,
+    handle: &Handle<Prefab>,
+    patch: &Patch,
+    options: &WriteOptions,
+) -> Result<EntityMap, PrefabError> {
+    let prefab = prefabs.get(handle);
+    let prefab = prefab.ok_or_else(|| PrefabError::NonExistentPrefab {
+        handle: handle.clone_weak(),
+    })?;
+
+    let resolved = patch.resolve(prefab);
+    let ids = prefab.entities.iter().map(|entity| entity.entity);
+    let ids = ids.filter(|id| !resolved.ignore.contains(id));
+    let ids = ids.chain(resolved.modify.iter().map(|entity| entity.entity));
+
+    let mut entity_map = EntityMap::default();
+    let mut reserved = EntityMap::default();
+    for id in ids {
+        if entity_map.get(Entity::from_raw(id)).is_none() {
+            let entity = commands.spawn_empty().id();
+            entity_map.insert(Entity::from_raw(id), entity);
+            reserved.insert(Entity::from_raw(id), entity);
+        }
+    }
+
+    commands.add(WritePrefab {
+        handle: handle.clone(),
+        patch: patch.clone(),
+        options: options.clone(),
+        entity_map: reserved,
+    });
+    Ok(entity_map)
+}
+
+/// The command queued by [`write_to_commands`].
+struct WritePrefab {
+    handle: Handle<Prefab>,
+    patch: Patch,
+    options: WriteOptions,
+    entity_map: EntityMap,
+}
+
+impl Command for WritePrefab {
+    fn apply(mut self, world: &mut World) {
+        let result = world.resource_scope(|world, prefabs: Mut<Assets<Prefab>>| {
+            let prefab = prefabs.get(&self.handle);
+            let prefab = prefab.ok_or_else(|| PrefabError::NonExistentPrefab {
+                handle: self.handle.clone_weak(),
+            })?;
+            let entity_map = &mut self.entity_map;
+            write_to_world_with(&self.patch, prefab, world, entity_map, &self.options)
+        });
+        if let Err(err) = result {
+            bevy::log::error!("{}", err);
+            // Nothing was written to the reserved entities, they would stay empty.
+            for entity in self.entity_map.values() {
+                world.despawn(entity);
+            }
+        }
+    }
+}
+
+/// Whether a component is part of the hierarchy, which is rebuilt instead of written as is.
+pub(crate) fn is_hierarchy(type_name: &str) -> bool {
+    type_name == std::any::type_name::<Parent>() || type_name == std::any::type_name::<Children>()
+}
+
+/// Remove a component written by a prefab, through its proxy if it has one,
+/// like it was inserted.
+pub(crate) fn remove_component(
+    entity: &mut EntityMut,
+    type_name: &str,
+    registry: &TypeRegistryInternal,
+) {
+    let Some(registration) = registry.get_with_name(type_name) else {
+        return;
+    };
+    if let Some(proxy) = registration.data::<ReflectPrefabComponent>() {
+        proxy.apply_remove(entity);
+    } else if let Some(reflect) = registration.data::<ReflectComponent>() {
+        reflect.remove(entity);
+    }
+}
+
+/// The hierarchy of the written entities, by prefab entity ids.
+#[derive(Default)]
+pub(crate) struct Hierarchy {
+    parents: Vec<(u32, u32)>,
+    /// Order of the children of an entity, from its [`Children`].
+    order: HashMap<u32, Vec<u32>>,
+    /// Entities detached by a patch, see [`PatchParent::Root`].
+    detached: Vec<u32>,
+}
+
+impl Hierarchy {
+    fn read(&mut self, prefab_entity: u32, component: &dyn Reflect) {
+        let ReflectRef::TupleStruct(value) = component.reflect_ref() else {
+            return;
+        };
+        let Some(field) = value.field(0) else {
+            return;
+        };
+        let id = |value: &dyn Reflect| value.downcast_ref::<Entity>().map(|e| e.index());
+
+        let type_name = component.type_name();
+        if type_name == std::any::type_name::<Parent>() {
+            if let Some(parent) = id(field) {
+                self.parents.push((prefab_entity, parent));
+            }
+        } else if type_name == std::any::type_name::<Children>() {
+            if let ReflectRef::List(children) = field.reflect_ref() {
+                let children = children.iter().filter_map(id);
+                self.order.insert(prefab_entity, children.collect());
+            }
+        }
+    }
+
+    /// Override the parent of an entity, see [`PatchEntity::parent`].
+    fn reparent(&mut self, prefab_entity: u32, parent: PatchParent) {
+        self.parents.retain(|&(child, _)| child != prefab_entity);
+        match parent {
+            PatchParent::Entity(parent) => self.parents.push((prefab_entity, parent)),
+            PatchParent::Root => self.detached.push(prefab_entity),
+        }
+    }
+
+    /// Attach the children to their parents, if they are both in `entity_map`.
+    ///
+    /// Detached entities are detached from the entities of `entity_map`,
+    /// as they may have been attached by an earlier write.
+    pub(crate) fn write(self, world: &mut World, entity_map: &EntityMap) {
+        for id in &self.detached {
+            let Some(entity) = entity_map.get(Entity::from_raw(*id)) else {
+                continue;
+            };
+            let Some(mut entity) = world.get_entity_mut(entity) else {
+                continue;
+            };
+            let parent = entity.get::<Parent>().map(Parent::get);
+            if parent.is_some_and(|parent| entity_map.values().any(|e| e == parent)) {
+                entity.remove_parent();
+            }
+        }
+
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::default();
+        for (child, parent) in self.parents {
+            children.entry(parent).or_default().push(child);
+        }
+        let mut children: Vec<_> = children.into_iter().collect();
+        children.sort_unstable_by_key(|&(parent, _)| parent);
+
+        for (parent, mut ids) in children {
+            let order = self.order.get(&parent);
+            ids.sort_unstable_by_key(|child| {
+                let index = order.and_then(|order| order.iter().position(|c| c == child));
+                (index.unwrap_or(usize::MAX), *child)
+            });
+
+            let lookup = |id: u32| entity_map.get(Entity::from_raw(id));
+            let Some(parent) = lookup(parent) else {
+                continue;
+            };
+            let ids: Vec<Entity> = ids.into_iter().filter_map(lookup).collect();
+            let Some(mut parent) = world.get_entity_mut(parent) else {
+                continue;
+            };
+            let current = parent.get::<Children>();
+            if !current.is_some_and(|current| current.starts_with(&ids)) {
+                parent.insert_children(0, &ids);
+            }
+        }
+    }
+}
+
+/// What has to be written for one entity.
+enum Work<'a> {
+    /// An entity of the prefab, with its patch.
+    Entity(&'a PrefabEntity, Option<&'a PatchEntity>),
+    /// A patch for an entity that isn't in the prefab, only its appended components are used.
+    Append(&'a PatchEntity),
+}
+
+impl<'a> Work<'a> {
+    /// Deserialize the lazy components of the entity on first spawn.
+    ///
+    /// Done when the entity is prepared, so that writing it can't fail.
+    fn materialize(&self, registry: &TypeRegistryInternal) -> Result<(), PrefabError> {
+        if let Work::Entity(prefab_entity, _) = *self {
+            for component in &prefab_entity.serialized {
+                component.materialize(registry)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The prefab entity, its components and the patch applied to them.
+    ///
+    /// Lazy components have to be materialized first, see [`Work::materialize`].
+    fn components(&self) -> (u32, Vec<&'a dyn Reflect>, Option<&'a PatchEntity>) {
+        match *self {
+            Work::Entity(prefab_entity, patch) => {
+                let serialized = prefab_entity.serialized.iter().map(|component| {
+                    let value = component.materialized();
+                    value.expect("lazy components are materialized when prepared")
+                });
+
+                // Combine components
+                let append = patch.map(|p| p.append.iter()).into_iter().flatten();
+                let components = prefab_entity.components.iter().map(AsRef::as_ref);
+                let components = components
+                    .chain(serialized)
+                    .chain(append.map(AsRef::as_ref));
+
+                (prefab_entity.entity, components.collect(), patch)
+            }
+            Work::Append(patch) => {
+                let components = patch.append.iter().map(AsRef::as_ref);
+                (patch.entity, components.collect(), None)
+            }
+        }
+    }
+
+    /// The prefab entity id.
+    fn entity(&self) -> u32 {
+        match *self {
+            Work::Entity(prefab_entity, _) => prefab_entity.entity,
+            Work::Append(patch) => patch.entity,
+        }
+    }
+
+    /// The [`Name`] of the prefab entity, if it has one that is deserialized.
+    fn name(&self) -> Option<String> {
+        let components = match *self {
+            Work::Entity(prefab_entity, _) => &prefab_entity.components,
+            Work::Append(patch) => &patch.append,
+        };
+        let name = components.iter().find_map(|c| c.downcast_ref::<Name>());
+        name.map(|name| name.as_str().to_owned())
+    }
+
+    /// The patch of the entity, also for entities that aren't in the prefab.
+    fn patch(&self) -> Option<&'a PatchEntity> {
+        match *self {
+            Work::Entity(_, patch) => patch,
+            Work::Append(patch) => Some(patch),
+        }
+    }
+}
+
+/// Components inserted into an entity together, see [`WriteOptions::batch_inserts`].
+#[derive(Default)]
+struct InsertBatch {
+    ids: Vec<ComponentId>,
+    /// Values of the concrete types of the components of `ids`.
+    values: Vec<Box<dyn Reflect>>,
+}
+
+impl InsertBatch {
+    /// Add a component value, replacing the value of the same component if it has one.
+    fn push(&mut self, id: ComponentId, value: Box<dyn Reflect>) {
+        match self.ids.iter().position(|&batched| batched == id) {
+            Some(index) => self.values[index] = value,
+            None => {
+                self.ids.push(id);
+                self.values.push(value);
+            }
+        }
+    }
+
+    /// Insert the batched components into an entity that has none of them, emptying the batch.
+    fn insert(&mut self, entity: &mut EntityMut) {
+        if self.ids.is_empty() {
+            return;
+        }
+
+        let values: Vec<(NonNull<u8>, Layout)> = (self.values.drain(..))
+            .map(|value| {
+                let layout = Layout::for_value(value.as_ref());
+                let value = NonNull::new(Box::into_raw(value)).unwrap();
+                (value.cast::<u8>(), layout)
+            })
+            .collect();
+        // SAFETY: the values have the concrete types of the components of `ids`,
+        // which were checked when they were batched, and are moved into the entity.
+        unsafe {
+            let values = values.iter().map(|&(value, _)| OwningPtr::new(value));
+            entity.insert_by_ids(&self.ids, values);
+        }
+        for (value, layout) in values {
+            if layout.size() != 0 {
+                // SAFETY: the memory was allocated by the box with this layout,
+                // and the value was moved out of it without being dropped.
+                unsafe { std::alloc::dealloc(value.as_ptr(), layout) };
+            }
+        }
+        self.ids.clear();
+    }
+}
+
+/// A prepared component as a value of its concrete type, taking the patched value if it is one.
+fn concrete_value(
+    patched: &mut Option<Box<dyn Reflect>>,
+    component: &dyn Reflect,
+    registration: &TypeRegistration,
+) -> Option<Box<dyn Reflect>> {
+    let type_id = registration.type_id();
+    if patched
+        .as_ref()
+        .is_some_and(|value| value.as_any().type_id() == type_id)
+    {
+        return patched.take();
+    }
+    let component = patched.as_deref().unwrap_or(component);
+    let value = registration
+        .data::<ReflectFromReflect>()?
+        .from_reflect(component)?;
+    (value.as_any().type_id() == type_id).then_some(value)
+}
+
+/// A component ready to be inserted.
+struct PreparedComponent {
+    /// Index of the component in [`Work::components`].
+    index: usize,
+    /// The patched value, if the component is patched.
+    patched: Option<Box<dyn Reflect>>,
+    type_id: TypeId,
+}
+
+fn prepare(
+    work: &[Work],
+    registry: &TypeRegistryInternal,
+) -> Result<Vec<Vec<PreparedComponent>>, PrefabError> {
+    if work.len() < PARALLEL_THRESHOLD {
+        return work
+            .iter()
+            .map(|work| prepare_entity(work, registry))
+            .collect();
+    }
+
+    let pool = ComputeTaskPool::init(TaskPool::default);
+    let chunk_size = (work.len() / pool.thread_num().max(1)).max(1);
+    let chunks = pool.scope(|scope| {
+        for chunk in work.chunks(chunk_size) {
+            scope.spawn(async move {
+                let prepared = chunk.iter().map(|work| prepare_entity(work, registry));
+                prepared.collect::<Result<Vec<_>, _>>()
+            });
+        }
+    });
+
+    let mut prepared = Vec::with_capacity(work.len());
+    for chunk in chunks {
+        prepared.extend(chunk?);
+    }
+    Ok(prepared)
+}
+
+fn prepare_entity(
+    work: &Work,
+    registry: &TypeRegistryInternal,
+) -> Result<Vec<PreparedComponent>, PrefabError> {
+    prepare_components(work, registry).map_err(|err| err.in_entity(work.entity(), work.name()))
+}
+
+fn prepare_components(
+    work: &Work,
+    registry: &TypeRegistryInternal,
+) -> Result<Vec<PreparedComponent>, PrefabError> {
+    work.materialize(registry)?;
+    let (_, components, patch) = work.components();
+
+    let mut prepared = Vec::with_capacity(components.len());
+    for (index, component) in components.into_iter().enumerate() {
+        let type_name = component.type_name();
+
+        // The hierarchy is rebuilt once all entities are written.
+        if is_hierarchy(type_name) {
+            continue;
+        }
+
+        let mut patched = None;
+        if let Some(patch) = patch {
+            // ignore removed components
+            if patch.remove.contains(type_name) {
+                continue;
+            }
+
+            // patch component fields
+            if let Some(modify) = patch.modify.get(type_name) {
+                let mut clone = clone_compact(component, registry);
+                let info = clone.get_represented_type_info();
+
+                for (path, value) in modify {
+                    let field = clone.reflect_path_mut(path);
+                    let field = field.map_err(|err| wrong_path(info, path, err, registry))?;
+                    field.apply(value.as_ref());
+                }
+
+                patched = Some(clone);
+            }
+        }
+
+        let registration = registration_of(registry, patched.as_deref().unwrap_or(component))?;
+
+        let is_component = registration.data::<ReflectPrefabComponent>().is_some()
+            || registration.data::<ReflectComponent>().is_some();
+        if !is_component {
+            return Err(PrefabError::UnregisteredComponent {
+                type_name: intern_type_name(registration),
+            });
+        }
+
+        prepared.push(PreparedComponent {
+            index,
+            patched,
+            type_id: registration.type_id(),
+        });
+    }
+
+    Ok(prepared)
+}
+
+/// Find the registration of a reflected value.
+///
+/// Values that know their represented type are looked up by [`TypeId`],
+/// which avoids hashing and comparing the type name.
+fn registration_of<'r>(
+    registry: &'r TypeRegistryInternal,
+    value: &dyn Reflect,
+) -> Result<&'r TypeRegistration, PrefabError> {
+    let registration = match value.get_represented_type_info() {
+        Some(info) => registry.get(info.type_id()),
+        None => registry.get_with_name(value.type_name()),
+    };
+    registration.ok_or_else(|| PrefabError::UnregisteredType {
+        type_name: Arc::from(value.type_name()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_to_commands, write_to_world, write_to_world_with, WriteOptions};
+    use crate::prefab::{
+        test_utils, Patch, PatchEntity, PatchParent, Prefab, PrefabBuilder, PrefabEntity,
+        PrefabError,
+    };
+    use bevy::asset::Assets;
+    use bevy::core::Name;
+    use bevy::ecs::{
+        component::Component,
+        entity::{Entity, EntityMap},
+        reflect::AppTypeRegistry,
+        reflect::ReflectComponent,
+        system::{CommandQueue, Commands},
+        world::World,
+    };
+    use bevy::hierarchy::{BuildWorldChildren, Children, Parent};
+    use bevy::reflect::Reflect;
+    use bevy::utils::HashMap;
+
+    #[derive(Component, Reflect, Default, PartialEq, Debug)]
+    #[reflect(Component)]
+    struct Index(u32);
+
+    #[derive(Reflect, Default)]
+    struct NotComponent;
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<Index>();
+        registry.write().register::<NotComponent>();
+        registry.write().register::<Name>();
+        world.insert_resource(registry);
+        world
+    }
+
+    fn prefab(len: u32) -> Prefab {
+        let entities =
+            (0..len).map(|entity| PrefabEntity::new(entity, vec![Box::new(Index(entity))]));
+        Prefab {
+            entities: entities.collect(),
+        }
+    }
+
+    #[test]
+    fn write_in_parallel() {
+        let mut world = world();
+        let mut patch = Patch::default();
+        patch.ignore.insert(1);
+        patch.modify.push(PatchEntity {
+            entity: 500,
+            uuid: None,
+            name: None,
+            append: Vec::new(),
+            modify: HashMap::from_iter([(
+                std::any::type_name::<Index>().to_string(),
+                HashMap::from_iter([(String::from(".0"), Box::new(7u32) as Box<dyn Reflect>)]),
+            )]),
+            remove: Default::default(),
+            parent: None,
+        });
+
+        let mut entity_map = EntityMap::default();
+        write_to_world(&patch, &prefab(1000), &mut world, &mut entity_map).unwrap();
+
+        assert_eq!(entity_map.len(), 999);
+        let mut query = world.query::<&Index>();
+        assert_eq!(query.iter(&world).count(), 999);
+        let index = |entity| {
+            let entity = entity_map.get(Entity::from_raw(entity))?;
+            world.get::<Index>(entity)
+        };
+        assert_eq!(index(0), Some(&Index(0)));
+        assert_eq!(index(1), None);
+        assert_eq!(index(999), Some(&Index(999)));
+        assert_eq!(index(500), Some(&Index(7)));
+    }
+
+    #[test]
+    fn batch_inserts() {
+        let mut world = world();
+        let entities = (0..2).map(|entity| {
+            PrefabEntity::new(
+                entity,
+                vec![
+                    Index(entity).clone_value(),
+                    Box::new(Name::new("batched")),
+                    Index(9).clone_value(),
+                ],
+            )
+        });
+        let prefab = Prefab {
+            entities: entities.collect(),
+        };
+
+        // The first entity inserts components unknown to the world, the second one batches them.
+        for batch_inserts in [true, false] {
+            let options = WriteOptions {
+                batch_inserts,
+                ..Default::default()
+            };
+            let mut entity_map = EntityMap::default();
+            let patch = Patch::default();
+            write_to_world_with(&patch, &prefab, &mut world, &mut entity_map, &options).unwrap();
+            for entity in entity_map.values() {
+                assert_eq!(world.get::<Index>(entity), Some(&Index(9)));
+                let name = world.get::<Name>(entity).map(Name::as_str);
+                assert_eq!(name, Some("batched"));
+            }
+        }
+    }
+
+    #[test]
+    fn nothing_written_on_error() {
+        let mut world = world();
+        let mut prefab = prefab(1000);
+        prefab.entities[900].components.push(Box::new(NotComponent));
+
+        let mut entity_map = EntityMap::default();
+        let err = write_to_world(&Patch::default(), &prefab, &mut world, &mut entity_map);
+        let err = err.unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            PrefabError::UnregisteredComponent { .. }
+        ));
+        assert_eq!(err.context().unwrap().entity, Some(900));
+        assert!(err.to_string().ends_with("(in entity 900)"));
+        assert_eq!(world.entities().len(), 0);
+
+        // Entities mapped to despawned ones fail before anything is written.
+        let despawned = world.spawn_empty().id();
+        world.despawn(despawned);
+        let mut entity_map = EntityMap::default();
+        entity_map.insert(Entity::from_raw(1), despawned);
+        let result = write_to_world(&Patch::default(), &prefab, &mut world, &mut entity_map);
+        assert!(matches!(
+            result,
+            Err(PrefabError::MissingEntity { entity: 1 })
+        ));
+        assert_eq!(world.entities().len(), 0);
+        assert_eq!(entity_map.len(), 1);
+    }
+
+    #[test]
+    fn wrong_path_suggestions() {
+        let mut world = world();
+        let mut patch = Patch::default();
+        let mut entity = PatchEntity::new(0);
+        entity.set_field::<Index>(".1", &7u32);
+        patch.modify.push(entity);
+
+        let mut entity_map = EntityMap::default();
+        let result = write_to_world(&patch, &prefab(1), &mut world, &mut entity_map);
+        let err = result.unwrap_err();
+        let PrefabError::PatchContainsWrongPath { path, valid, .. } = err.kind() else {
+            panic!("expected a wrong path");
+        };
+        assert_eq!(path, ".1");
+        assert_eq!(*valid, ["0"]);
+    }
+
+    #[test]
+    fn name_entities() {
+        let mut world = world();
+        let mut prefab = prefab(2);
+        prefab.entities[1]
+            .components
+            .push(Box::new(Name::new("weapon")));
+        let options = WriteOptions {
+            name_prefix: Some(String::from("goblin#3")),
+            ..Default::default()
+        };
+
+        let mut entity_map = EntityMap::default();
+        for _ in 0..2 {
+            write_to_world_with(
+                &Patch::default(),
+                &prefab,
+                &mut world,
+                &mut entity_map,
+                &options,
+            )
+            .unwrap();
+        }
+
+        let name = |entity| {
+            let entity = entity_map.get(Entity::from_raw(entity)).unwrap();
+            world.get::<Name>(entity).map(Name::as_str)
+        };
+        assert_eq!(name(0), Some("goblin#3/0"));
+        assert_eq!(name(1), Some("goblin#3/weapon"));
+    }
+
+    #[test]
+    fn rebuild_hierarchy() {
+        let mut source = world();
+        let root = source.spawn(Index(0)).id();
+        let first = source.spawn(Index(1)).id();
+        let second = source.spawn(Index(2)).id();
+        source.entity_mut(root).push_children(&[second, first]);
+        let mut builder = PrefabBuilder::from_world(&source);
+        builder.extract_entities([root, first, second].into_iter());
+        let prefab = builder.build();
+
+        let mut world = world();
+        let mut entity_map = EntityMap::default();
+        write_to_world(&Patch::default(), &prefab, &mut world, &mut entity_map).unwrap();
+        let runtime = world.spawn_empty().id();
+        world
+            .entity_mut(entity_map.get(root).unwrap())
+            .add_child(runtime);
+
+        // Writing again, like on hot reload, keeps children added at runtime.
+        write_to_world(&Patch::default(), &prefab, &mut world, &mut entity_map).unwrap();
+        let get = |entity: Entity| entity_map.get(entity).unwrap();
+        let children = world.get::<Children>(get(root)).unwrap();
+        assert_eq!(&children[..], [get(second), get(first), runtime]);
+        assert_eq!(world.get::<Parent>(get(first)).unwrap().get(), get(root));
+    }
+
+    #[test]
+    fn reparent_with_patch() {
+        let mut source = world();
+        let root = source.spawn(Index(0)).id();
+        let first = source.spawn(Index(1)).id();
+        let second = source.spawn(Index(2)).id();
+        source.entity_mut(root).push_children(&[first, second]);
+        let mut builder = PrefabBuilder::from_world(&source);
+        builder.extract_entities([root, first, second].into_iter());
+        let prefab = builder.build();
+
+        let mut world = world();
+        let mut entity_map = EntityMap::default();
+        write_to_world(&Patch::default(), &prefab, &mut world, &mut entity_map).unwrap();
+
+        // Move the second entity under the first one, and detach the first one.
+        let mut patch = Patch::default();
+        for (entity, parent) in [
+            (second, PatchParent::Entity(first.index())),
+            (first, PatchParent::Root),
+        ] {
+            patch.modify.push(PatchEntity {
+                entity: entity.index(),
+                uuid: None,
+                name: None,
+                append: Vec::new(),
+                modify: HashMap::default(),
+                remove: Default::default(),
+                parent: Some(parent),
+            });
+        }
+        write_to_world(&patch, &prefab, &mut world, &mut entity_map).unwrap();
+
+        let get = |entity: Entity| entity_map.get(entity).unwrap();
+        assert!(world.get::<Parent>(get(first)).is_none());
+        assert_eq!(world.get::<Parent>(get(second)).unwrap().get(), get(first));
+        assert!(world.get::<Children>(get(root)).is_none());
+    }
+
+    #[test]
+    fn write_from_commands() {
+        let mut app = test_utils::test_app();
+        app.register_type::<Index>();
+        let handle = app.world.resource_mut::<Assets<Prefab>>().add(prefab(2));
+        let mut patch = Patch::default();
+        patch.ignore.insert(1);
+
+        let mut queue = CommandQueue::default();
+        let prefabs = app.world.resource::<Assets<Prefab>>();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        let options = WriteOptions::default();
+        let entity_map = write_to_commands(&mut commands, prefabs, &handle, &patch, &options);
+        let entity_map = entity_map.unwrap();
+        queue.apply(&mut app.world);
+
+        let entity = entity_map.get(Entity::from_raw(0)).unwrap();
+        assert_eq!(app.world.get::<Index>(entity), Some(&Index(0)));
+        assert!(entity_map.get(Entity::from_raw(1)).is_none());
+        assert_eq!(app.world.query::<&Index>().iter(&app.world).count(), 1);
+
+        // The reserved entities are despawned when the write fails.
+        let mut entity = PatchEntity::new(0);
+        entity.set_field::<Index>(".1", &7u32);
+        patch.modify.push(entity);
+        let len = app.world.entities().len();
+        let prefabs = app.world.resource::<Assets<Prefab>>();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        let entity_map = write_to_commands(&mut commands, prefabs, &handle, &patch, &options);
+        assert_eq!(entity_map.unwrap().len(), 1);
+        queue.apply(&mut app.world);
+        assert_eq!(app.world.entities().len(), len);
+    }
+}